@@ -9,14 +9,27 @@ no-applications-found = No applications found
 
 popover-menu = Popover Menu
     .view-in-files = View in files
+    .run-now = Run now
+    .history = Launch history
+    .edit = Edit
+    .edit-script = Edit script
+    .convert-to-service = Run as systemd service
+    .convert-system-to-service = Run as systemd service, hiding this entry with one of the same name in ~/.config/autostart
 
 actions = Actions
     .yes = Yes
     .no = No
     .add = Add
+    .save = Save
+    .cancel = Cancel
+    .close = Close
 
 dialog-remove-application = Are you sure?
     .body = Are you sure you want to remove this application?
+    .delete-script = Also delete its script {$script}
+
+dialog-delete-script = Delete this script?
+    .body = {$script} will be deleted permanently.
 
 user-applications = User specific applications
     .description = These applications load whenever you log in to your current user.
@@ -24,7 +37,272 @@ user-applications = User specific applications
 system-applications = System wide applications
     .description = These applications load whenever you log in to any user.
 
+origin = Origin
+    .distro = Package
+    .distro-tooltip = Installed by your distribution's package manager
+    .flatpak = Flatpak
+    .flatpak-tooltip = Installed as the flatpak {$appid}
+    .snap = Snap
+    .snap-tooltip = Installed as the snap {$snap}
+    .appimage = AppImage
+    .appimage-tooltip = Runs the AppImage {$path}
+    .script = Script
+    .script-tooltip = A script added with this app
+    .user-local = Local
+    .user-local-tooltip = A .desktop file in your home directory
+    .owned-by = Installed by the package {$package} ({$manager})
+
+snap-applications = Snap applications
+    .description = Snaps keep their own autostart entries, which snapd starts when you log in.
+    .started-by = Started by snapd for {$snap}
+
 script-chooser = Choose a Script
-    .sh-scripts = *sh scripts
+    .sh-scripts = Shell scripts
     .python-scripts = Python scripts
-    .all-files = All files
\ No newline at end of file
+    .all-files = All files
+
+new-script = New Script
+    .description = Scripts are saved in {$dir} and run every time you log in.
+    .name = Name
+    .no-name = The script needs a name
+    .no-shebang = The script must start with a #! line naming its interpreter, such as #!/bin/sh
+    .exists = A script or entry called {$name} already exists
+    .edit = Edit Script
+    .not-text = The script is not a text file, so it can't be edited here
+
+import-script = Add Script
+    .body = {$path} can be started from where it is, or copied into your autostart folder so that it keeps working if the original is moved or deleted.
+    .reference = Use where it is
+    .copy = Copy
+    .not-on-host = The file is only visible inside the sandbox, so it has to be copied
+    .not-executable = {$path} isn't marked as executable. It can be run through an interpreter, or copied into your autostart folder and made executable.
+    .binary-not-executable = {$path} is a program that isn't marked as executable. It can be copied into your autostart folder and made executable.
+    .no-shebang = {$path} doesn't start with a #! line naming its interpreter, so it would be run by sh. Choose the interpreter it needs.
+    .missing-interpreter = {$path} needs {$interpreter}, which isn't installed. It can be run through another interpreter, or added anyway to work once {$interpreter} is installed.
+    .interpreter = Interpreter
+    .run-with = Run with interpreter
+    .anyway = Add anyway
+    .failed = Adding Script Failed
+
+appimage = AppImage
+    .choose = Add AppImage…
+    .body = {$path} will be started every time you log in.
+    .made-executable = It will be made executable so that it can start.
+    .run-to-read = Reading the name and icon of {$path} without running it needs unsquashfs from squashfs-tools. Only run the AppImage to read them if you trust it, as it will be made executable and can run any code.
+    .run = Run AppImage
+    .chmod-failed = {$path} couldn't be made executable
+    .extract-and-run = Run without mounting
+    .extract-and-run-description = Unpacks the AppImage each time instead of mounting it, for systems without FUSE. Starts more slowly.
+    .not-appimage = The file is not an AppImage
+    .unsupported-type = Only type 2 AppImages can be added
+    .sandbox-only = The AppImage is only visible inside the sandbox, so it can't be started at login
+    .no-desktop-file = The AppImage doesn't contain a .desktop file
+    .extract-failed = The AppImage's contents couldn't be read: {$why}
+
+open-at-login = Open at Login
+    .description = Opens a file, folder or web page every time you log in, in the application set to handle it.
+    .target = File, folder or URL
+    .choose-file = Choose File…
+    .choose-folder = Choose Folder…
+    .opens-with = Opens with {$app}
+    .entry-name = Open {$target}
+    .empty = Enter a file, folder or URL to open
+    .not-absolute = Paths have to start with / or ~/
+    .not-found = {$path} doesn't exist
+    .sandbox-only = The file is only visible inside the sandbox, so it can't be opened at login
+    .no-handler = No installed application opens {$mime}
+    .exists = An entry called {$name} already exists
+
+orphaned-scripts = Scripts without an entry
+    .description = These scripts were added by this app, but nothing starts them any more.
+    .add-entry = Start at login
+
+edit-application = Edit Application
+
+launch-conditions = Launch conditions
+    .description = The application only starts at login when all of these hold. Leave a field empty to ignore it.
+    .summary = Only starts when: {$conditions}
+    .network-up = {$interface} is up
+    .hostname = hostname matches {$pattern}
+    .weekdays = weekday is {$days}
+    .time-window = time is {$window}
+    .if-exists = {$path} exists
+    .unless-exists = {$path} does not exist
+
+condition-on-ac = On AC power
+condition-network-up = Network interface is up
+condition-hostname = Hostname matches
+condition-weekdays = Weekdays
+condition-time-window = Time window
+condition-if-exists = File exists
+condition-unless-exists = File does not exist
+
+resource-limits = Resource limits
+    .description = Lower the priority of or cap background applications which slow down login. Memory and CPU limits are applied with systemd-run.
+    .summary = Limited to: {$limits}
+    .nice = Nice level (-20 to 19)
+    .io-class = I/O priority
+    .io-default = Default
+    .io-idle = Idle
+    .io-best-effort = Best effort
+    .io-level = Best effort level (0 to 7)
+    .memory-max = Maximum memory
+    .cpu-quota = CPU quota
+    .nice-label = nice {$nice}
+    .io-idle-label = idle I/O
+    .io-best-effort-label = I/O level {$level}
+    .memory-max-label = {$size} memory
+    .cpu-quota-label = {$quota}% CPU
+
+entry-environment = Environment variables
+    .description = Set only for this entry's command, such as QT_QPA_PLATFORM=wayland.
+    .name = Name
+    .value = Value
+    .add = Add variable
+
+terminal = Run in a terminal
+    .description = Opens a terminal window for commands which ask for input, such as an SSH passphrase, or show output to read.
+    .emulator = Terminal
+    .none-installed = No terminal emulator is installed, so opening one is left to the session.
+
+session-conditions = Desktop sessions
+    .description = Separate desktop names with semicolons. Known desktops: {$desktops}. Vendor specific desktops start with X-.
+    .only-show-in = Only start in
+    .not-show-in = Never start in
+    .unknown-desktop = Unknown desktop {$name}
+    .did-you-mean = Unknown desktop {$name}, did you mean {$known}?
+    .shown-and-hidden = {$desktop} can't be both in "Only start in" and "Never start in"
+
+autostart-condition = Autostart condition
+    .none = None
+    .gsettings = GSettings key is true
+    .if-exists = File in config directory exists
+    .unless-exists = File in config directory does not exist
+    .other = Other
+    .gsettings-invalid = GSettings conditions need a schema and a key, separated by a space
+    .no-value = The autostart condition needs a value
+
+simulate-login = Simulate Login
+    .description = Shows which entries would launch when logging in to the given desktops, separated by colons like $XDG_CURRENT_DESKTOP.
+    .desktops = Desktops
+    .launch = Launched
+    .skip = Skipped
+    .cli-header = Logging in to {$desktops}:
+
+skip-reason = Skipped
+    .hidden = Skipped: the entry is hidden
+    .overridden-by-user = Skipped: a user entry with the same name replaces it
+    .shadowed = Skipped: {$path} takes precedence
+    .disabled = Skipped: X-GNOME-Autostart-enabled is false
+    .not-for-desktop = Skipped: not shown in this desktop
+    .missing-binary = Skipped: {$binary} is not installed
+    .condition-not-met = Skipped: {$condition} does not hold
+
+generator-preview = Generator Preview
+    .description = Shows the unit systemd-xdg-autostart-generator would create for each entry, or why it would skip it. Sessions started by systemd launch autostart entries this way.
+    .unit = Becomes {$unit}
+
+generator-skip = Skipped
+    .shadowed = Skipped: {$path} takes precedence
+    .hidden = Skipped: the entry is hidden or X-GNOME-Autostart-enabled is false
+    .systemd-skip = Skipped: X-systemd-skip is set
+    .not-application = Skipped: only Type=Application is supported, not "{$kind}"
+    .no-exec = Skipped: the entry has no Exec line
+    .autostart-phase = Skipped: X-GNOME-Autostart-Phase={$phase} is left to gnome-session
+    .missing-try-exec = Skipped: TryExec binary {$binary} can't be found
+
+running = Running
+    .summary = Running as PID {$pid} for {$uptime}, using {$memory} MB
+    .start = Start now
+    .stop = Stop
+    .start-failed = Couldn't be started: {$why}
+    .stop-failed = Process {$pid} couldn't be stopped: {$why}
+
+run-now = Run Now
+    .running = Running…
+    .duration = Ran for {$seconds} seconds
+    .stdout = Output
+    .stderr = Errors
+    .no-output = Nothing was printed
+    .exited = Exited with status {$code}
+    .signalled = Killed by signal {$signal}
+    .still-running = Still running after 10 seconds, so it was left running
+    .not-found = {$program} could not be found
+    .permission-denied = {$program} is not executable, check that it has execute permission
+    .missing-interpreter = {$script} needs {$interpreter}, which is not installed
+    .no-command = The entry has no command
+    .failed = Could not start: {$reason}
+
+launch-history = Launch History
+    .description = The last {$count} times this entry was launched.
+    .record = Record launch history
+    .record-description = Keeps track of when the entry starts and how it exits.
+    .empty = No launches have been recorded yet. Turn on "Record launch history" in the entry's settings to track it.
+    .unfinished = Started, no exit recorded
+    .exited = Exited with status {$code} after {$duration}
+    .signalled = Killed by signal {$signal} after {$duration}
+    .skipped = Skipped: {$condition} did not hold
+    .failed = Failed to start: {$reason}
+
+login-impact = Login Impact
+    .description = How long after logging in each running autostart entry started, and the CPU time and peak memory it used in its first minute. First minute usage is only known for entries with launch history recording turned on, or while that minute is still going.
+    .scanning = Looking at running processes…
+    .no-session = The process which started this login session could not be found.
+    .empty = None of the autostart entries are running.
+    .name = Application
+    .delay = Started
+    .cpu = CPU
+    .memory = Memory
+    .pid = PID {$pid}
+
+status = Status
+    .active = Active
+    .active-tooltip = Runs when you log in to {$desktops}.
+    .not-for-desktop = Not for this desktop
+    .not-for-desktop-tooltip = This session runs {$desktops}, but the entry has OnlyShowIn={$only} and NotShowIn={$not}.
+    .overridden-by-user = Overridden by user
+    .overridden-by-user-tooltip = {$path} has the same name and is used instead of this entry.
+    .missing-binary = Missing binary
+    .missing-binary-tooltip = TryExec={$binary} can't be found, so the entry is ignored.
+    .inactive = Inactive
+
+systemd-units = Systemd user services
+    .description = These services are started by systemd when your graphical session starts. Changes apply from the next login.
+    .enabled-system-wide = {$unit}, enabled for all users
+    .runs-as-service = Disabled, runs as the systemd service {$unit}
+    .convert-to-entry = Convert to autostart entry
+    .conversion-failed = Conversion Failed
+    .update-failed = Service Not Changed
+    .enable-failed = {$unit} couldn't be enabled: {$why}
+    .disable-failed = {$unit} couldn't be disabled: {$why}
+    .to-service-failed = {$name} couldn't be converted to a systemd service: {$why}
+    .to-entry-failed = {$name} couldn't be converted to an autostart entry: {$why}
+no-units-found = No services found
+
+environment = Environment
+    .description = Variables set for your whole session by files in ~/.config/environment.d, which systemd loads when you log in. Changes apply from the next login.
+    .empty = No variables are set in ~/.config/environment.d
+    .add = Add variable
+    .edit = Edit variable
+    .file = File in ~/.config/environment.d
+    .name = Name
+    .value = Value
+    .value-description = Other variables can be used as $NAME or ${"{"}NAME{"}"}, and ${"{"}NAME:-default{"}"} gives a default for unset ones.
+    .overridden = Overridden by {$path}, which is loaded later
+    .overrides = Overrides {$value} from {$path}
+    .shadows-system = Overrides system setting
+    .errors = Problems in {$path}
+    .merged = Merged Environment
+    .merged-description = Every variable the session gets from environment.d and /etc/environment, in the order they are loaded. Files loaded later override earlier ones, and a file replaces any system file with the same name.
+    .merged-empty = No variables are set.
+    .masked = {$path} is ignored, {$user} has the same name
+
+environment-error = Invalid
+    .missing-equals = Line {$line}: expected NAME=value
+    .invalid-name = Line {$line}: "{$name}" is not a valid variable name
+    .unterminated-quote = Line {$line}: a quote is never closed
+    .invalid-key = "{$name}" is not a valid variable name, use letters, digits and underscores, not starting with a digit
+    .nul = Values can't contain NUL characters
+    .unterminated-expansion = A ${"{"} is never closed
+    .invalid-file = File names must end in .conf and can't contain slashes
+    .changed = {$path} was changed by another program, try again
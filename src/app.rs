@@ -1,10 +1,28 @@
 // SPDX-License-Identifier: GPL-3
 
 use std::cmp::PartialEq;
+use crate::appimage::{self, AppImage, Extractor};
 use crate::apps::{get_installed_applications, get_startup_applications, DirectoryType};
+use crate::desktops::{self, KNOWN_DESKTOPS};
+use crate::editor::{condition_kind, io_class_kind, EditorMessage, EntryEditor};
+use crate::entry;
+use crate::environment::{self, EnvDirs, EnvFile, MergedVariable, VariableEditor, VariableMessage};
 use crate::fl;
+use crate::generator::{self, GeneratedUnit, GeneratorOutcome};
+use crate::impact::{self, ImpactColumn, ImpactRow};
+use crate::opener::{self, Target};
+use crate::origin::{self, Origin};
+use crate::packages::{Package, PackageDbs};
+use crate::processes::{self, Process};
+use crate::runner::{self, RunOutput};
+use crate::scripts::{self, ImportMode, ScriptImport, ScriptProblem};
+use crate::session::{simulate_login, AutostartEntry, Decision, SkipReason};
+use crate::snap;
+use crate::systemd::{self, get_user_units, UnitDirs, UserUnit};
+use crate::terminal::{self, Terminal};
 use cosmic::app::{context_drawer, Core, Task};
 use cosmic::iced::alignment::{Horizontal, Vertical};
+use cosmic::iced::widget::text_editor;
 use cosmic::iced::{Alignment, Border, Color, Length, Subscription};
 use cosmic::iced_core::widget::Text;
 use cosmic::theme::Container::List;
@@ -14,13 +32,24 @@ use freedesktop_desktop_entry::DesktopEntry;
 use futures_util::{FutureExt, SinkExt};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use cosmic::dialog::file_chooser::FileFilter;
 use cosmic::widget::icon::IconFallback;
+use startup_configuration::history::{self, LaunchRecord, Outcome};
+use startup_configuration::procfs;
+use startup_configuration::launcher::{self, Condition, IoClass, LaunchOptions, ResourceLimits};
 
 //const REPOSITORY: &str = env!("CARGO_PKG_REPOSITORY");
 const APP_ICON: &[u8] = include_bytes!("../resources/icons/hicolor/scalable/apps/icon.svg");
 
+/// How many launches the history drawer shows.
+const HISTORY_LENGTH: usize = 20;
+
+/// Width of the numeric columns in the login impact table.
+const IMPACT_COLUMN_WIDTH: f32 = 96.0;
+
 /// The application model stores app-specific state used to describe its interface and
 /// drive its logic.
 pub struct AppModel {
@@ -32,6 +61,10 @@ pub struct AppModel {
 
     locales: Vec<String>,
     installed_apps: Option<Vec<DesktopEntry>>,
+    /// Where each listed entry comes from, by path
+    origins: HashMap<PathBuf, Origin>,
+    /// The distribution package owning each system entry, by path
+    packages: HashMap<PathBuf, Package>,
 
     apps_per_type: HashMap<DirectoryType, Vec<DesktopEntry>>,
 
@@ -43,6 +76,82 @@ pub struct AppModel {
     global_search_id: widget::Id,
 
     popover_item: Option<u32>,
+
+    // "New Script": the script being written, or the owned script being edited in place
+    script_name: String,
+    script_content: text_editor::Content,
+    script_error: Option<String>,
+    script_editing: Option<PathBuf>,
+    /// An AppImage being added, or why it couldn't be read
+    appimage: Option<Result<AppImage, String>>,
+    appimage_extract_and_run: bool,
+    /// An AppImage which can only be read by running it, waiting for permission to
+    appimage_run: Option<PathBuf>,
+    open_target: String,
+    /// What the typed target resolves to and the application opening it, or why it can't be used
+    open_resolved: Option<Result<(Target, DesktopEntry), String>>,
+
+    /// Scripts in the app's directories which no entry runs
+    orphaned_scripts: Vec<PathBuf>,
+    /// An orphaned script waiting for confirmation before it's deleted
+    orphan_to_delete: Option<PathBuf>,
+    /// Whether removing the selected entry deletes its script too
+    remove_script: bool,
+
+    /// A chosen script which could either be copied or run where it is on the host, with the
+    /// directory to add it to and its path as seen by the sandbox and by the host
+    script_import: Option<ScriptImport>,
+    interpreters: Vec<String>,
+    /// Why the last script couldn't be added, shown in the import dialog or on its own
+    import_error: Option<String>,
+
+    editor: Option<EntryEditor>,
+    autostart_condition_kinds: Vec<String>,
+    io_class_kinds: Vec<String>,
+    terminals: Vec<Terminal>,
+    terminal_names: Vec<String>,
+
+    // login simulation
+    simulation_desktops: String,
+    simulation: Vec<AutostartEntry>,
+    // what systemd-xdg-autostart-generator would produce
+    generator_preview: Vec<GeneratedUnit>,
+
+    /// What happens to each autostart entry when logging in to the current session
+    autostart_status: HashMap<PathBuf, Decision>,
+    session_desktops: Vec<String>,
+
+    // systemd user units
+    unit_dirs: UnitDirs,
+    units: Vec<UserUnit>,
+    /// Why converting between an autostart entry and a service failed
+    conversion_error: Option<String>,
+    /// Why enabling or disabling a service failed
+    unit_error: Option<String>,
+
+    // session environment from environment.d
+    env_dirs: EnvDirs,
+    env_files: Vec<EnvFile>,
+    env_variables: Vec<MergedVariable>,
+    variable_editor: Option<VariableEditor>,
+
+    /// Processes running in the session, refreshed periodically
+    processes: Vec<Process>,
+    /// Why an entry couldn't be started or stopped, by the entry's path
+    process_errors: HashMap<PathBuf, String>,
+
+    // "Run now": the entry being run, and its output once finished
+    run_name: String,
+    run_output: Option<RunOutput>,
+
+    // launch history of the selected entry
+    history_name: String,
+    history: Vec<LaunchRecord>,
+
+    // login impact report, and how it's sorted
+    impact: Option<Vec<ImpactRow>>,
+    impact_scanning: bool,
+    impact_sort: (ImpactColumn, bool),
 }
 
 /// Messages emitted by the application and its widgets.
@@ -58,6 +167,7 @@ pub enum Message {
 
     RemoveApplication(DirectoryType, DesktopEntry),
     RemoveApplicationConfirm,
+    RemoveScript(bool),
     RemoveApplicationCancel,
 
     // global search
@@ -67,22 +177,96 @@ pub enum Message {
 
     ChooseScriptActivate(DirectoryType),
     ChooseScriptCancel,
+    ScriptChosen(DirectoryType, PathBuf),
+    ImportScript(DirectoryType, PathBuf, ImportMode, Option<String>),
+    ImportInterpreter(usize),
+    ImportScriptCancel,
+    ImportErrorDismiss,
+
+    NewScriptActivate,
+    ChooseAppImageActivate,
+    AppImageChosen(PathBuf),
+    AppImageInspected(Result<AppImage, String>),
+    AppImageNeedsRun(PathBuf),
+    AppImageRun,
+    AppImageExtractAndRun(bool),
+    AddAppImage,
+    AppImageCancel,
+    OpenAtLoginActivate,
+    OpenTarget(String),
+    ChooseOpenTarget(bool),
+    ChooseOpenTargetCancel,
+    AddOpenAtLogin,
+    ScriptName(String),
+    ScriptEdit(text_editor::Action),
+    SaveScript,
+    DeleteOrphanedScript(PathBuf),
+    DeleteOrphanedScriptConfirm,
+    DeleteOrphanedScriptCancel,
+    AddOrphanedScript(PathBuf),
 
     RefreshApps(DirectoryType),
+    PackagesFound(HashMap<PathBuf, Package>),
 
     TogglePopover(u32),
-    PopoverAction(u32, PopoverMessage)
+    PopoverAction(u32, PopoverMessage),
+
+    Editor(EditorMessage),
+
+    SimulateLoginActivate,
+    SimulateLoginDesktops(String),
+
+    GeneratorPreviewActivate,
+
+    LoginImpactActivate,
+    LoginImpactScanned(Option<Vec<ImpactRow>>),
+    SortImpact(ImpactColumn),
+
+    ToggleUnit(String, bool),
+    UnitErrorDismiss,
+    ConvertToService(DesktopEntry),
+    ConvertUnitToEntry(String),
+    ConversionErrorDismiss,
+
+    AddVariable,
+    EditVariable(PathBuf, Range<usize>),
+    RemoveVariable(PathBuf, Range<usize>),
+    VariableEditor(VariableMessage),
+    MergedEnvironmentActivate,
+
+    RefreshProcesses,
+    ProcessesScanned(Vec<Process>),
+    StartNow(DesktopEntry),
+    Stop(PathBuf, u32),
+
+    RunNow(DesktopEntry),
+    RunFinished(RunOutput),
 }
 
 #[derive(Clone, Debug)]
 pub enum PopoverMessage {
     ViewInFiles,
+    Edit,
+    EditScript,
+    ConvertToService,
+    RunNow,
+    History,
 }
 
 #[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
 pub enum ContextPage {
     #[default]
     AddApplication,
+    EditApplication,
+    NewScript,
+    OpenAtLogin,
+    SimulateLogin,
+    GeneratorPreview,
+    RunLog,
+    History,
+    LoginImpact,
+    EditVariable,
+    MergedEnvironment,
 }
 
 /// Create a COSMIC application from the app model
@@ -111,7 +295,7 @@ impl Application for AppModel {
     fn init(core: Core, _flags: Self::Flags) -> (Self, Task<Self::Message>) {
         let locales = freedesktop_desktop_entry::get_languages_from_env();
 
-        let mut apps_hash = HashMap::with_capacity(2);
+        let mut apps_hash = HashMap::with_capacity(3);
         apps_hash.insert(
             DirectoryType::User,
             get_startup_applications(DirectoryType::User, locales.clone()),
@@ -120,6 +304,12 @@ impl Application for AppModel {
             DirectoryType::System,
             get_startup_applications(DirectoryType::System, locales.clone()),
         );
+        apps_hash.insert(
+            DirectoryType::Snap,
+            get_startup_applications(DirectoryType::Snap, locales.clone()),
+        );
+
+        let unit_dirs = UnitDirs::from_system();
 
         // Construct the app model with the runtime's core.
         let mut app = AppModel {
@@ -127,6 +317,8 @@ impl Application for AppModel {
             context_page: ContextPage::default(),
             locales: locales.clone(),
             installed_apps: None,
+            origins: HashMap::new(),
+            packages: HashMap::new(),
             application_search: String::new(),
 
             apps_per_type: apps_hash,
@@ -138,12 +330,84 @@ impl Application for AppModel {
             global_search_id: widget::Id::unique(),
 
             popover_item: None,
+
+            script_name: String::new(),
+            script_content: text_editor::Content::new(),
+            script_error: None,
+            script_editing: None,
+            appimage: None,
+            appimage_extract_and_run: false,
+            appimage_run: None,
+            open_target: String::new(),
+            open_resolved: None,
+
+            orphaned_scripts: Vec::new(),
+            orphan_to_delete: None,
+            remove_script: false,
+            script_import: None,
+            interpreters: Vec::new(),
+            import_error: None,
+
+            editor: None,
+            autostart_condition_kinds: vec![
+                fl!("autostart-condition", "none"),
+                fl!("autostart-condition", "gsettings"),
+                fl!("autostart-condition", "if-exists"),
+                fl!("autostart-condition", "unless-exists"),
+                fl!("autostart-condition", "other"),
+            ],
+            terminals: Vec::new(),
+            terminal_names: Vec::new(),
+            io_class_kinds: vec![
+                fl!("resource-limits", "io-default"),
+                fl!("resource-limits", "io-idle"),
+                fl!("resource-limits", "io-best-effort"),
+            ],
+
+            simulation_desktops: String::new(),
+            simulation: Vec::new(),
+            generator_preview: Vec::new(),
+
+            autostart_status: HashMap::new(),
+            session_desktops: desktops::current_desktops(),
+
+            units: get_user_units(&unit_dirs),
+            unit_dirs,
+            conversion_error: None,
+            unit_error: None,
+
+            env_dirs: EnvDirs::from_system(),
+            env_files: Vec::new(),
+            env_variables: Vec::new(),
+            variable_editor: None,
+
+            processes: Vec::new(),
+            process_errors: HashMap::new(),
+
+            run_name: String::new(),
+            run_output: None,
+
+            history_name: String::new(),
+            history: Vec::new(),
+
+            impact: None,
+            impact_scanning: false,
+            impact_sort: (ImpactColumn::default(), false),
         };
 
+        app.refresh_origins();
+        app.refresh_autostart_status();
+        app.refresh_orphaned_scripts();
+        app.reload_environment();
+
         // Create a startup command that sets the window title.
         let command = app.update_title();
+        let find_packages = app.find_packages();
 
-        (app, command)
+        (
+            app,
+            Task::batch([command, find_packages, cosmic::task::message(Message::RefreshProcesses)]),
+        )
     }
 
     fn context_drawer(&self) -> Option<context_drawer::ContextDrawer<Self::Message>> {
@@ -182,13 +446,18 @@ impl Application for AppModel {
                             let mut icon = icon::from_name(icon_name);
                             icon.fallback = Some(IconFallback::Names(vec!["application-default".into()]));
 
-                            let app_item_row = cosmic::iced::widget::row![
-                            icon.size(24),
-                            cosmic::iced::widget::column![
+                            let mut name_col = cosmic::iced::widget::column![
                                 widget::text::heading(app_name),
                                 exec_line(String::from(application.exec().unwrap_or("")))
                             ]
-                            .width(Length::Fill),
+                            .width(Length::Fill);
+                            if let Some(origin) = self.origins.get(&application.path) {
+                                name_col = name_col.push(origin_badge(origin));
+                            }
+
+                            let app_item_row = cosmic::iced::widget::row![
+                            icon.size(24),
+                            name_col,
                             widget::button::text(fl!("actions", "add"))
                                 .on_press(Message::AddApplication(application.clone()))
                         ]
@@ -200,23 +469,1063 @@ impl Application for AppModel {
                     }
                 }
 
+                let appimage = widget::container(
+                    button::standard(fl!("appimage", "choose"))
+                        .trailing_icon(icon::from_name("window-pop-out-symbolic"))
+                        .on_press(Message::ChooseAppImageActivate),
+                )
+                .width(Length::Fill)
+                .align_x(Alignment::End);
+
                 context_drawer::context_drawer(
-                    cosmic::iced::widget::column![search, list]
+                    cosmic::iced::widget::column![search, appimage, list]
                         .spacing(theme::active().cosmic().space_m()),
                     Message::ToggleContextPage(ContextPage::AddApplication),
                 )
                 .title(fl!("add-application"))
             }
+            ContextPage::NewScript => {
+                let space_s = theme::active().cosmic().space_s();
+
+                let mut content = column().spacing(space_s);
+
+                content = match &self.script_editing {
+                    Some(path) => content.push(widget::text::caption(path.display().to_string())),
+                    None => content
+                        .push(widget::text::body(fl!(
+                            "new-script",
+                            "description",
+                            dir = scripts::scripts_dir().display().to_string()
+                        )))
+                        .push(labeled_input(
+                            fl!("new-script", "name"),
+                            "Sync notes",
+                            &self.script_name,
+                            Message::ScriptName,
+                        )),
+                };
+
+                content = content
+                    .push(
+                        text_editor(&self.script_content)
+                            .on_action(Message::ScriptEdit)
+                            .font(cosmic::font::mono())
+                            .height(Length::Fixed(320.0)),
+                    );
+
+                if let Some(error) = &self.script_error {
+                    content = content.push(widget::text::body(error.clone()));
+                }
+
+                content = content.push(
+                    widget::container(
+                        row()
+                            .spacing(space_s)
+                            .push(
+                                button::standard(fl!("actions", "cancel"))
+                                    .on_press(Message::ToggleContextPage(ContextPage::NewScript)),
+                            )
+                            .push(
+                                button::suggested(fl!("actions", "save"))
+                                    .on_press(Message::SaveScript),
+                            ),
+                    )
+                    .width(Length::Fill)
+                    .align_x(Alignment::End),
+                );
+
+                context_drawer::context_drawer(
+                    content,
+                    Message::ToggleContextPage(ContextPage::NewScript),
+                )
+                .title(match self.script_editing {
+                    Some(_) => fl!("new-script", "edit"),
+                    None => fl!("new-script"),
+                })
+            }
+            ContextPage::OpenAtLogin => {
+                let space_s = theme::active().cosmic().space_s();
+
+                let mut content = column()
+                    .spacing(space_s)
+                    .push(widget::text::body(fl!("open-at-login", "description")))
+                    .push(labeled_input(
+                        fl!("open-at-login", "target"),
+                        "~/Notes",
+                        &self.open_target,
+                        Message::OpenTarget,
+                    ))
+                    .push(
+                        row()
+                            .spacing(space_s)
+                            .push(
+                                button::standard(fl!("open-at-login", "choose-file"))
+                                    .on_press(Message::ChooseOpenTarget(false)),
+                            )
+                            .push(
+                                button::standard(fl!("open-at-login", "choose-folder"))
+                                    .on_press(Message::ChooseOpenTarget(true)),
+                            ),
+                    );
+
+                let mut add = button::suggested(fl!("actions", "add"));
+
+                match &self.open_resolved {
+                    Some(Ok((_, handler))) => {
+                        let name = handler
+                            .name(&self.locales)
+                            .map(|name| name.to_string())
+                            .unwrap_or_else(|| handler.appid.clone());
+
+                        content = content.push(
+                            row()
+                                .spacing(space_s)
+                                .align_y(Alignment::Center)
+                                .push(icon::from_name(handler.icon().unwrap_or("application-default")).size(24))
+                                .push(widget::text::body(fl!("open-at-login", "opens-with", app = name))),
+                        );
+                        add = add.on_press(Message::AddOpenAtLogin);
+                    }
+                    Some(Err(why)) => content = content.push(widget::text::body(why.clone())),
+                    None => {}
+                }
+
+                content = content.push(
+                    widget::container(
+                        row()
+                            .spacing(space_s)
+                            .push(
+                                button::standard(fl!("actions", "cancel"))
+                                    .on_press(Message::ToggleContextPage(ContextPage::OpenAtLogin)),
+                            )
+                            .push(add),
+                    )
+                    .width(Length::Fill)
+                    .align_x(Alignment::End),
+                );
+
+                context_drawer::context_drawer(
+                    content,
+                    Message::ToggleContextPage(ContextPage::OpenAtLogin),
+                )
+                .title(fl!("open-at-login"))
+            }
+            ContextPage::EditApplication => {
+                let editor = self.editor.as_ref()?;
+
+                let space_s = theme::active().cosmic().space_s();
+
+                let mut content = column()
+                    .spacing(space_s)
+                    .push(widget::text::title4(editor.name.clone()))
+                    .push(
+                        column()
+                            .push(widget::text::heading(fl!("launch-conditions")))
+                            .push(widget::text::caption(fl!("launch-conditions", "description"))),
+                    )
+                    .push(
+                        row()
+                            .spacing(space_s)
+                            .align_y(Alignment::Center)
+                            .push(widget::text::body(fl!("condition-on-ac")).width(Length::Fill))
+                            .push(
+                                widget::toggler(editor.on_ac)
+                                    .on_toggle(|on_ac| Message::Editor(EditorMessage::OnAc(on_ac))),
+                            ),
+                    )
+                    .push(labeled_input(
+                        fl!("condition-network-up"),
+                        "wlan0",
+                        &editor.network_up,
+                        |value| Message::Editor(EditorMessage::NetworkUp(value)),
+                    ))
+                    .push(labeled_input(
+                        fl!("condition-hostname"),
+                        "laptop-*",
+                        &editor.hostname,
+                        |value| Message::Editor(EditorMessage::Hostname(value)),
+                    ))
+                    .push(labeled_input(
+                        fl!("condition-weekdays"),
+                        "mon-fri",
+                        &editor.weekdays,
+                        |value| Message::Editor(EditorMessage::Weekdays(value)),
+                    ))
+                    .push(labeled_input(
+                        fl!("condition-time-window"),
+                        "08:00-18:00",
+                        &editor.time_window,
+                        |value| Message::Editor(EditorMessage::TimeWindow(value)),
+                    ))
+                    .push(labeled_input(
+                        fl!("condition-if-exists"),
+                        "~/.config/some-file",
+                        &editor.if_exists,
+                        |value| Message::Editor(EditorMessage::IfExists(value)),
+                    ))
+                    .push(labeled_input(
+                        fl!("condition-unless-exists"),
+                        "~/.config/some-file",
+                        &editor.unless_exists,
+                        |value| Message::Editor(EditorMessage::UnlessExists(value)),
+                    ))
+                    .push(
+                        column()
+                            .push(widget::text::heading(fl!("resource-limits")))
+                            .push(widget::text::caption(fl!("resource-limits", "description"))),
+                    )
+                    .push(labeled_input(
+                        fl!("resource-limits", "nice"),
+                        "10",
+                        &editor.nice,
+                        |value| Message::Editor(EditorMessage::Nice(value)),
+                    ))
+                    .push(
+                        column()
+                            .spacing(4)
+                            .push(widget::text::caption(fl!("resource-limits", "io-class")))
+                            .push(widget::dropdown(
+                                &self.io_class_kinds,
+                                Some(editor.io_class_kind),
+                                |kind| Message::Editor(EditorMessage::IoClassKind(kind)),
+                            )),
+                    );
+
+                if editor.io_class_kind == io_class_kind::BEST_EFFORT {
+                    content = content.push(labeled_input(
+                        fl!("resource-limits", "io-level"),
+                        "4",
+                        &editor.io_level,
+                        |value| Message::Editor(EditorMessage::IoLevel(value)),
+                    ));
+                }
+
+                content = content
+                    .push(labeled_input(
+                        fl!("resource-limits", "memory-max"),
+                        "512M",
+                        &editor.memory_max,
+                        |value| Message::Editor(EditorMessage::MemoryMax(value)),
+                    ))
+                    .push(labeled_input(
+                        fl!("resource-limits", "cpu-quota"),
+                        "50%",
+                        &editor.cpu_quota,
+                        |value| Message::Editor(EditorMessage::CpuQuota(value)),
+                    ))
+                    .push(
+                        row()
+                            .spacing(space_s)
+                            .align_y(Alignment::Center)
+                            .push(
+                                column()
+                                    .width(Length::Fill)
+                                    .push(widget::text::body(fl!("launch-history", "record")))
+                                    .push(widget::text::caption(fl!("launch-history", "record-description"))),
+                            )
+                            .push(
+                                widget::toggler(editor.record_history)
+                                    .on_toggle(|record| Message::Editor(EditorMessage::RecordHistory(record))),
+                            ),
+                    )
+                    .push(
+                        row()
+                            .spacing(space_s)
+                            .align_y(Alignment::Center)
+                            .push(
+                                column()
+                                    .width(Length::Fill)
+                                    .push(widget::text::body(fl!("terminal")))
+                                    .push(widget::text::caption(fl!("terminal", "description"))),
+                            )
+                            .push(
+                                widget::toggler(editor.terminal)
+                                    .on_toggle(|terminal| Message::Editor(EditorMessage::Terminal(terminal))),
+                            ),
+                    );
+
+                if editor.terminal {
+                    content = content.push(if self.terminal_names.is_empty() {
+                        Element::from(widget::text::caption(fl!("terminal", "none-installed")))
+                    } else {
+                        column()
+                            .spacing(4)
+                            .push(widget::text::caption(fl!("terminal", "emulator")))
+                            .push(widget::dropdown(
+                                &self.terminal_names,
+                                Some(editor.terminal_index),
+                                |idx| Message::Editor(EditorMessage::TerminalIndex(idx)),
+                            ))
+                            .into()
+                    });
+                }
+
+                content = content
+                    .push(
+                        column()
+                            .push(widget::text::heading(fl!("entry-environment")))
+                            .push(widget::text::caption(fl!("entry-environment", "description"))),
+                    );
+
+                for (idx, (name, value)) in editor.environment.iter().enumerate() {
+                    content = content.push(
+                        row()
+                            .spacing(space_s)
+                            .align_y(Alignment::Center)
+                            .push(
+                                widget::text_input(fl!("entry-environment", "name"), name)
+                                    .on_input(move |name| Message::Editor(EditorMessage::VariableName(idx, name)))
+                                    .width(Length::FillPortion(2)),
+                            )
+                            .push(
+                                widget::text_input(fl!("entry-environment", "value"), value)
+                                    .on_input(move |value| Message::Editor(EditorMessage::VariableValue(idx, value)))
+                                    .width(Length::FillPortion(3)),
+                            )
+                            .push(
+                                button::icon(icon::from_name("edit-delete-symbolic"))
+                                    .extra_small()
+                                    .on_press(Message::Editor(EditorMessage::RemoveVariable(idx))),
+                            ),
+                    );
+                }
+
+                content = content
+                    .push(
+                        button::text(fl!("entry-environment", "add"))
+                            .leading_icon(icon::from_name("list-add-symbolic"))
+                            .on_press(Message::Editor(EditorMessage::AddVariable)),
+                    )
+                    .push(
+                        column()
+                            .push(widget::text::heading(fl!("session-conditions")))
+                            .push(widget::text::caption(fl!(
+                                "session-conditions",
+                                "description",
+                                desktops = KNOWN_DESKTOPS.join(", ")
+                            ))),
+                    )
+                    .push(labeled_input(
+                        fl!("session-conditions", "only-show-in"),
+                        "COSMIC; GNOME",
+                        &editor.only_show_in,
+                        |value| Message::Editor(EditorMessage::OnlyShowIn(value)),
+                    ))
+                    .push(labeled_input(
+                        fl!("session-conditions", "not-show-in"),
+                        "KDE",
+                        &editor.not_show_in,
+                        |value| Message::Editor(EditorMessage::NotShowIn(value)),
+                    ))
+                    .push(
+                        column()
+                            .spacing(4)
+                            .push(widget::text::caption(fl!("autostart-condition")))
+                            .push(widget::dropdown(
+                                &self.autostart_condition_kinds,
+                                Some(editor.autostart_condition_kind),
+                                |kind| Message::Editor(EditorMessage::AutostartConditionKind(kind)),
+                            )),
+                    );
+
+                if editor.autostart_condition_kind != condition_kind::NONE {
+                    let placeholder = match editor.autostart_condition_kind {
+                        condition_kind::GSETTINGS => "org.gnome.desktop.a11y.applications screen-reader-enabled",
+                        condition_kind::IF_EXISTS | condition_kind::UNLESS_EXISTS => "some-app/enabled",
+                        _ => "",
+                    };
+
+                    content = content.push(
+                        widget::text_input(placeholder, &editor.autostart_condition_value)
+                            .on_input(|value| Message::Editor(EditorMessage::AutostartConditionValue(value))),
+                    );
+                }
+
+                if let Some(error) = &editor.error {
+                    content = content.push(widget::text::body(error.clone()));
+                }
+
+                content = content.push(
+                    widget::container(
+                        row()
+                            .spacing(space_s)
+                            .push(
+                                button::standard(fl!("actions", "cancel"))
+                                    .on_press(Message::Editor(EditorMessage::Cancel)),
+                            )
+                            .push(
+                                button::suggested(fl!("actions", "save"))
+                                    .on_press(Message::Editor(EditorMessage::Save)),
+                            ),
+                    )
+                    .width(Length::Fill)
+                    .align_x(Alignment::End),
+                );
+
+                context_drawer::context_drawer(
+                    content,
+                    Message::ToggleContextPage(ContextPage::EditApplication),
+                )
+                .title(fl!("edit-application"))
+            }
+            ContextPage::SimulateLogin => {
+                let space_xs = theme::active().cosmic().space_xs();
+
+                let desktops_input = labeled_input(
+                    fl!("simulate-login", "desktops"),
+                    "COSMIC",
+                    &self.simulation_desktops,
+                    Message::SimulateLoginDesktops,
+                );
+
+                let mut list = list_column()
+                    .padding(space_xs)
+                    .list_item_padding(0);
+
+                for autostart in &self.simulation {
+                    let name = match autostart.entry.name(&self.locales) {
+                        Some(name) => name.to_string(),
+                        None => autostart.entry.appid.to_owned(),
+                    };
+
+                    let (status_icon, status) = match &autostart.decision {
+                        Decision::Launch => ("object-select-symbolic", fl!("simulate-login", "launch")),
+                        Decision::Skip(reason) => ("action-unavailable-symbolic", reason.describe()),
+                    };
+
+                    list = list.add(
+                        row()
+                            .spacing(space_xs)
+                            .align_y(Alignment::Center)
+                            .push(icon::from_name(status_icon).size(16))
+                            .push(
+                                column()
+                                    .width(Length::Fill)
+                                    .push(widget::text::heading(name))
+                                    .push(widget::text::caption(status))
+                                    .push(exec_line(autostart.entry.path.display().to_string())),
+                            ),
+                    );
+                }
+
+                context_drawer::context_drawer(
+                    cosmic::iced::widget::column![
+                        widget::text::body(fl!("simulate-login", "description")),
+                        desktops_input,
+                        list
+                    ]
+                    .spacing(theme::active().cosmic().space_m()),
+                    Message::ToggleContextPage(ContextPage::SimulateLogin),
+                )
+                .title(fl!("simulate-login"))
+            }
+            ContextPage::GeneratorPreview => {
+                let space_xs = theme::active().cosmic().space_xs();
+
+                let mut list = list_column()
+                    .padding(space_xs)
+                    .list_item_padding(0);
+
+                for generated in &self.generator_preview {
+                    let name = match generated.entry.name(&self.locales) {
+                        Some(name) => name.to_string(),
+                        None => generated.entry.appid.to_owned(),
+                    };
+
+                    let details: Element<Message> = match &generated.outcome {
+                        GeneratorOutcome::Unit { name, contents } => column()
+                            .spacing(4)
+                            .push(widget::text::caption(fl!("generator-preview", "unit", unit = name.clone())))
+                            .push(exec_line(contents.clone()))
+                            .into(),
+                        GeneratorOutcome::Skipped(reason) => {
+                            widget::text::caption(reason.describe()).into()
+                        }
+                    };
+
+                    list = list.add(
+                        column()
+                            .width(Length::Fill)
+                            .push(widget::text::heading(name))
+                            .push(exec_line(generated.entry.path.display().to_string()))
+                            .push(details),
+                    );
+                }
+
+                context_drawer::context_drawer(
+                    cosmic::iced::widget::column![
+                        widget::text::body(fl!("generator-preview", "description")),
+                        list
+                    ]
+                    .spacing(theme::active().cosmic().space_m()),
+                    Message::ToggleContextPage(ContextPage::GeneratorPreview),
+                )
+                .title(fl!("generator-preview"))
+            }
+            ContextPage::RunLog => {
+                let space_s = theme::active().cosmic().space_s();
+
+                let mut content = column()
+                    .spacing(space_s)
+                    .push(widget::text::title4(self.run_name.clone()));
+
+                match &self.run_output {
+                    None => {
+                        content = content.push(widget::text::body(fl!("run-now", "running")));
+                    }
+                    Some(output) => {
+                        content = content
+                            .push(exec_line(entry::join_exec(&output.command)))
+                            .push(widget::text::heading(output.status.describe()))
+                            .push(widget::text::caption(fl!(
+                                "run-now",
+                                "duration",
+                                seconds = format!("{:.1}", output.duration.as_secs_f32())
+                            )));
+
+                        for (label, text) in [
+                            (fl!("run-now", "stdout"), &output.stdout),
+                            (fl!("run-now", "stderr"), &output.stderr),
+                        ] {
+                            content = content.push(
+                                column()
+                                    .spacing(4)
+                                    .push(widget::text::heading(label))
+                                    .push(if text.is_empty() {
+                                        widget::text::caption(fl!("run-now", "no-output"))
+                                    } else {
+                                        exec_line(text.clone())
+                                    }),
+                            );
+                        }
+                    }
+                }
+
+                context_drawer::context_drawer(
+                    content,
+                    Message::ToggleContextPage(ContextPage::RunLog),
+                )
+                .title(fl!("run-now"))
+            }
+            ContextPage::History => {
+                let space_xs = theme::active().cosmic().space_xs();
+
+                let mut list = list_column()
+                    .padding(space_xs)
+                    .list_item_padding(0);
+
+                for record in &self.history {
+                    let (status_icon, outcome) = match &record.outcome {
+                        Outcome::Unfinished => ("content-loading-symbolic", fl!("launch-history", "unfinished")),
+                        Outcome::Exited { code: 0, duration } => (
+                            "object-select-symbolic",
+                            fl!("launch-history", "exited", code = 0, duration = processes::format_duration(*duration)),
+                        ),
+                        Outcome::Exited { code, duration } => (
+                            "dialog-error-symbolic",
+                            fl!("launch-history", "exited", code = *code, duration = processes::format_duration(*duration)),
+                        ),
+                        Outcome::Signalled { signal, duration } => (
+                            "dialog-error-symbolic",
+                            fl!("launch-history", "signalled", signal = *signal, duration = processes::format_duration(*duration)),
+                        ),
+                        Outcome::Skipped(condition) => (
+                            "action-unavailable-symbolic",
+                            fl!("launch-history", "skipped", condition = condition.clone()),
+                        ),
+                        Outcome::FailedToStart(reason) => (
+                            "dialog-error-symbolic",
+                            fl!("launch-history", "failed", reason = reason.clone()),
+                        ),
+                    };
+
+                    list = list.add(
+                        row()
+                            .spacing(space_xs)
+                            .align_y(Alignment::Center)
+                            .push(icon::from_name(status_icon).size(16))
+                            .push(
+                                column()
+                                    .width(Length::Fill)
+                                    .push(widget::text::heading(record.started.format("%Y-%m-%d %H:%M:%S").to_string()))
+                                    .push(widget::text::caption(outcome)),
+                            ),
+                    );
+                }
+
+                let content: Element<Message> = if self.history.is_empty() {
+                    widget::text::body(fl!("launch-history", "empty")).into()
+                } else {
+                    list.into()
+                };
+
+                context_drawer::context_drawer(
+                    cosmic::iced::widget::column![
+                        widget::text::title4(self.history_name.clone()),
+                        widget::text::body(fl!("launch-history", "description", count = HISTORY_LENGTH)),
+                        content
+                    ]
+                    .spacing(theme::active().cosmic().space_m()),
+                    Message::ToggleContextPage(ContextPage::History),
+                )
+                .title(fl!("launch-history"))
+            }
+            ContextPage::LoginImpact => {
+                let space_xs = theme::active().cosmic().space_xs();
+                let (sort_column, descending) = self.impact_sort;
+
+                let header = |label: String, column: ImpactColumn, width: Length| {
+                    let label = match (column == sort_column, descending) {
+                        (true, true) => format!("{label} ↓"),
+                        (true, false) => format!("{label} ↑"),
+                        (false, _) => label,
+                    };
+
+                    button::text(label)
+                        .on_press(Message::SortImpact(column))
+                        .width(width)
+                };
+
+                let cell = |text: String| widget::text::body(text).width(Length::Fixed(IMPACT_COLUMN_WIDTH));
+
+                let content: Element<Message> = match &self.impact {
+                    None if self.impact_scanning => widget::text::body(fl!("login-impact", "scanning")).into(),
+                    None => widget::text::body(fl!("login-impact", "no-session")).into(),
+                    Some(rows) if rows.is_empty() => widget::text::body(fl!("login-impact", "empty")).into(),
+                    Some(rows) => {
+                        let mut list = list_column()
+                            .padding(space_xs)
+                            .list_item_padding(0)
+                            .add(
+                                row()
+                                    .spacing(space_xs)
+                                    .push(header(fl!("login-impact", "name"), ImpactColumn::Name, Length::Fill))
+                                    .push(header(fl!("login-impact", "delay"), ImpactColumn::Delay, Length::Fixed(IMPACT_COLUMN_WIDTH)))
+                                    .push(header(fl!("login-impact", "cpu"), ImpactColumn::Cpu, Length::Fixed(IMPACT_COLUMN_WIDTH)))
+                                    .push(header(fl!("login-impact", "memory"), ImpactColumn::Memory, Length::Fixed(IMPACT_COLUMN_WIDTH))),
+                            );
+
+                        for impact_row in rows {
+                            let (cpu, memory) = match &impact_row.first_minute {
+                                Some(usage) => (
+                                    format!("{:.1} s", usage.cpu.as_secs_f32()),
+                                    format!("{} MB", usage.peak_rss / 1024),
+                                ),
+                                None => ("–".to_string(), "–".to_string()),
+                            };
+
+                            list = list.add(
+                                row()
+                                    .spacing(space_xs)
+                                    .align_y(Alignment::Center)
+                                    .push(
+                                        column()
+                                            .width(Length::Fill)
+                                            .push(widget::text::body(impact_row.name.clone()))
+                                            .push(widget::text::caption(fl!("login-impact", "pid", pid = impact_row.pid))),
+                                    )
+                                    .push(cell(format!("+{:.1} s", impact_row.delay.as_secs_f32())))
+                                    .push(cell(cpu))
+                                    .push(cell(memory)),
+                            );
+                        }
+
+                        list.into()
+                    }
+                };
+
+                context_drawer::context_drawer(
+                    cosmic::iced::widget::column![
+                        widget::text::body(fl!("login-impact", "description")),
+                        content
+                    ]
+                    .spacing(theme::active().cosmic().space_m()),
+                    Message::ToggleContextPage(ContextPage::LoginImpact),
+                )
+                .title(fl!("login-impact"))
+            }
+            ContextPage::EditVariable => {
+                let editor = self.variable_editor.as_ref()?;
+
+                let space_s = theme::active().cosmic().space_s();
+
+                let mut content = column().spacing(space_s);
+
+                content = if editor.editing.is_some() {
+                    content.push(widget::text::caption(
+                        self.env_dirs.user.join(&editor.file).display().to_string(),
+                    ))
+                } else {
+                    content.push(labeled_input(
+                        fl!("environment", "file"),
+                        environment::DEFAULT_FILE,
+                        &editor.file,
+                        |file| Message::VariableEditor(VariableMessage::File(file)),
+                    ))
+                };
+
+                content = content
+                    .push(labeled_input(
+                        fl!("environment", "name"),
+                        "EDITOR",
+                        &editor.key,
+                        |key| Message::VariableEditor(VariableMessage::Key(key)),
+                    ))
+                    .push(labeled_input(
+                        fl!("environment", "value"),
+                        "$HOME/.local/bin:$PATH",
+                        &editor.value,
+                        |value| Message::VariableEditor(VariableMessage::Value(value)),
+                    ))
+                    .push(widget::text::caption(fl!("environment", "value-description")));
+
+                if let Some(error) = &editor.error {
+                    content = content.push(widget::text::body(error.clone()));
+                }
+
+                content = content.push(
+                    widget::container(
+                        row()
+                            .spacing(space_s)
+                            .push(
+                                button::standard(fl!("actions", "cancel"))
+                                    .on_press(Message::VariableEditor(VariableMessage::Cancel)),
+                            )
+                            .push(
+                                button::suggested(fl!("actions", "save"))
+                                    .on_press(Message::VariableEditor(VariableMessage::Save)),
+                            ),
+                    )
+                    .width(Length::Fill)
+                    .align_x(Alignment::End),
+                );
+
+                context_drawer::context_drawer(
+                    content,
+                    Message::ToggleContextPage(ContextPage::EditVariable),
+                )
+                .title(match editor.editing {
+                    Some(_) => fl!("environment", "edit"),
+                    None => fl!("environment", "add"),
+                })
+            }
+            ContextPage::MergedEnvironment => {
+                let space_xs = theme::active().cosmic().space_xs();
+
+                let mut list = list_column()
+                    .padding(space_xs)
+                    .list_item_padding(0);
+
+                for variable in &self.env_variables {
+                    let mut variable_row = row()
+                        .spacing(space_xs)
+                        .align_y(Alignment::Center)
+                        .push(
+                            column()
+                                .width(Length::Fill)
+                                .push(widget::text::heading(variable.key.clone()))
+                                .push(exec_line(variable.value.clone()))
+                                .push(widget::text::caption(variable.source.display().to_string())),
+                        );
+
+                    if !variable.shadowed.is_empty() {
+                        variable_row = variable_row.push(shadowed_chip(variable));
+                    }
+
+                    list = list.add(variable_row);
+                }
+
+                let mut content = column()
+                    .spacing(theme::active().cosmic().space_m())
+                    .push(widget::text::body(fl!("environment", "merged-description")));
+
+                content = if self.env_variables.is_empty() {
+                    content.push(widget::text::body(fl!("environment", "merged-empty")))
+                } else {
+                    content.push(list)
+                };
+
+                for file in &self.env_files {
+                    if let Some(user_file) = &file.masked_by {
+                        content = content.push(widget::text::caption(fl!(
+                            "environment",
+                            "masked",
+                            path = file.path.display().to_string(),
+                            user = user_file.display().to_string()
+                        )));
+                    }
+                }
+
+                context_drawer::context_drawer(
+                    content,
+                    Message::ToggleContextPage(ContextPage::MergedEnvironment),
+                )
+                .title(fl!("environment", "merged"))
+            }
         })
     }
 
     fn dialog(&self) -> Option<Element<Self::Message>> {
-        if let Some(_selected_app) = &self.selected_app {
+        if let Some(why) = &self.unit_error {
             return Some(
                 widget::dialog()
-                    .title(fl!("dialog-remove-application"))
+                    .title(fl!("systemd-units", "update-failed"))
                     .icon(icon::from_name("dialog-error-symbolic").size(64))
-                    .body(fl!("dialog-remove-application", "body"))
+                    .body(why.clone())
+                    .primary_action(button::standard(fl!("actions", "close")).on_press(Message::UnitErrorDismiss))
+                    .into(),
+            );
+        }
+
+        if let Some(why) = &self.conversion_error {
+            return Some(
+                widget::dialog()
+                    .title(fl!("systemd-units", "conversion-failed"))
+                    .icon(icon::from_name("dialog-error-symbolic").size(64))
+                    .body(why.clone())
+                    .primary_action(
+                        button::standard(fl!("actions", "close")).on_press(Message::ConversionErrorDismiss),
+                    )
+                    .into(),
+            );
+        }
+
+        match &self.appimage {
+            Some(Ok(appimage)) => {
+                let icon: Element<_> = match &appimage.icon {
+                    Some(path) => widget::icon(icon::from_path(path.clone())).size(64).into(),
+                    None => icon::from_name("application-x-executable").size(64).into(),
+                };
+
+                let mut body = fl!("appimage", "body", path = appimage.host_path.display().to_string());
+                if !appimage.executable {
+                    body.push(' ');
+                    body.push_str(&fl!("appimage", "made-executable"));
+                }
+
+                return Some(
+                    widget::dialog()
+                        .title(appimage.name.clone())
+                        .icon(icon)
+                        .body(body)
+                        .control(
+                            row()
+                                .spacing(theme::active().cosmic().space_s())
+                                .align_y(Alignment::Center)
+                                .push(
+                                    column()
+                                        .width(Length::Fill)
+                                        .push(widget::text::body(fl!("appimage", "extract-and-run")))
+                                        .push(widget::text::caption(fl!(
+                                            "appimage",
+                                            "extract-and-run-description"
+                                        ))),
+                                )
+                                .push(
+                                    widget::toggler(self.appimage_extract_and_run)
+                                        .on_toggle(Message::AppImageExtractAndRun),
+                                ),
+                        )
+                        .primary_action(
+                            button::suggested(fl!("actions", "add")).on_press(Message::AddAppImage),
+                        )
+                        .secondary_action(
+                            button::standard(fl!("actions", "cancel")).on_press(Message::AppImageCancel),
+                        )
+                        .into(),
+                );
+            }
+            Some(Err(why)) => {
+                return Some(
+                    widget::dialog()
+                        .title(fl!("appimage"))
+                        .icon(icon::from_name("dialog-error-symbolic").size(64))
+                        .body(why.clone())
+                        .primary_action(
+                            button::standard(fl!("actions", "cancel")).on_press(Message::AppImageCancel),
+                        )
+                        .into(),
+                );
+            }
+            None => {}
+        }
+
+        if let Some(path) = &self.appimage_run {
+            return Some(
+                widget::dialog()
+                    .title(fl!("appimage"))
+                    .icon(icon::from_name("dialog-warning-symbolic").size(64))
+                    .body(fl!("appimage", "run-to-read", path = path.display().to_string()))
+                    .primary_action(button::suggested(fl!("appimage", "run")).on_press(Message::AppImageRun))
+                    .secondary_action(
+                        button::standard(fl!("actions", "cancel")).on_press(Message::AppImageCancel),
+                    )
+                    .into(),
+            );
+        }
+
+        if let Some(import) = &self.script_import {
+            let import_message = |mode, interpreter: Option<&String>| {
+                Message::ImportScript(
+                    import.directory_type.clone(),
+                    import.path.clone(),
+                    mode,
+                    interpreter.cloned(),
+                )
+            };
+
+            let dialog = widget::dialog()
+                .title(fl!("import-script"))
+                .icon(icon::from_name("dialog-question-symbolic").size(64))
+                .tertiary_action(button::text(fl!("actions", "cancel")).on_press(Message::ImportScriptCancel));
+
+            // a failed import leaves the dialog open to try another way
+            let with_error = |body: String| match &self.import_error {
+                Some(why) => format!("{body}\n\n{why}"),
+                None => body,
+            };
+
+            let Some(problem) = &import.problem else {
+                let host_path = import.host_path.clone().unwrap_or_else(|| import.path.clone());
+
+                return Some(
+                    dialog
+                        .body(with_error(fl!("import-script", "body", path = host_path.display().to_string())))
+                        .primary_action(
+                            button::suggested(fl!("import-script", "reference"))
+                                .on_press(import_message(ImportMode::Reference, None)),
+                        )
+                        .secondary_action(
+                            button::standard(fl!("import-script", "copy"))
+                                .on_press(import_message(ImportMode::Copy, None)),
+                        )
+                        .into(),
+                );
+            };
+
+            let path = import.path.display().to_string();
+            let body = match problem {
+                ScriptProblem::NotExecutable if import.is_binary => {
+                    fl!("import-script", "binary-not-executable", path = path)
+                }
+                ScriptProblem::NotExecutable => fl!("import-script", "not-executable", path = path),
+                ScriptProblem::NoShebang => fl!("import-script", "no-shebang", path = path),
+                ScriptProblem::MissingInterpreter(interpreter) => fl!(
+                    "import-script",
+                    "missing-interpreter",
+                    path = path,
+                    interpreter = interpreter.as_str()
+                ),
+            };
+
+            // a program can only be made executable, which copying it does
+            if import.is_binary {
+                return Some(
+                    dialog
+                        .body(with_error(body))
+                        .primary_action(
+                            button::suggested(fl!("import-script", "copy"))
+                                .on_press(import_message(ImportMode::Copy, None)),
+                        )
+                        .into(),
+                );
+            }
+
+            // run in place when the host can see the file, otherwise through a copy
+            let mode = match import.host_path {
+                Some(_) => ImportMode::Reference,
+                None => ImportMode::Copy,
+            };
+            let interpreter = import.interpreter.and_then(|index| self.interpreters.get(index));
+
+            let mut dialog = dialog.body(with_error(body));
+
+            if interpreter.is_some() {
+                dialog = dialog.control(
+                    column()
+                        .spacing(4)
+                        .push(widget::text::caption(fl!("import-script", "interpreter")))
+                        .push(widget::dropdown(
+                            &self.interpreters,
+                            import.interpreter,
+                            Message::ImportInterpreter,
+                        )),
+                );
+            }
+
+            let mut run_with = button::suggested(fl!("import-script", "run-with"));
+            if let Some(interpreter) = interpreter {
+                run_with = run_with.on_press(import_message(mode, Some(interpreter)));
+            }
+
+            // copying sets the execute bit, and runs a file without a `#!` line through sh
+            let anyway = match problem {
+                ScriptProblem::MissingInterpreter(_) => {
+                    button::standard(fl!("import-script", "anyway")).on_press(import_message(mode, None))
+                }
+                _ => button::standard(fl!("import-script", "copy"))
+                    .on_press(import_message(ImportMode::Copy, None)),
+            };
+
+            return Some(dialog.primary_action(run_with).secondary_action(anyway).into());
+        }
+
+        if let Some(why) = &self.import_error {
+            return Some(
+                widget::dialog()
+                    .title(fl!("import-script", "failed"))
+                    .icon(icon::from_name("dialog-error-symbolic").size(64))
+                    .body(why.clone())
+                    .primary_action(
+                        button::standard(fl!("actions", "close")).on_press(Message::ImportErrorDismiss),
+                    )
+                    .into(),
+            );
+        }
+
+        if let Some(script) = &self.orphan_to_delete {
+            return Some(
+                widget::dialog()
+                    .title(fl!("dialog-delete-script"))
+                    .icon(icon::from_name("dialog-error-symbolic").size(64))
+                    .body(fl!("dialog-delete-script", "body", script = script.display().to_string()))
+                    .secondary_action(
+                        button::destructive(fl!("actions", "yes"))
+                            .on_press(Message::DeleteOrphanedScriptConfirm),
+                    )
+                    .primary_action(
+                        button::suggested(fl!("actions", "no"))
+                            .on_press(Message::DeleteOrphanedScriptCancel),
+                    )
+                    .into(),
+            );
+        }
+
+        if let Some(selected_app) = &self.selected_app {
+            let mut dialog = widget::dialog()
+                .title(fl!("dialog-remove-application"))
+                .icon(icon::from_name("dialog-error-symbolic").size(64))
+                .body(fl!("dialog-remove-application", "body"));
+
+            if let Some(script) = scripts::owned_script(selected_app, &scripts::script_dirs()) {
+                dialog = dialog.control(
+                    row()
+                        .spacing(theme::active().cosmic().space_s())
+                        .align_y(Alignment::Center)
+                        .push(
+                            widget::text::body(fl!(
+                                "dialog-remove-application",
+                                "delete-script",
+                                script = script.display().to_string()
+                            ))
+                            .width(Length::Fill),
+                        )
+                        .push(widget::toggler(self.remove_script).on_toggle(Message::RemoveScript)),
+                );
+            }
+
+            return Some(
+                dialog
                     .secondary_action(button::destructive(fl!("actions", "yes")).on_press(
                         Message::RemoveApplicationConfirm,
                     ))
@@ -231,7 +1540,25 @@ impl Application for AppModel {
     }
 
     fn header_end(&self) -> Vec<Element<Self::Message>> {
-        let mut elements = Vec::with_capacity(2);
+        let mut elements = Vec::with_capacity(4);
+
+        elements.push(
+            widget::button::text(fl!("simulate-login"))
+                .on_press(Message::SimulateLoginActivate)
+                .into(),
+        );
+
+        elements.push(
+            widget::button::text(fl!("generator-preview"))
+                .on_press(Message::GeneratorPreviewActivate)
+                .into(),
+        );
+
+        elements.push(
+            widget::button::text(fl!("login-impact"))
+                .on_press(Message::LoginImpactActivate)
+                .into(),
+        );
 
         if let Some(search) = &self.global_search {
             elements.push(
@@ -273,6 +1600,8 @@ impl Application for AppModel {
                     futures_util::future::pending().await
                 }),
             ),
+            // keep the running indicators up to date
+            cosmic::iced::time::every(Duration::from_secs(3)).map(|_| Message::RefreshProcesses),
         ])
     }
 
@@ -297,9 +1626,7 @@ impl Application for AppModel {
                 self.application_search = search;
             }
             Message::AddApplicationActivate(directory_type) => {
-                if self.installed_apps.is_none() {
-                    self.installed_apps = Some(get_installed_applications(self.locales.clone()));
-                }
+                self.load_installed_apps();
                 self.selected_type = Some(directory_type);
                 return cosmic::task::message(Message::ToggleContextPage(ContextPage::AddApplication));
             }
@@ -313,14 +1640,30 @@ impl Application for AppModel {
                     let directory_to_target = directories.get(0).expect("Always at least one directory");
 
                     if let Ok(exists) = std::fs::exists(directory_to_target.join(file_name.clone())) {
-                        if !exists {
+                        // snapd rewrites its exported entries on every refresh and removes them
+                        // with the snap, so snaps get a copy which starts them with `snap run`
+                        let snap_command = snap::snap_run_command(&desktop_entry);
+
+                        if let (false, Some(command)) = (exists, snap_command) {
+                            // installed entries are listed through `apps::host_path`, so the copy
+                            // reads them where the sandbox can see them
+                            let target = directory_to_target.join(file_name);
+                            let added = fs::copy(&desktop_entry.path, &target).and_then(|_| {
+                                entry::set_keys(&target, &[("Exec", Some(entry::join_exec(&command)))])
+                            });
+
+                            match added {
+                                Ok(_) => self.reload_apps(directory_type.clone()),
+                                Err(why) => eprintln!("failed to add {}: {why}", desktop_entry.path.display()),
+                            }
+                        } else if !exists {
                             #[cfg(feature = "flatpak")]
                             match fs::copy(
                                 desktop_entry.clone().path,
                                 directory_to_target.join(file_name),
                             ) {
                                 Ok(_) => {
-                                    self.apps_per_type.insert(directory_type.clone(), get_startup_applications(directory_type.clone(), self.locales.clone()));
+                                    self.reload_apps(directory_type.clone());
                                 }
                                 Err(e) => {
                                     // @todo - error handling
@@ -333,7 +1676,7 @@ impl Application for AppModel {
                                 directory_to_target.join(file_name),
                             ) {
                                 Ok(_) => {
-                                    self.apps_per_type.insert(directory_type.clone(), get_startup_applications(directory_type.clone(), self.locales.clone()));
+                                    self.reload_apps(directory_type.clone());
                                 }
                                 Err(e) => {
                                     // @todo - error handling
@@ -349,6 +1692,10 @@ impl Application for AppModel {
             Message::RemoveApplication(directory_type, desktop_entry) => {
                 self.selected_type = Some(directory_type);
                 self.selected_app = Some(desktop_entry);
+                self.remove_script = false;
+            }
+            Message::RemoveScript(remove_script) => {
+                self.remove_script = remove_script;
             }
             Message::RemoveApplicationConfirm => {
                 if let Some(directory_type) = &self.selected_type {
@@ -358,7 +1705,18 @@ impl Application for AppModel {
 
                         let directories: Vec<PathBuf> = directory_type.clone().into();
 
-                        let directory_to_target = directories.get(0).expect("Always at least one directory");
+                        // each snap has its own autostart directory, so use the one holding the entry
+                        let directory_to_target = desktop_entry
+                            .path
+                            .parent()
+                            .filter(|dir| directories.iter().any(|directory| directory == dir))
+                            .or(directories.first().map(PathBuf::as_path))
+                            .expect("Always at least one directory");
+
+                        // scripts the app copied or wrote only exist for this entry, but are only
+                        // deleted when asked to
+                        let script = scripts::owned_script(desktop_entry, &scripts::script_dirs())
+                            .filter(|_| self.remove_script);
 
                         if let Ok(exists) = std::fs::exists(directory_to_target.join(file_name.clone())) {
                             if exists {
@@ -366,7 +1724,12 @@ impl Application for AppModel {
                                     directory_to_target.join(file_name),
                                 ) {
                                     Ok(_) => {
-                                        self.apps_per_type.insert(directory_type.clone(), get_startup_applications(directory_type.clone(), self.locales.clone()));
+                                        if let Some(script) = script {
+                                            if let Err(why) = fs::remove_file(&script) {
+                                                eprintln!("failed to remove {}: {why}", script.display());
+                                            }
+                                        }
+                                        self.reload_apps(directory_type.clone());
                                     }
                                     Err(e) => {
                                         // @todo - error handling
@@ -400,100 +1763,23 @@ impl Application for AppModel {
                 return cosmic::dialog::file_chooser::open::Dialog::new()
                     .directory(dirs::home_dir().unwrap())
                     .title(fl!("script-chooser"))
+                    .filter(FileFilter::new(fl!("script-chooser", "all-files")).glob("*"))
                     .filter(
-                        FileFilter::new("*sh scripts")
-                            .glob("*.*sh")
-                    )
-                    .filter(
-                        FileFilter::new("Python scripts")
-                            .glob("*.py*")
-                    )
-                    .filter(
-                        FileFilter::new("All files")
-                            .glob("*.*")
+                        FileFilter::new(fl!("script-chooser", "sh-scripts"))
+                            .glob("*.sh")
+                            .glob("*.*sh"),
                     )
+                    .filter(FileFilter::new(fl!("script-chooser", "python-scripts")).glob("*.py"))
                     .open_file()
                     .then(|result| async move {
                         return match result {
                             Ok(response) => {
-                                println!("{:?}", response.0);
                                 let Ok(path) = response.url().to_file_path() else {
                                     // @todo - error
                                     return Message::ChooseScriptCancel;
                                 };
 
-                                // spaghetti?
-                                let Some(file_name) = path.file_name() else {
-                                    // @todo - error
-                                    return Message::ChooseScriptCancel;
-                                };
-                                let Some(file_name) = file_name.to_str() else {
-                                    // @todo - error
-                                    return Message::ChooseScriptCancel;
-                                };
-
-                                let directories: Vec<PathBuf> = directory_type.clone().into();
-
-                                let directory_to_target = directories.get(0).expect("Always at least one directory");
-
-                                // when bundled as a flatpak, we need to do a few creative things..
-                                // 1) write the script itself to `~/.config/autostart/` so it's in a
-                                // known location - flatpaks do not let us get the host path!
-                                // 2) set the script as +x
-                                #[cfg(feature = "flatpak")]
-                                match std::fs::write(directory_to_target.join(file_name), std::fs::read_to_string(path.clone()).expect("Should be able to read")) {
-                                    Ok(_) => {
-                                        let entry_text = format!("[Desktop Entry]
-Type=Application
-Name={}
-Exec=sh -c '~/.config/autostart/{}'", file_name, file_name);
-                                        let mut desktop_file_name = file_name.to_string();
-                                        desktop_file_name.push_str(".desktop");
-                                        match std::fs::write(directory_to_target.join(desktop_file_name), entry_text) {
-                                            Ok(_) => {
-                                                if let Ok(metadata) = fs::metadata(directory_to_target.join(file_name)) {
-                                                    use std::os::unix::fs::PermissionsExt;
-
-                                                    let mut permissions = metadata.permissions();
-                                                    permissions.set_mode(permissions.mode() | 0o111);
-                                                    if let Ok(_) = fs::set_permissions(
-                                                        directory_to_target.join(file_name),
-                                                        permissions
-                                                    ) {
-                                                        return Message::RefreshApps(directory_type);
-                                                    }
-                                                }
-
-                                            }
-                                            Err(err) => {
-                                                // @ todo - error
-                                            }
-                                        }
-                                    }
-                                    Err(err) => {
-                                        // @todo - error
-                                    }
-                                }
-
-                                #[cfg(not(feature = "flatpak"))]
-                                {
-                                    let entry_text = format!("[Desktop Entry]
-Type=Application
-Name={}
-Exec={:?}", file_name, path);
-                                    let mut desktop_file_name = file_name.to_string();
-                                    desktop_file_name.push_str(".desktop");
-                                    match std::fs::write(directory_to_target.join(desktop_file_name), entry_text) {
-                                        Ok(_) => {
-                                            return Message::RefreshApps(directory_type);
-                                        }
-                                        Err(err) => {
-                                            // @ todo - error
-                                        }
-                                    }
-                                }
-
-                                Message::ChooseScriptCancel
+                                Message::ScriptChosen(directory_type, path)
                             }
                             Err(cosmic::dialog::file_chooser::Error::Cancelled) => {
                                 Message::ChooseScriptCancel
@@ -506,8 +1792,293 @@ Exec={:?}", file_name, path);
                     .apply(cosmic::task::future);
             }
             Message::ChooseScriptCancel => {}
+            Message::ScriptChosen(directory_type, path) => {
+                self.import_error = None;
+                let problem = scripts::check_script(&path);
+                let host_path = scripts::host_location(&path);
+
+                if problem.is_some() {
+                    self.interpreters = scripts::available_interpreters();
+                    let guess = scripts::guess_interpreter(&path);
+
+                    self.script_import = Some(ScriptImport {
+                        directory_type,
+                        is_binary: scripts::is_binary(&path),
+                        interpreter: self
+                            .interpreters
+                            .iter()
+                            .position(|interpreter| interpreter == guess)
+                            .or((!self.interpreters.is_empty()).then_some(0)),
+                        path,
+                        host_path,
+                        problem,
+                    });
+                    return Task::none();
+                }
+
+                // when bundled as a flatpak, the chosen file may only be visible to the sandbox,
+                // so it's copied into the autostart directory unless the host can run it in place
+                #[cfg(feature = "flatpak")]
+                if host_path.as_deref().is_some_and(scripts::host_executable) {
+                    self.script_import = Some(ScriptImport {
+                        directory_type,
+                        path,
+                        host_path,
+                        problem,
+                        is_binary: false,
+                        interpreter: None,
+                    });
+                    return Task::none();
+                }
+
+                #[cfg(feature = "flatpak")]
+                return cosmic::task::message(Message::ImportScript(
+                    directory_type,
+                    path,
+                    ImportMode::Copy,
+                    None,
+                ));
+
+                #[cfg(not(feature = "flatpak"))]
+                return cosmic::task::message(Message::ImportScript(
+                    directory_type,
+                    path,
+                    ImportMode::Reference,
+                    None,
+                ));
+            }
+            Message::ImportInterpreter(index) => {
+                if let Some(import) = &mut self.script_import {
+                    import.interpreter = Some(index);
+                }
+            }
+            Message::ImportScript(directory_type, path, mode, interpreter) => {
+                let import = self.script_import.take();
+
+                let directories: Vec<PathBuf> = directory_type.clone().into();
+                let directory_to_target = directories.first().expect("Always at least one directory");
+
+                let imported = match mode {
+                    ImportMode::Copy => scripts::import_copy(
+                        &path,
+                        directory_to_target,
+                        &scripts::user_script_dir(),
+                        interpreter.as_deref(),
+                    ),
+                    ImportMode::Reference => match scripts::host_location(&path) {
+                        Some(host_path) => {
+                            scripts::import_reference(&host_path, directory_to_target, interpreter.as_deref())
+                        }
+                        None => Err(fl!("import-script", "not-on-host")),
+                    },
+                };
+
+                match imported {
+                    Ok(_) => {
+                        self.import_error = None;
+                        self.reload_apps(directory_type);
+                    }
+                    Err(why) => {
+                        eprintln!("failed to add {}: {why}", path.display());
+                        self.import_error = Some(why);
+                        self.script_import = import;
+                    }
+                }
+            }
+            Message::ImportScriptCancel => {
+                self.script_import = None;
+                self.import_error = None;
+            }
+            Message::ImportErrorDismiss => {
+                self.import_error = None;
+            }
+            Message::NewScriptActivate => {
+                self.script_name = String::new();
+                self.script_content = text_editor::Content::with_text(scripts::TEMPLATE);
+                self.script_error = None;
+                self.script_editing = None;
+                self.context_page = ContextPage::NewScript;
+                self.core.window.show_context = true;
+            }
+            Message::ChooseAppImageActivate => {
+                return cosmic::dialog::file_chooser::open::Dialog::new()
+                    .directory(dirs::home_dir().unwrap())
+                    .title(fl!("appimage", "choose"))
+                    .filter(
+                        FileFilter::new(fl!("appimage"))
+                            .glob("*.AppImage")
+                            .glob("*.appimage"),
+                    )
+                    .filter(FileFilter::new(fl!("script-chooser", "all-files")).glob("*"))
+                    .open_file()
+                    .then(|result| async move {
+                        match result.map(|response| response.url().to_file_path()) {
+                            Ok(Ok(path)) => Message::AppImageChosen(path),
+                            _ => Message::AppImageCancel,
+                        }
+                    })
+                    .apply(cosmic::task::future);
+            }
+            Message::AppImageChosen(path) => {
+                // extracting means reading the whole payload, so keep it off the UI thread
+                return cosmic::task::future(async move {
+                    // only ask to run files which are AppImages at all
+                    if !appimage::is_appimage(&path) {
+                        return Message::AppImageInspected(Err(fl!("appimage", "not-appimage")));
+                    }
+
+                    match appimage::can_unsquashfs() {
+                        true => Message::AppImageInspected(appimage::inspect(&path, Extractor::Unsquashfs)),
+                        false => Message::AppImageNeedsRun(path),
+                    }
+                });
+            }
+            Message::AppImageNeedsRun(path) => {
+                self.appimage_run = Some(path);
+            }
+            Message::AppImageRun => {
+                let Some(path) = self.appimage_run.take() else {
+                    return Task::none();
+                };
+
+                return cosmic::task::future(async move {
+                    Message::AppImageInspected(appimage::inspect(&path, Extractor::Runtime))
+                });
+            }
+            Message::AppImageInspected(inspected) => {
+                self.appimage = Some(inspected);
+                self.appimage_extract_and_run = false;
+            }
+            Message::AppImageExtractAndRun(extract_and_run) => {
+                self.appimage_extract_and_run = extract_and_run;
+            }
+            Message::AddAppImage => {
+                let Some(Ok(appimage)) = &self.appimage else {
+                    return Task::none();
+                };
+
+                let directories: Vec<PathBuf> = DirectoryType::User.into();
+                let autostart_dir = directories.first().expect("Always at least one directory");
+
+                match appimage::write_entry(appimage, self.appimage_extract_and_run, autostart_dir) {
+                    Ok(_) => {
+                        self.appimage = None;
+                        self.core.window.show_context = false;
+                        self.reload_apps(DirectoryType::User);
+                    }
+                    Err(why) => self.appimage = Some(Err(why)),
+                }
+            }
+            Message::AppImageCancel => {
+                self.appimage = None;
+                self.appimage_run = None;
+            }
+            Message::OpenAtLoginActivate => {
+                self.load_installed_apps();
+                self.open_target = String::new();
+                self.open_resolved = None;
+                self.context_page = ContextPage::OpenAtLogin;
+                self.core.window.show_context = true;
+            }
+            Message::OpenTarget(target) => {
+                self.open_target = target;
+                self.open_resolved = (!self.open_target.trim().is_empty())
+                    .then(|| self.resolve_open_target(Target::parse(&self.open_target)));
+            }
+            Message::ChooseOpenTarget(folder) => {
+                let dialog = cosmic::dialog::file_chooser::open::Dialog::new()
+                    .directory(dirs::home_dir().unwrap())
+                    .title(fl!("open-at-login"));
+
+                return cosmic::task::future(async move {
+                    let response = match folder {
+                        true => dialog.open_folder().await,
+                        false => dialog.open_file().await,
+                    };
+
+                    match response.map(|response| response.url().to_file_path()) {
+                        Ok(Ok(path)) => Message::OpenTarget(path.display().to_string()),
+                        _ => Message::ChooseOpenTargetCancel,
+                    }
+                });
+            }
+            Message::ChooseOpenTargetCancel => {}
+            Message::AddOpenAtLogin => {
+                let Some(Ok((target, handler))) = &self.open_resolved else {
+                    return Task::none();
+                };
+
+                let directories: Vec<PathBuf> = DirectoryType::User.into();
+                let autostart_dir = directories.first().expect("Always at least one directory");
+
+                match opener::write_entry(target, handler, autostart_dir) {
+                    Ok(_) => {
+                        self.core.window.show_context = false;
+                        self.reload_apps(DirectoryType::User);
+                    }
+                    Err(why) => {
+                        self.open_resolved = Some(Err(why));
+                    }
+                }
+            }
+            Message::ScriptName(name) => {
+                self.script_name = name;
+            }
+            Message::ScriptEdit(action) => {
+                self.script_content.perform(action);
+            }
+            Message::SaveScript => {
+                let directories: Vec<PathBuf> = DirectoryType::User.into();
+                let autostart_dir = directories.first().expect("Always at least one directory");
+
+                let saved = match &self.script_editing {
+                    Some(path) => scripts::save_script(path, &self.script_content.text()),
+                    None => scripts::create(
+                        &self.script_name,
+                        &self.script_content.text(),
+                        &scripts::scripts_dir(),
+                        autostart_dir,
+                    )
+                    .map(|_| ()),
+                };
+
+                match saved {
+                    Ok(_) => {
+                        self.core.window.show_context = false;
+                        self.reload_apps(DirectoryType::User);
+                    }
+                    Err(why) => {
+                        self.script_error = Some(why);
+                    }
+                }
+            }
+            Message::DeleteOrphanedScript(path) => {
+                self.orphan_to_delete = Some(path);
+            }
+            Message::DeleteOrphanedScriptConfirm => {
+                if let Some(path) = self.orphan_to_delete.take() {
+                    if let Err(why) = fs::remove_file(&path) {
+                        eprintln!("failed to remove {}: {why}", path.display());
+                    }
+                }
+
+                self.refresh_orphaned_scripts();
+            }
+            Message::DeleteOrphanedScriptCancel => {
+                self.orphan_to_delete = None;
+            }
+            Message::AddOrphanedScript(path) => {
+                let directories: Vec<PathBuf> = DirectoryType::User.into();
+                let autostart_dir = directories.first().expect("Always at least one directory");
+
+                if let Err(why) = scripts::adopt_orphan(&path, autostart_dir) {
+                    eprintln!("failed to add an entry for {}: {why}", path.display());
+                }
+
+                self.reload_apps(DirectoryType::User);
+            }
             Message::RefreshApps(directory_type) => {
-                self.apps_per_type.insert(directory_type.clone(), get_startup_applications(directory_type.clone(), self.locales.clone()));
+                self.reload_apps(directory_type);
             }
             Message::TogglePopover(idx) => {
                 if let Some(current_idx) = self.popover_item {
@@ -526,6 +2097,62 @@ Exec={:?}", file_name, path);
                 if let Some(user_apps) = self.apps_per_type.get(&DirectoryType::User) {
                     if let Some(app) = user_apps.get(idx as usize) {
                         match popover_action {
+                            PopoverMessage::ConvertToService => {
+                                self.popover_item = None;
+                                return cosmic::task::message(Message::ConvertToService(app.clone()));
+                            }
+                            PopoverMessage::RunNow => {
+                                self.popover_item = None;
+                                return cosmic::task::message(Message::RunNow(app.clone()));
+                            }
+                            PopoverMessage::History => {
+                                self.history_name = match app.name(&self.locales) {
+                                    Some(name) => name.to_string(),
+                                    None => app.appid.to_owned(),
+                                };
+                                self.history = history::read(
+                                    &history::history_dir(),
+                                    &history::entry_id(&app.path),
+                                    HISTORY_LENGTH,
+                                );
+                                self.context_page = ContextPage::History;
+                                self.core.window.show_context = true;
+                            }
+                            PopoverMessage::EditScript => {
+                                let Some(script) = scripts::owned_script(app, &scripts::script_dirs()) else {
+                                    self.popover_item = None;
+                                    return Task::none();
+                                };
+
+                                match scripts::read_script(&script) {
+                                    Ok(contents) => {
+                                        self.script_content = text_editor::Content::with_text(&contents);
+                                        self.script_error = None;
+                                        self.script_editing = Some(script);
+                                        self.context_page = ContextPage::NewScript;
+                                        self.core.window.show_context = true;
+                                    }
+                                    Err(why) => eprintln!("can't edit {}: {why}", script.display()),
+                                }
+                            }
+                            PopoverMessage::Edit => {
+                                self.load_installed_apps();
+                                self.terminals = terminal::detect(
+                                    self.installed_apps.as_deref().unwrap_or_default(),
+                                    &self.locales,
+                                );
+                                self.terminal_names =
+                                    self.terminals.iter().map(|terminal| terminal.name.clone()).collect();
+
+                                self.editor = Some(EntryEditor::new(
+                                    DirectoryType::User,
+                                    app,
+                                    &self.locales,
+                                    &self.terminals,
+                                ));
+                                self.context_page = ContextPage::EditApplication;
+                                self.core.window.show_context = true;
+                            }
                             PopoverMessage::ViewInFiles => {
                                 if let Some(dir) = &app.path.parent() {
                                     // when run as a flatpak, we need to de-sandbox the directory to try and guess
@@ -557,6 +2184,270 @@ Exec={:?}", file_name, path);
                 
                 self.popover_item = None;
             }
+            Message::SimulateLoginActivate => {
+                self.simulation_desktops = desktops::current_desktops().join(":");
+                self.update_simulation();
+                return cosmic::task::message(Message::ToggleContextPage(ContextPage::SimulateLogin));
+            }
+            Message::SimulateLoginDesktops(desktop_list) => {
+                self.simulation_desktops = desktop_list;
+                self.update_simulation();
+            }
+            Message::GeneratorPreviewActivate => {
+                let empty = Vec::new();
+                let system_dirs: Vec<PathBuf> = DirectoryType::System.into();
+                self.generator_preview = generator::preview(
+                    self.apps_per_type.get(&DirectoryType::User).unwrap_or(&empty),
+                    self.apps_per_type.get(&DirectoryType::System).unwrap_or(&empty),
+                    &system_dirs,
+                );
+                return cosmic::task::message(Message::ToggleContextPage(ContextPage::GeneratorPreview));
+            }
+            Message::ToggleUnit(name, enabled) => {
+                if let Some(unit) = self.units.iter().find(|unit| unit.name == name) {
+                    let result = if enabled {
+                        systemd::enable_unit(&self.unit_dirs, unit)
+                    } else {
+                        systemd::disable_unit(unit)
+                    }
+                    .and_then(|_| systemd::daemon_reload());
+
+                    if let Err(why) = result {
+                        eprintln!("failed to update {name}: {why}");
+                        let (unit, why) = (name.as_str(), why.to_string());
+                        self.unit_error = Some(match enabled {
+                            true => fl!("systemd-units", "enable-failed", unit = unit, why = why),
+                            false => fl!("systemd-units", "disable-failed", unit = unit, why = why),
+                        });
+                    }
+                }
+
+                self.units = get_user_units(&self.unit_dirs);
+            }
+            Message::ConvertToService(desktop_entry) => {
+                let directories: Vec<PathBuf> = DirectoryType::User.into();
+                let autostart_dir = directories.first().expect("Always at least one directory");
+
+                if let Err(why) = systemd::convert_entry_to_service(
+                    &self.unit_dirs,
+                    &desktop_entry,
+                    &self.locales,
+                    autostart_dir,
+                ) {
+                    eprintln!("failed to convert {} to a service: {why}", desktop_entry.appid);
+                    self.conversion_error = Some(fl!(
+                        "systemd-units",
+                        "to-service-failed",
+                        name = desktop_entry.appid.as_str(),
+                        why = why.to_string()
+                    ));
+                }
+
+                self.units = get_user_units(&self.unit_dirs);
+                self.reload_apps(DirectoryType::User);
+            }
+            Message::RefreshProcesses => {
+                return cosmic::task::future(async {
+                    Message::ProcessesScanned(processes::running_processes())
+                });
+            }
+            Message::ProcessesScanned(processes) => {
+                self.processes = processes;
+            }
+            Message::PackagesFound(packages) => {
+                self.packages = packages;
+            }
+            Message::StartNow(desktop_entry) => {
+                self.process_errors.remove(&desktop_entry.path);
+
+                if let Err(why) = processes::start(&desktop_entry) {
+                    eprintln!("failed to start {}: {why}", desktop_entry.path.display());
+                    self.process_errors.insert(
+                        desktop_entry.path,
+                        fl!("running", "start-failed", why = why.to_string()),
+                    );
+                }
+
+                return cosmic::task::message(Message::RefreshProcesses);
+            }
+            Message::Stop(path, pid) => {
+                self.process_errors.remove(&path);
+
+                if let Err(why) = processes::stop(pid) {
+                    eprintln!("failed to stop process {pid}: {why}");
+                    self.process_errors
+                        .insert(path, fl!("running", "stop-failed", pid = pid, why = why.to_string()));
+                }
+
+                return cosmic::task::message(Message::RefreshProcesses);
+            }
+            Message::LoginImpactActivate => {
+                self.impact = None;
+                self.impact_scanning = true;
+                self.context_page = ContextPage::LoginImpact;
+                self.core.window.show_context = true;
+
+                let mut entries = Vec::new();
+                for directory_type in [DirectoryType::User, DirectoryType::System, DirectoryType::Snap] {
+                    for app in self.apps_per_type.get(&directory_type).into_iter().flatten() {
+                        let name = match app.name(&self.locales) {
+                            Some(name) => name.to_string(),
+                            None => app.appid.to_owned(),
+                        };
+                        entries.push((name, app.clone()));
+                    }
+                }
+
+                return cosmic::task::future(async move {
+                    let processes = processes::running_processes();
+                    let system_uptime = procfs::read_uptime(Path::new("/proc")).unwrap_or_default();
+
+                    #[cfg(feature = "flatpak")]
+                    let leader = impact::host_session_leader();
+
+                    #[cfg(not(feature = "flatpak"))]
+                    let leader = impact::session_leader(Path::new("/run"), Path::new("/proc"));
+
+                    let entries: Vec<(String, &DesktopEntry)> =
+                        entries.iter().map(|(name, app)| (name.clone(), app)).collect();
+
+                    Message::LoginImpactScanned(leader.map(|leader| {
+                        impact::build_report(&entries, &processes, leader, system_uptime)
+                    }))
+                });
+            }
+            Message::LoginImpactScanned(rows) => {
+                self.impact = rows;
+                self.impact_scanning = false;
+                self.sort_impact();
+            }
+            Message::SortImpact(column) => {
+                self.impact_sort = match self.impact_sort {
+                    (current, descending) if current == column => (column, !descending),
+                    // the biggest numbers are the interesting ones
+                    _ => (column, column != ImpactColumn::Name),
+                };
+                self.sort_impact();
+            }
+            Message::RunNow(desktop_entry) => {
+                self.run_name = match desktop_entry.name(&self.locales) {
+                    Some(name) => name.to_string(),
+                    None => desktop_entry.appid.to_owned(),
+                };
+                self.run_output = None;
+                self.context_page = ContextPage::RunLog;
+                self.core.window.show_context = true;
+
+                return cosmic::task::future(async move {
+                    Message::RunFinished(runner::run_in_background(desktop_entry, runner::RUN_TIMEOUT).await)
+                });
+            }
+            Message::RunFinished(output) => {
+                self.run_output = Some(output);
+                return cosmic::task::message(Message::RefreshProcesses);
+            }
+            Message::ConvertUnitToEntry(name) => {
+                if let Some(unit) = self.units.iter().find(|unit| unit.name == name) {
+                    let directories: Vec<PathBuf> = DirectoryType::User.into();
+                    let autostart_dir = directories.first().expect("Always at least one directory");
+
+                    if let Err(why) = systemd::convert_service_to_entry(&self.unit_dirs, unit, autostart_dir) {
+                        eprintln!("failed to convert {name} to an autostart entry: {why}");
+                        self.conversion_error = Some(fl!(
+                            "systemd-units",
+                            "to-entry-failed",
+                            name = name.as_str(),
+                            why = why.to_string()
+                        ));
+                    }
+                }
+
+                self.units = get_user_units(&self.unit_dirs);
+                self.reload_apps(DirectoryType::User);
+            }
+            Message::ConversionErrorDismiss => {
+                self.conversion_error = None;
+            }
+            Message::UnitErrorDismiss => {
+                self.unit_error = None;
+            }
+            Message::AddVariable => {
+                self.variable_editor = Some(VariableEditor::new());
+                self.context_page = ContextPage::EditVariable;
+                self.core.window.show_context = true;
+            }
+            Message::EditVariable(path, lines) => {
+                let assignment = self
+                    .env_files
+                    .iter()
+                    .filter(|file| file.path == path)
+                    .flat_map(|file| &file.assignments)
+                    .find(|assignment| assignment.lines == lines);
+
+                if let Some(assignment) = assignment {
+                    self.variable_editor = Some(VariableEditor::edit(&path, assignment));
+                    self.context_page = ContextPage::EditVariable;
+                    self.core.window.show_context = true;
+                }
+            }
+            Message::RemoveVariable(path, lines) => {
+                if let Err(why) = environment::remove_variable(&path, lines) {
+                    eprintln!("failed to remove a variable from {}: {why}", path.display());
+                }
+
+                self.reload_environment();
+            }
+            Message::VariableEditor(variable_message) => {
+                let Some(editor) = &mut self.variable_editor else {
+                    return Task::none();
+                };
+
+                match variable_message {
+                    VariableMessage::Save => match editor.save(&self.env_dirs) {
+                        Ok(_) => {
+                            self.variable_editor = None;
+                            self.core.window.show_context = false;
+                            self.reload_environment();
+                        }
+                        Err(why) => {
+                            editor.error = Some(why);
+                        }
+                    },
+                    VariableMessage::Cancel => {
+                        self.variable_editor = None;
+                        self.core.window.show_context = false;
+                    }
+                    variable_message => editor.update(variable_message),
+                }
+            }
+            Message::MergedEnvironmentActivate => {
+                self.reload_environment();
+                return cosmic::task::message(Message::ToggleContextPage(ContextPage::MergedEnvironment));
+            }
+            Message::Editor(editor_message) => {
+                let Some(editor) = &mut self.editor else {
+                    return Task::none();
+                };
+
+                match editor_message {
+                    EditorMessage::Save => match editor.save(&self.terminals) {
+                        Ok(_) => {
+                            let directory_type = editor.directory_type.clone();
+                            self.editor = None;
+                            self.core.window.show_context = false;
+                            return cosmic::task::message(Message::RefreshApps(directory_type));
+                        }
+                        Err(why) => {
+                            editor.error = Some(why);
+                        }
+                    },
+                    EditorMessage::Cancel => {
+                        self.editor = None;
+                        self.core.window.show_context = false;
+                    }
+                    editor_message => editor.update(editor_message),
+                }
+            }
         }
         Task::none()
     }
@@ -581,7 +2472,16 @@ Exec={:?}", file_name, path);
 
         sections = sections.push(header);
 
-        let available_types = vec![DirectoryType::User, DirectoryType::System];
+        let mut available_types = vec![DirectoryType::User, DirectoryType::System];
+
+        // most systems have no snaps with autostart entries of their own
+        if self
+            .apps_per_type
+            .get(&DirectoryType::Snap)
+            .is_some_and(|apps| !apps.is_empty())
+        {
+            available_types.push(DirectoryType::Snap);
+        }
 
         for directory_type in available_types {
             let mut section = column().spacing(space_s);
@@ -595,6 +2495,10 @@ Exec={:?}", file_name, path);
                     fl!("system-applications"),
                     fl!("system-applications", "description"),
                 ),
+                DirectoryType::Snap => (
+                    fl!("snap-applications"),
+                    fl!("snap-applications", "description"),
+                ),
             };
 
             section = section.push(
@@ -621,6 +2525,7 @@ Exec={:?}", file_name, path);
                         };
 
                         let app_exec = app.exec().expect("invalid state");
+                        let (launch_options, command) = LaunchOptions::unwrap(&entry::split_exec(app_exec));
 
                         if search_input.is_empty()
                             || app_name.to_lowercase().contains(search_input)
@@ -641,10 +2546,114 @@ Exec={:?}", file_name, path);
 
                             name_col =
                                 name_col.push(widget::text::heading(app_name).width(Length::Fill));
-                            name_col = name_col.push(exec_line(String::from(app_exec)));
+                            name_col = name_col.push(exec_line(entry::join_exec(&command)));
+
+                            if !launch_options.conditions.is_empty() {
+                                let conditions = launch_options
+                                    .conditions
+                                    .iter()
+                                    .map(condition_label)
+                                    .collect::<Vec<String>>()
+                                    .join(", ");
+                                name_col = name_col.push(widget::text::caption(fl!(
+                                    "launch-conditions",
+                                    "summary",
+                                    conditions = conditions
+                                )));
+                            }
+
+                            if !launch_options.limits.is_empty() {
+                                name_col = name_col.push(widget::text::caption(fl!(
+                                    "resource-limits",
+                                    "summary",
+                                    limits = limits_label(&launch_options.limits)
+                                )));
+                            }
+
+                            if let Some(snap_name) = snap::autostart_snap(&app.path) {
+                                name_col = name_col.push(widget::text::caption(fl!(
+                                    "snap-applications",
+                                    "started-by",
+                                    snap = snap_name
+                                )));
+                            } else if let Some(origin) = self.origins.get(&app.path) {
+                                name_col = name_col.push(origin_badge(origin));
+                            }
+
+                            if let Some(package) = self.packages.get(&app.path) {
+                                name_col = name_col.push(widget::text::caption(fl!(
+                                    "origin",
+                                    "owned-by",
+                                    package = package.name.as_str(),
+                                    manager = package.manager.name()
+                                )));
+                            }
+
+                            if let Some(unit) = app.desktop_entry(systemd::UNIT_KEY) {
+                                name_col = name_col.push(widget::text::caption(fl!(
+                                    "systemd-units",
+                                    "runs-as-service",
+                                    unit = unit.to_string()
+                                )));
+                            }
+
+                            let process = processes::find_process(app, &self.processes);
+
+                            if let Some(process) = process {
+                                name_col = name_col.push(widget::text::caption(fl!(
+                                    "running",
+                                    "summary",
+                                    pid = process.pid,
+                                    uptime = processes::format_duration(process.uptime),
+                                    memory = process.rss / 1024
+                                )));
+                            }
+
+                            if let Some(why) = self.process_errors.get(&app.path) {
+                                name_col = name_col.push(widget::text::caption(why.clone()));
+                            }
 
                             row = row.push(name_col);
 
+                            row = row.push(match process {
+                                Some(process) => widget::tooltip(
+                                    button::icon(icon::from_name("media-playback-stop-symbolic"))
+                                        .extra_small()
+                                        .on_press(Message::Stop(app.path.clone(), process.pid)),
+                                    widget::text::body(fl!("running", "stop")),
+                                    widget::tooltip::Position::Bottom,
+                                ),
+                                None => widget::tooltip(
+                                    button::icon(icon::from_name("media-playback-start-symbolic"))
+                                        .extra_small()
+                                        .on_press(Message::StartNow(app.clone())),
+                                    widget::text::body(fl!("running", "start")),
+                                    widget::tooltip::Position::Bottom,
+                                ),
+                            });
+
+                            if directory_type == DirectoryType::Snap {
+                                row = row.push(
+                                    button::icon(icon::from_name("edit-delete-symbolic"))
+                                        .extra_small()
+                                        .on_press(Message::RemoveApplication(directory_type.clone(), app.clone())),
+                                );
+                            }
+
+                            if directory_type == DirectoryType::System {
+                                if let Some(chip) = self.status_chip(app) {
+                                    row = row.push(chip);
+                                }
+
+                                row = row.push(widget::tooltip(
+                                    button::icon(icon::from_name("system-run-symbolic"))
+                                        .extra_small()
+                                        .on_press(Message::ConvertToService(app.clone())),
+                                    widget::text::body(fl!("popover-menu", "convert-system-to-service")),
+                                    widget::tooltip::Position::Bottom,
+                                ));
+                            }
+
                             // actions
                             if is_user {
                                 let is_expanded = match self.popover_item {
@@ -664,10 +2673,21 @@ Exec={:?}", file_name, path);
                                     );
 
                                 if is_expanded {
+                                    let mut items = vec![
+                                        popover_item(idx, fl!("popover-menu", "run-now"), PopoverMessage::RunNow),
+                                        popover_item(idx, fl!("popover-menu", "edit"), PopoverMessage::Edit),
+                                    ];
+                                    if scripts::owned_script(app, &scripts::script_dirs()).is_some() {
+                                        items.push(popover_item(idx, fl!("popover-menu", "edit-script"), PopoverMessage::EditScript));
+                                    }
+                                    items.extend([
+                                        popover_item(idx, fl!("popover-menu", "history"), PopoverMessage::History),
+                                        popover_item(idx, fl!("popover-menu", "convert-to-service"), PopoverMessage::ConvertToService),
+                                        popover_item(idx, fl!("popover-menu", "view-in-files"), PopoverMessage::ViewInFiles),
+                                    ]);
+
                                     actions_row = actions_row.push(cosmic::widget::popover(more_button)
-                                        .popup(column::with_children(vec![
-                                            popover_item(idx, fl!("popover-menu", "view-in-files"), PopoverMessage::ViewInFiles),
-                                        ])
+                                        .popup(column::with_children(items)
                                             .padding([2, 8])
                                             .width(Length::Shrink)
                                             .height(Length::Shrink)
@@ -729,11 +2749,51 @@ Exec={:?}", file_name, path);
                     );
                 }
 
+                if search_input.is_empty() && is_user && !self.orphaned_scripts.is_empty() {
+                    let mut orphans = list_column().style(List).add(
+                        column()
+                            .push(widget::text::heading(fl!("orphaned-scripts")))
+                            .push(widget::text::caption(fl!("orphaned-scripts", "description"))),
+                    );
+
+                    for script in &self.orphaned_scripts {
+                        orphans = orphans.add(
+                            row()
+                                .spacing(space_xs)
+                                .align_y(Alignment::Center)
+                                .push(exec_line(script.display().to_string()).width(Length::Fill))
+                                .push(
+                                    button::text(fl!("orphaned-scripts", "add-entry"))
+                                        .on_press(Message::AddOrphanedScript(script.clone())),
+                                )
+                                .push(
+                                    button::icon(icon::from_name("edit-delete-symbolic"))
+                                        .extra_small()
+                                        .on_press(Message::DeleteOrphanedScript(script.clone())),
+                                ),
+                        );
+                    }
+
+                    section = section.push(orphans);
+                }
+
                 // @todo: get directory type
                 if search_input.is_empty() && is_user {
                     let controls = widget::container(
                         row()
                             .spacing(space_xs)
+                            .push(
+                                button::standard(fl!("open-at-login")).trailing_icon(
+                                    icon::from_name("document-open-symbolic"),
+                                )
+                                    .on_press(Message::OpenAtLoginActivate),
+                            )
+                            .push(
+                                button::standard(fl!("new-script")).trailing_icon(
+                                    icon::from_name("document-new-symbolic"),
+                                )
+                                    .on_press(Message::NewScriptActivate),
+                            )
                             .push(
                                 button::standard(fl!("add-script")).trailing_icon(
                                     icon::from_name("window-pop-out-symbolic"),
@@ -755,6 +2815,10 @@ Exec={:?}", file_name, path);
             sections = sections.push(section);
         }
 
+        sections = sections.push(self.units_section());
+
+        sections = sections.push(self.environment_section());
+
         sections = sections.push(vertical_space().height(Length::Fixed(64.0)));
 
         widget::container(
@@ -773,6 +2837,394 @@ Exec={:?}", file_name, path);
 }
 
 impl AppModel {
+    /// Looks up the application opening a typed or chosen target.
+    fn resolve_open_target(&self, target: Result<Target, String>) -> Result<(Target, DesktopEntry), String> {
+        let target = target?;
+        let installed = self.installed_apps.as_deref().unwrap_or_default();
+
+        match opener::find_handler(&target, installed) {
+            Some(handler) => {
+                let handler = handler.clone();
+                Ok((target, handler))
+            }
+            None => Err(fl!("open-at-login", "no-handler", mime = target.mime_type())),
+        }
+    }
+
+    /// Reloads the entries of a directory type from disk.
+    fn reload_apps(&mut self, directory_type: DirectoryType) {
+        self.apps_per_type.insert(
+            directory_type.clone(),
+            get_startup_applications(directory_type, self.locales.clone()),
+        );
+        self.refresh_origins();
+        self.refresh_autostart_status();
+        self.refresh_orphaned_scripts();
+    }
+
+    fn load_installed_apps(&mut self) {
+        if self.installed_apps.is_some() {
+            return;
+        }
+
+        self.installed_apps = Some(get_installed_applications(self.locales.clone()));
+        self.refresh_origins();
+    }
+
+    /// Works out where each listed entry comes from.
+    fn refresh_origins(&mut self) {
+        self.origins = self
+            .apps_per_type
+            .values()
+            .chain(self.installed_apps.iter())
+            .flatten()
+            .map(|desktop_entry| (desktop_entry.path.clone(), origin::classify(desktop_entry)))
+            .collect();
+    }
+
+    /// Looks up which distribution package owns each system entry, which means reading the
+    /// package databases, so it's done in the background.
+    fn find_packages(&self) -> Task<Message> {
+        // the databases list paths on the host
+        let paths: HashMap<PathBuf, PathBuf> = self
+            .apps_per_type
+            .get(&DirectoryType::System)
+            .into_iter()
+            .flatten()
+            .filter_map(|app| Some((scripts::host_location(&app.path)?, app.path.clone())))
+            .collect();
+
+        cosmic::task::future(async move {
+            let host_paths: Vec<PathBuf> = paths.keys().cloned().collect();
+            let owners = PackageDbs::from_system().owners(&host_paths);
+
+            Message::PackagesFound(
+                owners
+                    .into_iter()
+                    .filter_map(|(host_path, package)| Some((paths.get(&host_path)?.clone(), package)))
+                    .collect(),
+            )
+        })
+    }
+
+    fn refresh_orphaned_scripts(&mut self) {
+        let empty = Vec::new();
+        self.orphaned_scripts = scripts::find_orphans(
+            self.apps_per_type.get(&DirectoryType::User).unwrap_or(&empty),
+            &scripts::orphan_dirs(),
+        );
+    }
+
+    /// Rereads the environment.d files and works out the merged environment again.
+    fn reload_environment(&mut self) {
+        self.env_files = environment::load_files(&self.env_dirs);
+        self.env_variables = environment::merge(&self.env_files, &environment::base_environment());
+    }
+
+    fn refresh_autostart_status(&mut self) {
+        let empty = Vec::new();
+        self.autostart_status = simulate_login(
+            self.apps_per_type.get(&DirectoryType::User).unwrap_or(&empty),
+            self.apps_per_type.get(&DirectoryType::System).unwrap_or(&empty),
+            &self.session_desktops,
+        )
+        .into_iter()
+        .map(|autostart| (autostart.entry.path, autostart.decision))
+        .collect();
+    }
+
+    /// The section listing systemd user units started with the session.
+    fn units_section(&self) -> Element<Message> {
+        let cosmic::cosmic_theme::Spacing {
+            space_s,
+            space_xs,
+            ..
+        } = theme::active().cosmic().spacing;
+
+        let search_input = match &self.global_search {
+            None => String::new(),
+            Some(search) => search.trim().to_lowercase(),
+        };
+
+        let mut section = column().spacing(space_s).push(
+            column()
+                .push(widget::text::heading(fl!("systemd-units")).size(18.0))
+                .push(widget::text(fl!("systemd-units", "description"))),
+        );
+
+        let mut list_col = list_column().style(List);
+        let mut valid_units = 0;
+
+        for unit in &self.units {
+            let exec_start = unit.exec_start.clone().unwrap_or_default();
+
+            if !search_input.is_empty()
+                && !unit.display_name().to_lowercase().contains(&search_input)
+                && !unit.name.to_lowercase().contains(&search_input)
+                && !exec_start.to_lowercase().contains(&search_input)
+            {
+                continue;
+            }
+
+            valid_units += 1;
+
+            let mut name_col = column()
+                .align_x(Alignment::Start)
+                .push(widget::text::heading(unit.display_name().to_string()).width(Length::Fill))
+                .push(exec_line(exec_start));
+
+            let toggler = if unit.is_enabled_system_wide() {
+                // enabled from /etc or /usr, which we can't change as a user
+                name_col = name_col.push(widget::text::caption(fl!(
+                    "systemd-units",
+                    "enabled-system-wide",
+                    unit = unit.name.clone()
+                )));
+                widget::toggler(true)
+            } else {
+                name_col = name_col.push(widget::text::caption(unit.name.clone()));
+
+                let name = unit.name.clone();
+                widget::toggler(unit.is_enabled())
+                    .on_toggle(move |enabled| Message::ToggleUnit(name.clone(), enabled))
+            };
+
+            let mut unit_row = row::with_capacity(4)
+                .spacing(space_xs)
+                .align_y(Alignment::Center)
+                .push(icon::from_name("system-run-symbolic").size(32))
+                .push(name_col);
+
+            if unit.is_user_unit(&self.unit_dirs) && unit.exec_start.is_some() {
+                unit_row = unit_row.push(
+                    button::text(fl!("systemd-units", "convert-to-entry"))
+                        .on_press(Message::ConvertUnitToEntry(unit.name.clone())),
+                );
+            }
+
+            list_col = list_col.add(unit_row.push(toggler));
+        }
+
+        if valid_units > 0 {
+            section = section.push(list_col);
+        } else {
+            section = section.push(
+                list_column()
+                    .style(List)
+                    .add(widget::text::heading(fl!("no-units-found"))),
+            );
+        }
+
+        section.into()
+    }
+
+    fn environment_section(&self) -> Element<Message> {
+        let cosmic::cosmic_theme::Spacing {
+            space_s,
+            space_xs,
+            ..
+        } = theme::active().cosmic().spacing;
+
+        let search_input = match &self.global_search {
+            None => String::new(),
+            Some(search) => search.trim().to_lowercase(),
+        };
+
+        let mut section = column().spacing(space_s).push(
+            column()
+                .push(widget::text::heading(fl!("environment")).size(18.0))
+                .push(widget::text(fl!("environment", "description"))),
+        );
+
+        let mut list_col = list_column().style(List);
+        let mut valid_variables = 0;
+
+        for file in self.env_files.iter().filter(|file| file.is_user) {
+            for assignment in &file.assignments {
+                if !search_input.is_empty()
+                    && !assignment.key.to_lowercase().contains(&search_input)
+                    && !assignment.value.to_lowercase().contains(&search_input)
+                {
+                    continue;
+                }
+
+                valid_variables += 1;
+
+                let mut name_col = column()
+                    .align_x(Alignment::Start)
+                    .push(widget::text::heading(assignment.key.clone()).width(Length::Fill))
+                    .push(exec_line(assignment.value.clone()))
+                    .push(widget::text::caption(file.path.display().to_string()));
+
+                let merged = self
+                    .env_variables
+                    .iter()
+                    .find(|variable| variable.key == assignment.key);
+
+                let mut variable_row = row::with_capacity(5)
+                    .spacing(space_xs)
+                    .align_y(Alignment::Center)
+                    .push(icon::from_name("preferences-system-symbolic").size(32));
+
+                match merged {
+                    Some(variable)
+                        if variable.source == file.path && variable.assignment.lines == assignment.lines =>
+                    {
+                        variable_row = variable_row.push(name_col);
+                        if !variable.shadowed.is_empty() {
+                            variable_row = variable_row.push(shadowed_chip(variable));
+                        }
+                    }
+                    Some(variable) => {
+                        name_col = name_col.push(widget::text::caption(fl!(
+                            "environment",
+                            "overridden",
+                            path = variable.source.display().to_string()
+                        )));
+                        variable_row = variable_row.push(name_col);
+                    }
+                    None => variable_row = variable_row.push(name_col),
+                }
+
+                variable_row = variable_row
+                    .push(widget::tooltip(
+                        button::icon(icon::from_name("document-edit-symbolic"))
+                            .extra_small()
+                            .on_press(Message::EditVariable(file.path.clone(), assignment.lines.clone())),
+                        widget::text::body(fl!("environment", "edit")),
+                        widget::tooltip::Position::Bottom,
+                    ))
+                    .push(
+                        button::icon(icon::from_name("edit-delete-symbolic"))
+                            .extra_small()
+                            .on_press(Message::RemoveVariable(file.path.clone(), assignment.lines.clone())),
+                    );
+
+                list_col = list_col.add(variable_row);
+            }
+        }
+
+        if valid_variables > 0 {
+            section = section.push(list_col);
+        } else {
+            section = section.push(
+                list_column()
+                    .style(List)
+                    .add(widget::text::heading(fl!("environment", "empty"))),
+            );
+        }
+
+        for file in self.env_files.iter().filter(|file| !file.errors.is_empty()) {
+            let mut errors = column().push(widget::text::heading(fl!(
+                "environment",
+                "errors",
+                path = file.path.display().to_string()
+            )));
+
+            for error in &file.errors {
+                errors = errors.push(widget::text::caption(error.describe()));
+            }
+
+            section = section.push(errors);
+        }
+
+        if search_input.is_empty() {
+            section = section.push(
+                widget::container(
+                    row()
+                        .spacing(space_xs)
+                        .push(
+                            button::standard(fl!("environment", "merged"))
+                                .on_press(Message::MergedEnvironmentActivate),
+                        )
+                        .push(
+                            button::suggested(fl!("environment", "add"))
+                                .trailing_icon(icon::from_name("list-add-symbolic"))
+                                .on_press(Message::AddVariable),
+                        ),
+                )
+                .width(Length::Fill)
+                .align_x(Alignment::End),
+            );
+        }
+
+        section.into()
+    }
+
+    /// A chip summarising whether a system entry runs in this session, with a tooltip explaining
+    /// the rule which decided it.
+    fn status_chip(&self, app: &DesktopEntry) -> Option<Element<Message>> {
+        let decision = self.autostart_status.get(&app.path)?;
+
+        let desktops = self.session_desktops.join(":");
+
+        let (label, explanation, active) = match decision {
+            Decision::Launch => (
+                fl!("status", "active"),
+                fl!("status", "active-tooltip", desktops = desktops),
+                true,
+            ),
+            Decision::Skip(SkipReason::NotForDesktop) => (
+                fl!("status", "not-for-desktop"),
+                fl!(
+                    "status",
+                    "not-for-desktop-tooltip",
+                    desktops = desktops,
+                    only = app.only_show_in().map(|only| only.join(";")).unwrap_or_default(),
+                    not = app.not_show_in().map(|not| not.join(";")).unwrap_or_default()
+                ),
+                false,
+            ),
+            Decision::Skip(SkipReason::OverriddenByUser) => {
+                let directories: Vec<PathBuf> = DirectoryType::User.into();
+                let user_path = directories
+                    .first()
+                    .expect("Always at least one directory")
+                    .join(app.path.file_name().unwrap_or_default());
+
+                (
+                    fl!("status", "overridden-by-user"),
+                    fl!("status", "overridden-by-user-tooltip", path = user_path.display().to_string()),
+                    false,
+                )
+            }
+            Decision::Skip(SkipReason::MissingBinary(binary)) => (
+                fl!("status", "missing-binary"),
+                fl!("status", "missing-binary-tooltip", binary = binary.clone()),
+                false,
+            ),
+            Decision::Skip(reason) => (fl!("status", "inactive"), reason.describe(), false),
+        };
+
+        Some(
+            widget::tooltip(
+                chip(label, active),
+                widget::text::body(explanation),
+                widget::tooltip::Position::Bottom,
+            )
+            .into(),
+        )
+    }
+
+    fn sort_impact(&mut self) {
+        let (column, descending) = self.impact_sort;
+        if let Some(rows) = &mut self.impact {
+            impact::sort_rows(rows, column, descending);
+        }
+    }
+
+    /// Recomputes the login simulation for the desktops currently entered.
+    fn update_simulation(&mut self) {
+        let desktops = desktops::desktops_from_env(Some(&self.simulation_desktops), None);
+
+        let empty = Vec::new();
+        self.simulation = simulate_login(
+            self.apps_per_type.get(&DirectoryType::User).unwrap_or(&empty),
+            self.apps_per_type.get(&DirectoryType::System).unwrap_or(&empty),
+            &desktops,
+        );
+    }
+
     /// Updates the header and window titles.
     pub fn update_title(&mut self) -> Task<Message> {
         let window_title = fl!("app-title");
@@ -785,10 +3237,158 @@ impl AppModel {
     }
 }
 
+/// A small rounded label, coloured as a success if `active` and as a warning otherwise.
+fn chip<'a>(label: String, active: bool) -> Element<'a, Message> {
+    match active {
+        true => pill(label, |cosmic| &cosmic.success),
+        false => pill(label, |cosmic| &cosmic.warning),
+    }
+}
+
+/// A rounded label coloured with one of the theme's components.
+fn pill<'a>(
+    label: String,
+    component: fn(&cosmic::cosmic_theme::Theme) -> &cosmic::cosmic_theme::Component,
+) -> Element<'a, Message> {
+    widget::text::caption(label)
+        .apply(widget::container)
+        .padding([2, 8])
+        .class(theme::Container::custom(move |theme| {
+            let component = component(theme.cosmic());
+            let cosmic = theme.cosmic();
+
+            container::Style {
+                icon_color: Some(component.on.into()),
+                text_color: Some(component.on.into()),
+                background: Some(Color::from(component.base).into()),
+                border: Border {
+                    radius: cosmic.corner_radii.radius_xl.into(),
+                    ..Border::default()
+                },
+                shadow: Default::default(),
+            }
+        }))
+        .into()
+}
+
+/// Shows where an entry comes from, with the details in a tooltip.
+fn origin_badge<'a>(origin: &Origin) -> Element<'a, Message> {
+    let (label, explanation) = match origin {
+        Origin::Distro => (fl!("origin", "distro"), fl!("origin", "distro-tooltip")),
+        Origin::Flatpak(appid) => (
+            fl!("origin", "flatpak"),
+            fl!("origin", "flatpak-tooltip", appid = appid.as_str()),
+        ),
+        Origin::Snap(snap_name) => (
+            fl!("origin", "snap"),
+            fl!("origin", "snap-tooltip", snap = snap_name.as_str()),
+        ),
+        Origin::AppImage(image) => (
+            fl!("origin", "appimage"),
+            fl!("origin", "appimage-tooltip", path = image.display().to_string()),
+        ),
+        Origin::Script => (fl!("origin", "script"), fl!("origin", "script-tooltip")),
+        Origin::UserLocal => (fl!("origin", "user-local"), fl!("origin", "user-local-tooltip")),
+    };
+
+    widget::tooltip(
+        pill(label, |cosmic| &cosmic.accent),
+        widget::text::body(explanation),
+        widget::tooltip::Position::Bottom,
+    )
+    .into()
+}
+
+/// Flags a user variable which overrides system definitions, listing them in a tooltip.
+fn shadowed_chip<'a>(variable: &MergedVariable) -> Element<'a, Message> {
+    let overridden = variable
+        .shadowed
+        .iter()
+        .map(|shadowed| {
+            fl!(
+                "environment",
+                "overrides",
+                value = shadowed.value.clone(),
+                path = shadowed.path.display().to_string()
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    widget::tooltip(
+        chip(fl!("environment", "shadows-system"), false),
+        widget::text::body(overridden),
+        widget::tooltip::Position::Bottom,
+    )
+    .into()
+}
+
 fn exec_line<'a>(text: String) -> Text<'a, Theme, Renderer> {
     widget::text::monotext(text).size(10.0)
 }
 
+fn labeled_input<'a>(
+    label: String,
+    placeholder: &'a str,
+    value: &'a str,
+    on_input: impl Fn(String) -> Message + 'a,
+) -> Element<'a, Message> {
+    column()
+        .spacing(4)
+        .push(widget::text::caption(label))
+        .push(widget::text_input(placeholder, value).on_input(on_input))
+        .into()
+}
+
+fn limits_label(limits: &ResourceLimits) -> String {
+    let mut labels = Vec::new();
+
+    if let Some(nice) = limits.nice {
+        labels.push(fl!("resource-limits", "nice-label", nice = nice));
+    }
+    match limits.io_class {
+        Some(IoClass::Idle) => labels.push(fl!("resource-limits", "io-idle-label")),
+        Some(IoClass::BestEffort(level)) => {
+            labels.push(fl!("resource-limits", "io-best-effort-label", level = level))
+        }
+        None => {}
+    }
+    if let Some(memory_max) = &limits.memory_max {
+        labels.push(fl!("resource-limits", "memory-max-label", size = memory_max.clone()));
+    }
+    if let Some(cpu_quota) = limits.cpu_quota {
+        labels.push(fl!("resource-limits", "cpu-quota-label", quota = cpu_quota));
+    }
+
+    labels.join(", ")
+}
+
+fn condition_label(condition: &Condition) -> String {
+    match condition {
+        Condition::OnAcPower => fl!("condition-on-ac"),
+        Condition::NetworkUp(interface) => {
+            fl!("launch-conditions", "network-up", interface = interface.clone())
+        }
+        Condition::Hostname(pattern) => {
+            fl!("launch-conditions", "hostname", pattern = pattern.clone())
+        }
+        Condition::Weekdays(days) => {
+            fl!("launch-conditions", "weekdays", days = launcher::format_weekdays(days))
+        }
+        Condition::TimeWindow(start, end) => fl!(
+            "launch-conditions",
+            "time-window",
+            window = launcher::format_time_window(*start, *end)
+        ),
+        Condition::FileExists(path) => {
+            fl!("launch-conditions", "if-exists", path = path.display().to_string())
+        }
+        Condition::FileMissing(path) => {
+            fl!("launch-conditions", "unless-exists", path = path.display().to_string())
+        }
+    }
+}
+
 fn popover_item(idx: u32, label: String, message: PopoverMessage) -> Element<'static, Message> {
     widget::text::body(label)
         .apply(widget::container)
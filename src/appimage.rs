@@ -0,0 +1,591 @@
+// SPDX-License-Identifier: GPL-3
+
+//! Autostart entries for AppImages, which aren't installed anywhere `get_installed_applications`
+//! looks unless someone integrated them by hand.
+//!
+//! The `.desktop` file and icon inside the image are read from its squashfs payload, which starts
+//! right after the ELF runtime, with `unsquashfs`. Without it, the image's own runtime can extract
+//! them, but that means running the image, so it's only done when asked to.
+
+use crate::apps::host_command;
+use crate::entry;
+use crate::fl;
+use crate::scripts;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Component, Path, PathBuf};
+
+/// Marks autostart entries which start an AppImage, naming the image.
+pub const APPIMAGE_KEY: &str = "X-StartupConfiguration-AppImage";
+
+/// Runs the image without mounting it, for systems without FUSE.
+pub const EXTRACT_AND_RUN: &str = "--appimage-extract-and-run";
+
+/// What an AppImage's embedded `.desktop` file says about it.
+#[derive(Debug, Clone)]
+pub struct AppImage {
+    /// The image as seen by the app
+    pub path: PathBuf,
+
+    /// The image as seen by the host, which is what the entry runs
+    pub host_path: PathBuf,
+
+    pub name: String,
+    pub comment: Option<String>,
+
+    /// The extracted icon, copied next to the app's other data
+    pub icon: Option<PathBuf>,
+
+    /// Arguments the embedded entry passes to the image, without field codes
+    pub args: Vec<String>,
+
+    /// Whether the image can already be run, or has to be made executable when it's added
+    pub executable: bool,
+}
+
+/// How the files inside an image are read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extractor {
+    /// `unsquashfs` reads the payload without running anything
+    Unsquashfs,
+
+    /// The image's runtime extracts its own payload, which means making it executable and
+    /// running it
+    Runtime,
+}
+
+/// Whether `unsquashfs` from squashfs-tools is installed, so images can be read without running
+/// them.
+pub fn can_unsquashfs() -> bool {
+    host_command("unsquashfs")
+        .arg("-version")
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Whether the file at `path` is an AppImage, from the magic bytes in its ELF header.
+pub fn is_appimage(path: &Path) -> bool {
+    image_type(path).is_some()
+}
+
+/// Reads the name, icon and arguments out of the AppImage at `path`.
+///
+/// The runtime can only extract anything from an executable image, so with
+/// [`Extractor::Runtime`] the image is made executable for its owner first.
+pub fn inspect(path: &Path, extractor: Extractor) -> Result<AppImage, String> {
+    match image_type(path) {
+        Some(2) => {}
+        Some(_) => return Err(fl!("appimage", "unsupported-type")),
+        None => return Err(fl!("appimage", "not-appimage")),
+    }
+
+    let host_path = scripts::host_location(path).ok_or_else(|| fl!("appimage", "sandbox-only"))?;
+
+    let extract = match extractor {
+        Extractor::Unsquashfs => Extract::Unsquashfs {
+            offset: payload_offset(path).ok_or_else(|| fl!("appimage", "not-appimage"))?,
+        },
+        Extractor::Runtime => {
+            make_executable(path, &host_path)?;
+            Extract::Runtime
+        }
+    };
+
+    // the cache directory has the same path inside and outside the sandbox
+    let work_dir = dirs::cache_dir()
+        .unwrap_or_else(|| dirs::home_dir().unwrap_or_default().join(".cache"))
+        .join(format!("startup-configuration/appimage-{}", std::process::id()));
+    _ = fs::remove_dir_all(&work_dir);
+    fs::create_dir_all(&work_dir).map_err(|why| why.to_string())?;
+
+    let inspected = inspect_in(path, &host_path, &work_dir, extract);
+    _ = fs::remove_dir_all(&work_dir);
+
+    inspected
+}
+
+/// Makes the image executable for its owner, if it isn't already. This is done on the host, as
+/// the sandbox may only be able to read the image.
+fn make_executable(path: &Path, host_path: &Path) -> Result<(), String> {
+    let mode = fs::metadata(path).map_err(|why| why.to_string())?.permissions().mode();
+    if mode & 0o100 != 0 {
+        return Ok(());
+    }
+
+    let changed = host_command("chmod")
+        .arg("u+x")
+        .arg(host_path)
+        .status()
+        .is_ok_and(|status| status.success());
+
+    match changed {
+        true => Ok(()),
+        false => Err(fl!("appimage", "chmod-failed", path = host_path.display().to_string())),
+    }
+}
+
+/// An [`Extractor`] with what it needs to know about the image.
+#[derive(Debug, Clone, Copy)]
+enum Extract {
+    Unsquashfs { offset: u64 },
+    Runtime,
+}
+
+/// Extracts the files in the image matching a pattern into the `squashfs-root` being read.
+type Fetch<'a> = dyn FnMut(&str) -> Result<(), String> + 'a;
+
+fn inspect_in(path: &Path, host_path: &Path, work_dir: &Path, extract: Extract) -> Result<AppImage, String> {
+    let root = work_dir.join("squashfs-root");
+    let fetch: &mut Fetch = &mut |pattern| extract.run(host_path, work_dir, pattern);
+
+    let contents = desktop_file(&root, fetch)
+        .and_then(|desktop_file| fs::read_to_string(desktop_file).ok())
+        .ok_or_else(|| fl!("appimage", "no-desktop-file"))?;
+    let key = |key: &str| entry::get_key(&contents, key);
+
+    // `Exec` is unescaped as it's split, the rest here
+    let text = |name: &str| key(name).map(|value| entry::unescape_value(&value));
+
+    let name = text("Name").unwrap_or_else(|| {
+        path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    });
+
+    // the program in the embedded `Exec` is the image itself, usually named AppRun
+    let args = key("Exec")
+        .map(|exec| entry::strip_field_codes(&entry::split_exec(&exec)))
+        .map(|command| command.into_iter().skip(1).collect())
+        .unwrap_or_default();
+
+    let icon = key("Icon").and_then(|icon| copy_icon(&extracted_icon(&root, &icon, fetch)?, &icon, &name));
+    let executable = fs::metadata(path).is_ok_and(|metadata| metadata.permissions().mode() & 0o100 != 0);
+
+    Ok(AppImage {
+        path: path.to_path_buf(),
+        host_path: host_path.to_path_buf(),
+        name,
+        comment: text("Comment"),
+        icon,
+        args,
+        executable,
+    })
+}
+
+/// Extracts the image's top-level `.desktop` file, which is often a link into `usr/share`.
+fn desktop_file(root: &Path, fetch: &mut Fetch) -> Option<PathBuf> {
+    fetch("*.desktop").ok()?;
+
+    let link = fs::read_dir(root)
+        .ok()?
+        .flatten()
+        .map(|dir_entry| dir_entry.path())
+        .find(|path| path.extension().is_some_and(|extension| extension == "desktop"))?;
+
+    resolve(root, &link, fetch)
+}
+
+/// Extracts the icon the embedded entry names, or else the image's `.DirIcon`.
+fn extracted_icon(root: &Path, icon: &str, fetch: &mut Fetch) -> Option<PathBuf> {
+    // icons are named without an extension, and sit at the top of the image
+    if fetch(&format!("{icon}.*")).is_ok() {
+        let extracted = ["png", "svg", "xpm"]
+            .iter()
+            .map(|extension| root.join(format!("{icon}.{extension}")))
+            .filter(|path| path.symlink_metadata().is_ok())
+            .find_map(|path| resolve(root, &path, fetch));
+
+        if extracted.is_some() {
+            return extracted;
+        }
+    }
+
+    fetch(".DirIcon").ok()?;
+    resolve(root, &root.join(".DirIcon"), fetch)
+}
+
+/// Follows `path`, an extracted file under `root`, through any links inside the image, extracting
+/// each target, until it reaches a regular file.
+fn resolve(root: &Path, path: &Path, fetch: &mut Fetch) -> Option<PathBuf> {
+    let mut path = path.to_path_buf();
+
+    // a loop of links would never end
+    for _ in 0..8 {
+        let metadata = path.symlink_metadata().ok()?;
+        if !metadata.is_symlink() {
+            return metadata.is_file().then_some(path);
+        }
+
+        let target = link_target(path.strip_prefix(root).ok()?, &fs::read_link(&path).ok()?)?;
+        fetch(&target.to_string_lossy()).ok()?;
+        path = root.join(target);
+    }
+
+    None
+}
+
+/// Where a link at `link` pointing at `target` leads, both relative to the image's root. Absolute
+/// targets are taken to be inside the image, and nothing may lead out of it.
+fn link_target(link: &Path, target: &Path) -> Option<PathBuf> {
+    let start = match target.has_root() {
+        true => Path::new(""),
+        false => link.parent()?,
+    };
+
+    let mut resolved = PathBuf::new();
+    for component in start.components().chain(target.components()) {
+        match component {
+            Component::Normal(name) => resolved.push(name),
+            Component::ParentDir => {
+                if !resolved.pop() {
+                    return None;
+                }
+            }
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+        }
+    }
+
+    (!resolved.as_os_str().is_empty()).then_some(resolved)
+}
+
+/// The extension an extracted icon is kept with, from its name or else its contents, as
+/// `.DirIcon` has none.
+fn icon_extension(icon: &Path) -> Option<&'static str> {
+    let named = icon.extension().and_then(|extension| {
+        ["png", "svg", "xpm"]
+            .into_iter()
+            .find(|known| extension.eq_ignore_ascii_case(known))
+    });
+    if named.is_some() {
+        return named;
+    }
+
+    let head = fs::read(icon).ok()?;
+    let head = &head[..head.len().min(256)];
+
+    if head.starts_with(b"\x89PNG") {
+        Some("png")
+    } else if head.windows(4).any(|window| window == b"<svg") {
+        Some("svg")
+    } else if head.starts_with(b"/* XPM */") {
+        Some("xpm")
+    } else {
+        None
+    }
+}
+
+/// Keeps a copy of the extracted icon which outlives the extraction.
+fn copy_icon(extracted: &Path, icon: &str, name: &str) -> Option<PathBuf> {
+    let icons_dir = dirs::data_dir()
+        .unwrap_or_else(|| dirs::home_dir().unwrap_or_default().join(".local/share"))
+        .join("startup-configuration/icons");
+    let target = icons_dir.join(format!(
+        "{}.{}",
+        scripts::file_name_for(name).unwrap_or_else(|| icon.to_string()),
+        icon_extension(extracted)?
+    ));
+
+    fs::create_dir_all(&icons_dir).ok()?;
+    fs::copy(extracted, &target).ok()?;
+
+    Some(target)
+}
+
+impl Extract {
+    /// Extracts the files in the image matching `pattern`, a path from its root which may use
+    /// wildcards, into `work_dir/squashfs-root`.
+    fn run(self, host_path: &Path, work_dir: &Path, pattern: &str) -> Result<(), String> {
+        let mut command = match self {
+            Extract::Unsquashfs { offset } => {
+                let mut command = host_command("unsquashfs");
+                command
+                    .args(["-no-progress", "-no-xattrs", "-f", "-o"])
+                    .arg(offset.to_string())
+                    .arg("-d")
+                    .arg(work_dir.join("squashfs-root"))
+                    .arg(host_path);
+                command
+            }
+            Extract::Runtime => {
+                let mut command = host_command(&host_path.display().to_string());
+                command.arg("--appimage-extract");
+                command
+            }
+        };
+
+        let output = command
+            .arg(pattern)
+            .current_dir(work_dir)
+            .output()
+            .map_err(|why| fl!("appimage", "extract-failed", why = why.to_string()))?;
+
+        match output.status.success() {
+            true => Ok(()),
+            false => Err(fl!(
+                "appimage",
+                "extract-failed",
+                why = String::from_utf8_lossy(&output.stderr).trim().to_string()
+            )),
+        }
+    }
+}
+
+/// Writes an autostart entry to `autostart_dir` which starts `appimage`, returning its path.
+///
+/// The image has to be executable to start at login, so it's made executable if it isn't yet.
+pub fn write_entry(
+    appimage: &AppImage,
+    extract_and_run: bool,
+    autostart_dir: &Path,
+) -> Result<PathBuf, String> {
+    let file_name = scripts::file_name_for(&appimage.name).ok_or_else(|| fl!("new-script", "no-name"))?;
+    let entry_path = autostart_dir.join(format!("{file_name}.desktop"));
+
+    let command: Vec<String> = std::iter::once(appimage.host_path.display().to_string())
+        .chain(extract_and_run.then(|| EXTRACT_AND_RUN.to_string()))
+        .chain(appimage.args.iter().cloned())
+        // a lone `%` would be taken for a field code
+        .map(|arg| arg.replace('%', "%%"))
+        .collect();
+
+    let mut entry_text = format!(
+        "[Desktop Entry]\nType=Application\nName={}\nExec={}\n",
+        entry::escape_value(&appimage.name),
+        entry::escape_value(&entry::join_exec(&command))
+    );
+    if let Some(comment) = &appimage.comment {
+        entry_text.push_str(&format!("Comment={}\n", entry::escape_value(comment)));
+    }
+    if let Some(icon) = &appimage.icon {
+        entry_text.push_str(&format!("Icon={}\n", entry::escape_value(&icon.display().to_string())));
+    }
+    entry_text.push_str(&format!(
+        "{APPIMAGE_KEY}={}\n",
+        entry::escape_value(&appimage.host_path.display().to_string())
+    ));
+
+    make_executable(&appimage.path, &appimage.host_path)?;
+    fs::create_dir_all(autostart_dir).map_err(|why| why.to_string())?;
+
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&entry_path)
+        .and_then(|mut file| file.write_all(entry_text.as_bytes()))
+        .map_err(|why| match why.kind() {
+            io::ErrorKind::AlreadyExists => fl!("new-script", "exists", name = file_name.clone()),
+            _ => why.to_string(),
+        })?;
+
+    Ok(entry_path)
+}
+
+/// The AppImage type from the `AI` magic at offset 8 of the ELF header, if it has one.
+fn image_type(path: &Path) -> Option<u8> {
+    let mut head = Vec::with_capacity(11);
+    let file = fs::File::open(path).ok()?;
+    io::Read::read_to_end(&mut io::Read::take(file, 11), &mut head).ok()?;
+
+    match head.as_slice() {
+        [0x7f, b'E', b'L', b'F', _, _, _, _, b'A', b'I', image_type] => Some(*image_type),
+        _ => None,
+    }
+}
+
+/// Where the squashfs payload starts, which is the end of the ELF runtime: after its section
+/// header table, the last part of the file the ELF header describes.
+fn payload_offset(path: &Path) -> Option<u64> {
+    let mut header = Vec::with_capacity(64);
+    let file = fs::File::open(path).ok()?;
+    io::Read::read_to_end(&mut io::Read::take(file, 64), &mut header).ok()?;
+
+    let little_endian = match header.get(5)? {
+        1 => true,
+        2 => false,
+        _ => return None,
+    };
+    let number = |range: std::ops::Range<usize>| -> Option<u64> {
+        let bytes = header.get(range)?;
+        let fold = |number: u64, byte: &u8| number << 8 | u64::from(*byte);
+
+        Some(match little_endian {
+            true => bytes.iter().rev().fold(0, fold),
+            false => bytes.iter().fold(0, fold),
+        })
+    };
+
+    // 32 and 64 bit headers place the section header table fields differently
+    let (table, entry_size, entries) = match header.get(4)? {
+        1 => (number(0x20..0x24)?, number(0x2e..0x30)?, number(0x30..0x32)?),
+        2 => (number(0x28..0x30)?, number(0x3a..0x3c)?, number(0x3c..0x3e)?),
+        _ => return None,
+    };
+
+    table.checked_add(entry_size.checked_mul(entries)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    /// A fresh directory holding an unpacked `image` and the `squashfs-root` it's extracted to.
+    fn fixture_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("appimage-{name}-{}", std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("image")).unwrap();
+        dir
+    }
+
+    fn write(path: PathBuf, contents: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn link(path: PathBuf, target: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        symlink(target, path).unwrap();
+    }
+
+    /// Every file and link in `dir`, relative to `image`.
+    fn files(image: &Path, dir: &Path) -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .flat_map(|dir_entry| match dir_entry.file_type().unwrap().is_dir() {
+                true => files(image, &dir_entry.path()),
+                false => vec![dir_entry.path().strip_prefix(image).unwrap().to_path_buf()],
+            })
+            .collect()
+    }
+
+    /// Extracts like `unsquashfs` does: a wildcard only matches files at the top of the image, and
+    /// links are extracted as links.
+    fn extract_from(image: PathBuf, root: PathBuf) -> impl FnMut(&str) -> Result<(), String> {
+        move |pattern| {
+            let matches = |file: &str| match pattern.split_once('*') {
+                Some((prefix, suffix)) => !file.contains('/') && file.starts_with(prefix) && file.ends_with(suffix),
+                None => file == pattern,
+            };
+
+            for file in files(&image, &image).into_iter().filter(|file| matches(&file.to_string_lossy())) {
+                let (from, to) = (image.join(&file), root.join(&file));
+                fs::create_dir_all(to.parent().unwrap()).unwrap();
+                _ = fs::remove_file(&to);
+
+                match fs::read_link(&from) {
+                    Ok(target) => symlink(target, to).unwrap(),
+                    Err(_) => {
+                        fs::copy(from, to).unwrap();
+                    }
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn symlinked_desktop_file_and_icon() {
+        let dir = fixture_dir("symlinked");
+        let (image, root) = (dir.join("image"), dir.join("squashfs-root"));
+        write(
+            image.join("usr/share/applications/foo.desktop"),
+            b"[Desktop Entry]\nName=Foo\nExec=foo %U\nIcon=foo\n",
+        );
+        link(image.join("foo.desktop"), "usr/share/applications/foo.desktop");
+        write(image.join("usr/share/icons/hicolor/256x256/apps/foo.png"), b"\x89PNG\r\n");
+        link(image.join("foo.png"), "usr/share/icons/hicolor/256x256/apps/foo.png");
+        link(image.join(".DirIcon"), "foo.png");
+        let fetch: &mut Fetch = &mut extract_from(image, root.clone());
+
+        let desktop_file = desktop_file(&root, fetch).unwrap();
+        assert_eq!(desktop_file, root.join("usr/share/applications/foo.desktop"));
+        assert_eq!(
+            entry::get_key(&fs::read_to_string(desktop_file).unwrap(), "Name").as_deref(),
+            Some("Foo")
+        );
+
+        let icon = extracted_icon(&root, "foo", fetch).unwrap();
+        assert_eq!(icon, root.join("usr/share/icons/hicolor/256x256/apps/foo.png"));
+        assert_eq!(icon_extension(&icon), Some("png"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn dir_icon_fallback() {
+        let dir = fixture_dir("dir-icon");
+        let (image, root) = (dir.join("image"), dir.join("squashfs-root"));
+        write(image.join("bar.desktop"), b"[Desktop Entry]\nName=Bar\nIcon=bar\n");
+        write(image.join("usr/share/bar/logo"), b"<?xml version=\"1.0\"?>\n<svg/>\n");
+        link(image.join(".DirIcon"), "/usr/share/bar/logo");
+        let fetch: &mut Fetch = &mut extract_from(image, root.clone());
+
+        assert_eq!(desktop_file(&root, fetch), Some(root.join("bar.desktop")));
+
+        let icon = extracted_icon(&root, "bar", fetch).unwrap();
+        assert_eq!(icon, root.join("usr/share/bar/logo"));
+        assert_eq!(icon_extension(&icon), Some("svg"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn dangling_and_looping_links() {
+        let dir = fixture_dir("dangling");
+        let (image, root) = (dir.join("image"), dir.join("squashfs-root"));
+        link(image.join("gone.desktop"), "usr/share/applications/gone.desktop");
+        link(image.join("baz.png"), "baz.svg");
+        link(image.join("baz.svg"), "baz.png");
+        let fetch: &mut Fetch = &mut extract_from(image, root.clone());
+
+        assert_eq!(desktop_file(&root, fetch), None);
+        assert_eq!(extracted_icon(&root, "baz", fetch), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn written_entries_read_back() {
+        let dir = fixture_dir("written");
+        let image = dir.join("My $App \"1\".AppImage");
+        write(image.clone(), b"");
+        fs::set_permissions(&image, fs::Permissions::from_mode(0o755)).unwrap();
+        let appimage = AppImage {
+            path: image.clone(),
+            host_path: image.clone(),
+            name: "My \\ App".into(),
+            comment: Some("Line one\nline two".into()),
+            icon: None,
+            args: vec!["--volume=100%".into(), "`date`".into()],
+            executable: true,
+        };
+
+        let written = write_entry(&appimage, true, &dir.join("autostart")).unwrap();
+        let contents = fs::read_to_string(written).unwrap();
+        let text = |key: &str| entry::unescape_value(&entry::get_key(&contents, key).unwrap());
+
+        assert_eq!(text("Name"), appimage.name);
+        assert_eq!(text("Comment"), "Line one\nline two");
+        assert_eq!(text(APPIMAGE_KEY), image.display().to_string());
+        assert_eq!(
+            entry::strip_field_codes(&entry::split_exec(&entry::get_key(&contents, "Exec").unwrap())),
+            [&image.display().to_string(), EXTRACT_AND_RUN, "--volume=100%", "`date`"]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn link_targets() {
+        let target = |link: &str, target: &str| link_target(Path::new(link), Path::new(target));
+
+        assert_eq!(target("foo.desktop", "usr/share/foo.desktop"), Some("usr/share/foo.desktop".into()));
+        assert_eq!(target("usr/share/foo.png", "../icons/./foo.png"), Some("usr/icons/foo.png".into()));
+        assert_eq!(target("usr/share/foo.png", "/usr/foo.png"), Some("usr/foo.png".into()));
+        assert_eq!(target("foo.png", "../foo.png"), None);
+        assert_eq!(target("usr/foo", ".."), None);
+    }
+}
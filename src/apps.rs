@@ -1,7 +1,10 @@
+use crate::desktops;
+use crate::snap;
 use freedesktop_desktop_entry as fde;
 use freedesktop_desktop_entry::DesktopEntry;
 use std::{env, fs};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::time::Instant;
 
 const AUTOSTART: &'static str = "autostart";
@@ -13,6 +16,9 @@ pub enum DirectoryType {
 
     /// System directories
     System,
+
+    /// Each snap's own autostart directory, started by `snap userd --autostart`
+    Snap,
 }
 
 impl Into<Vec<PathBuf>> for DirectoryType {
@@ -48,10 +54,92 @@ impl Into<Vec<PathBuf>> for DirectoryType {
 
                 vec
             }
+            DirectoryType::Snap => snap::user_autostart_dirs(),
+        }
+    }
+}
+
+/// Returns where a path on the host can be found from inside the sandbox.
+pub fn host_path(path: &Path) -> PathBuf {
+    // when running as a flatpak, /usr and /etc are mounted under /run/host
+    #[cfg(feature = "flatpak")]
+    if path.starts_with("/usr/") || path.starts_with("/etc/") {
+        return PathBuf::from("/run/host/").join(path.strip_prefix("/").expect("This should never fail"));
+    }
+
+    path.to_path_buf()
+}
+
+/// Builds a command which runs `program` in the user's session, outside the sandbox if need be.
+pub fn host_command(program: &str) -> Command {
+    host_command_in(program, None)
+}
+
+/// Like [`host_command`], but running `program` in `dir`, a directory on the host.
+pub fn host_command_in(program: &str, dir: Option<&Path>) -> Command {
+    #[cfg(feature = "flatpak")]
+    {
+        // flatpak-spawn's own working directory isn't passed on to the host
+        let mut command = Command::new("flatpak-spawn");
+        command.arg("--host");
+        if let Some(dir) = dir {
+            command.arg(format!("--directory={}", dir.display()));
+        }
+        command.arg(program);
+        command
+    }
+
+    #[cfg(not(feature = "flatpak"))]
+    {
+        let mut command = Command::new(program);
+        if let Some(dir) = dir {
+            command.current_dir(dir);
         }
+        command
     }
 }
 
+/// Looks up an executable the way the session would, either as a path or through `$PATH`.
+pub fn find_executable(name: &str) -> Option<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let is_executable = |path: &Path| {
+        fs::metadata(path)
+            .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+    };
+
+    if name.contains('/') {
+        let path = host_path(Path::new(name));
+        return is_executable(&path).then_some(path);
+    }
+
+    // the sandbox's $PATH says nothing about the host, so fall back to the usual directories
+    #[cfg(feature = "flatpak")]
+    let search_path = {
+        let home = dirs::home_dir().expect("home dir not found");
+        vec![
+            home.join(".local/bin"),
+            PathBuf::from("/usr/local/bin"),
+            PathBuf::from("/usr/bin"),
+            PathBuf::from("/usr/sbin"),
+            home.join(".local/share/flatpak/exports/bin"),
+            PathBuf::from("/var/lib/flatpak/exports/bin"),
+            PathBuf::from("/snap/bin"),
+        ]
+    };
+
+    #[cfg(not(feature = "flatpak"))]
+    let search_path = env::split_paths(
+        &env::var_os("PATH").unwrap_or_else(|| "/usr/local/bin:/usr/bin:/bin".into()),
+    )
+    .collect::<Vec<PathBuf>>();
+
+    search_path
+        .iter()
+        .map(|dir| host_path(&dir.join(name)))
+        .find(|path| is_executable(path))
+}
+
 pub fn get_installed_applications(locales: Vec<String>) -> Vec<DesktopEntry> {
     let mut dedup = std::collections::HashSet::new();
 
@@ -73,14 +161,18 @@ pub fn get_installed_applications(locales: Vec<String>) -> Vec<DesktopEntry> {
     }
 
     #[cfg(feature = "flatpak")]
-    {
-        valid_paths.push(dirs::home_dir().expect("home dir not found").join(".local/share/applications"));
-        valid_paths.push(PathBuf::from("/var/lib/snapd/desktop/applications"));
+    valid_paths.push(dirs::home_dir().expect("home dir not found").join(".local/share/applications"));
+
+    // snapd only adds its directory to $XDG_DATA_DIRS through a profile script, which not every
+    // session runs
+    let snap_applications = host_path(Path::new(snap::SNAP_APPLICATIONS_DIR));
+    if !valid_paths.contains(&snap_applications) {
+        valid_paths.push(snap_applications);
     }
 
     let entries = fde::Iter::new(valid_paths.into_iter()).entries(Some(&locales));
 
-    let current_desktop = env::var("XDG_SESSION_DESKTOP");
+    let current_desktops = desktops::current_desktops();
 
     let mut res = Vec::new();
 
@@ -99,19 +191,9 @@ pub fn get_installed_applications(locales: Vec<String>) -> Vec<DesktopEntry> {
             continue;
         }
 
-        // match based off of current desktop environment if it exists
-        if let Ok(ref desktop_str) = current_desktop {
-            if let Some(only_show_in) = entry.only_show_in() {
-                if !only_show_in.contains(&desktop_str.as_str()) {
-                    continue;
-                }
-            }
-
-            if let Some(not_show_in) = entry.not_show_in() {
-                if not_show_in.contains(&desktop_str.as_str()) {
-                    continue;
-                }
-            }
+        // match against the desktops of the current session
+        if !desktops::entry_shown_in(&entry, &current_desktops) {
+            continue;
         }
 
         res.push(entry.clone());
@@ -160,19 +242,9 @@ pub fn get_installed_applications(locales: Vec<String>) -> Vec<DesktopEntry> {
                 continue;
             }
 
-            // match based off of current desktop environment if it exists
-            if let Ok(ref desktop_str) = current_desktop {
-                if let Some(only_show_in) = entry.only_show_in() {
-                    if !only_show_in.contains(&desktop_str.as_str()) {
-                        continue;
-                    }
-                }
-
-                if let Some(not_show_in) = entry.not_show_in() {
-                    if not_show_in.contains(&desktop_str.as_str()) {
-                        continue;
-                    }
-                }
+            // match against the desktops of the current session
+            if !desktops::entry_shown_in(&entry, &current_desktops) {
+                continue;
             }
 
             res.push(entry.clone());
@@ -0,0 +1,137 @@
+// SPDX-License-Identifier: GPL-3
+
+//! Checks an autostart entry's conditions and, if they all hold, replaces itself with the
+//! entry's real command, started with any resource limits applied.
+//!
+//! With `--record ID` it instead stays around until the command exits, so the launch can be
+//! added to the entry's history along with what the command used in its first minute.
+//!
+//! Usage: `startup-configuration-launcher [OPTIONS] -- COMMAND [ARGS...]`
+
+use startup_configuration::history::{self, LaunchRecord, Outcome};
+use startup_configuration::launcher::{LaunchOptions, Probe};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Command, ExitCode};
+use std::time::Instant;
+
+#[cfg(not(feature = "flatpak"))]
+use startup_configuration::{history::Usage, procfs};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let (options, command) = match LaunchOptions::parse(&args) {
+        Ok(parsed) => parsed,
+        Err(why) => {
+            eprintln!("startup-configuration-launcher: {why}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let Some((program, _)) = command.split_first() else {
+        eprintln!("startup-configuration-launcher: no command given");
+        return ExitCode::from(2);
+    };
+
+    let record = |record: &LaunchRecord| {
+        if let Some(id) = &options.record {
+            if let Err(why) = history::append(&history::history_dir(), id, record) {
+                eprintln!("startup-configuration-launcher: failed to record launch of {id}: {why}");
+            }
+        }
+    };
+
+    let probe = Probe::from_system();
+    if let Some(condition) = options.failed_condition(&probe) {
+        eprintln!("startup-configuration-launcher: not starting {program}, condition not met: {condition}");
+        record(&LaunchRecord::now(None, Outcome::Skipped(condition.to_string())));
+        return ExitCode::SUCCESS;
+    }
+
+    let limited = options.limits.apply(&command);
+    let mut process = host_command(&limited);
+
+    if options.record.is_none() {
+        let why = process.exec();
+        eprintln!("startup-configuration-launcher: failed to start {program}: {why}");
+        return ExitCode::from(127);
+    }
+
+    let started = Instant::now();
+    let mut child = match process.spawn() {
+        Ok(child) => child,
+        Err(why) => {
+            eprintln!("startup-configuration-launcher: failed to start {program}: {why}");
+            record(&LaunchRecord::now(None, Outcome::FailedToStart(why.to_string())));
+            return ExitCode::from(127);
+        }
+    };
+
+    let mut launch = LaunchRecord::now(Some(child.id()), Outcome::Unfinished);
+    record(&launch);
+
+    // inside the sandbox the child is flatpak-spawn, whose usage says nothing about the command
+    #[cfg(not(feature = "flatpak"))]
+    if let Some(id) = options.record.clone() {
+        let launch = launch.clone();
+        let pid = child.id();
+        std::thread::spawn(move || {
+            std::thread::sleep(history::SAMPLE_AFTER);
+
+            let dir = std::path::Path::new("/proc").join(pid.to_string());
+            let Some(stat) = procfs::read_stat(&dir) else {
+                // it already exited
+                return;
+            };
+
+            let usage = Usage {
+                cpu: stat.cpu,
+                peak_rss: procfs::read_status_kib(&dir, "VmHWM").unwrap_or_default(),
+            };
+
+            if let Err(why) = history::append_sample(&history::history_dir(), &id, &launch, usage) {
+                eprintln!("startup-configuration-launcher: failed to record usage of {id}: {why}");
+            }
+        });
+    }
+
+    let status = match child.wait() {
+        Ok(status) => status,
+        Err(why) => {
+            eprintln!("startup-configuration-launcher: failed to wait for {program}: {why}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let duration = started.elapsed();
+    launch.outcome = match (status.code(), status.signal()) {
+        (_, Some(signal)) => Outcome::Signalled { signal, duration },
+        (code, None) => Outcome::Exited {
+            code: code.unwrap_or(-1),
+            duration,
+        },
+    };
+    record(&launch);
+
+    // like a shell, report death by a signal as 128 + the signal
+    ExitCode::from(status.code().unwrap_or_else(|| 128 + status.signal().unwrap_or(0)) as u8)
+}
+
+/// Builds the command to run, which inside the sandbox has to be started on the host.
+fn host_command(command: &[String]) -> Command {
+    let (program, args) = command.split_first().expect("command is never empty");
+
+    #[cfg(feature = "flatpak")]
+    {
+        let mut process = Command::new("flatpak-spawn");
+        process.arg("--host").arg(program).args(args);
+        process
+    }
+
+    #[cfg(not(feature = "flatpak"))]
+    {
+        let mut process = Command::new(program);
+        process.args(args);
+        process
+    }
+}
@@ -0,0 +1,292 @@
+// SPDX-License-Identifier: GPL-3
+
+//! Desktop environment names, matching of `OnlyShowIn`/`NotShowIn` against the current session
+//! and the `AutostartCondition` key.
+
+use crate::fl;
+use freedesktop_desktop_entry::DesktopEntry;
+use std::env;
+use std::fmt;
+
+/// Desktop names registered in the freedesktop menu spec, plus COSMIC.
+pub const KNOWN_DESKTOPS: &[&str] = &[
+    "COSMIC",
+    "GNOME",
+    "GNOME-Classic",
+    "GNOME-Flashback",
+    "KDE",
+    "LXDE",
+    "LXQt",
+    "MATE",
+    "Razor",
+    "ROX",
+    "TDE",
+    "Unity",
+    "XFCE",
+    "EDE",
+    "Cinnamon",
+    "Pantheon",
+    "Budgie",
+    "Enlightenment",
+    "DDE",
+    "Endless",
+    "Old",
+];
+
+/// Returns the desktops of the current session, most specific first.
+pub fn current_desktops() -> Vec<String> {
+    desktops_from_env(
+        env::var("XDG_CURRENT_DESKTOP").ok().as_deref(),
+        env::var("XDG_SESSION_DESKTOP")
+            .or_else(|_| env::var("DESKTOP_SESSION"))
+            .ok()
+            .as_deref(),
+    )
+}
+
+/// Builds the list of session desktops from `$XDG_CURRENT_DESKTOP`, a colon separated list.
+///
+/// That variable is sometimes unset, so the single session name is used as a fallback. Session
+/// names are often lowercase (`cosmic`, `gnome`, `plasma`), so they're mapped onto the registered
+/// desktop names where possible.
+pub fn desktops_from_env(current_desktop: Option<&str>, session_desktop: Option<&str>) -> Vec<String> {
+    let desktops: Vec<String> = current_desktop
+        .unwrap_or_default()
+        .split(':')
+        .map(str::trim)
+        .filter(|desktop| !desktop.is_empty())
+        .map(String::from)
+        .collect();
+
+    if !desktops.is_empty() {
+        return desktops;
+    }
+
+    let Some(session) = session_desktop.map(str::trim).filter(|session| !session.is_empty()) else {
+        return Vec::new();
+    };
+
+    let session = match session.to_lowercase().as_str() {
+        "plasma" | "plasmawayland" | "plasmax11" | "kde-plasma" => "KDE".to_string(),
+        "gnome-xorg" | "gnome-wayland" | "ubuntu" => "GNOME".to_string(),
+        lowercase => KNOWN_DESKTOPS
+            .iter()
+            .find(|known| known.to_lowercase() == lowercase)
+            .map(|known| known.to_string())
+            .unwrap_or_else(|| session.to_string()),
+    };
+
+    vec![session]
+}
+
+/// Decides whether an entry with the given `OnlyShowIn` and `NotShowIn` lists applies to a
+/// session running `desktops`.
+///
+/// Following the desktop entry spec, the session's desktops are checked in order and the first
+/// one named in either list decides. If none of them is named, the entry applies unless it has an
+/// `OnlyShowIn` list.
+pub fn shown_in(only_show_in: Option<&[&str]>, not_show_in: Option<&[&str]>, desktops: &[String]) -> bool {
+    for desktop in desktops {
+        if only_show_in.is_some_and(|only| only.contains(&desktop.as_str())) {
+            return true;
+        }
+
+        if not_show_in.is_some_and(|not| not.contains(&desktop.as_str())) {
+            return false;
+        }
+    }
+
+    only_show_in.is_none()
+}
+
+/// [`shown_in`] for a parsed desktop entry.
+pub fn entry_shown_in(entry: &DesktopEntry, desktops: &[String]) -> bool {
+    shown_in(
+        entry.only_show_in().as_deref(),
+        entry.not_show_in().as_deref(),
+        desktops,
+    )
+}
+
+/// Parses a user supplied list of desktops (separated by `;`, `,` or spaces), rejecting names
+/// which aren't registered and don't use the `X-` prefix for vendor specific desktops.
+pub fn parse_desktop_list(input: &str) -> Result<Vec<String>, String> {
+    let mut desktops = Vec::new();
+
+    for name in input
+        .split(|c: char| c == ';' || c == ',' || c.is_whitespace())
+        .filter(|name| !name.is_empty())
+    {
+        if !KNOWN_DESKTOPS.contains(&name) && !name.starts_with("X-") {
+            return Err(
+                match KNOWN_DESKTOPS
+                    .iter()
+                    .find(|known| known.eq_ignore_ascii_case(name))
+                {
+                    Some(known) => fl!("session-conditions", "did-you-mean", name = name, known = *known),
+                    None => fl!("session-conditions", "unknown-desktop", name = name),
+                },
+            );
+        }
+
+        if !desktops.iter().any(|desktop| desktop == name) {
+            desktops.push(name.to_string());
+        }
+    }
+
+    Ok(desktops)
+}
+
+/// Formats a list of desktops as a desktop entry string list.
+pub fn format_desktop_list(desktops: &[String]) -> String {
+    let mut value = desktops.join(";");
+    value.push(';');
+    value
+}
+
+/// The `AutostartCondition` (or `X-GNOME-AutostartCondition`) of an autostart entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AutostartCondition {
+    /// Only start while the boolean GSettings key is true
+    GSettings { schema: String, key: String },
+
+    /// Only start if the file, relative to `$XDG_CONFIG_HOME`, exists
+    IfExists(String),
+
+    /// Only start if the file, relative to `$XDG_CONFIG_HOME`, does not exist
+    UnlessExists(String),
+
+    /// Any other condition, which is kept as written
+    Other(String),
+}
+
+impl AutostartCondition {
+    pub fn parse(value: &str) -> Option<AutostartCondition> {
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+
+        let (kind, rest) = value.split_once(char::is_whitespace).unwrap_or((value, ""));
+        let rest = rest.trim();
+
+        Some(match kind.to_lowercase().as_str() {
+            "gsettings" => match rest.split_once(char::is_whitespace) {
+                Some((schema, key)) => AutostartCondition::GSettings {
+                    schema: schema.to_string(),
+                    key: key.trim().to_string(),
+                },
+                None => AutostartCondition::Other(value.to_string()),
+            },
+            "if-exists" if !rest.is_empty() => AutostartCondition::IfExists(rest.to_string()),
+            "unless-exists" if !rest.is_empty() => {
+                AutostartCondition::UnlessExists(rest.to_string())
+            }
+            _ => AutostartCondition::Other(value.to_string()),
+        })
+    }
+}
+
+impl fmt::Display for AutostartCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AutostartCondition::GSettings { schema, key } => write!(f, "GSettings {schema} {key}"),
+            AutostartCondition::IfExists(path) => write!(f, "if-exists {path}"),
+            AutostartCondition::UnlessExists(path) => write!(f, "unless-exists {path}"),
+            AutostartCondition::Other(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn desktops(list: &[&str]) -> Vec<String> {
+        list.iter().map(|desktop| desktop.to_string()).collect()
+    }
+
+    #[test]
+    fn current_desktop_is_a_list() {
+        assert_eq!(
+            desktops_from_env(Some("ubuntu:GNOME"), Some("ubuntu")),
+            desktops(&["ubuntu", "GNOME"])
+        );
+        assert_eq!(desktops_from_env(Some("COSMIC"), None), desktops(&["COSMIC"]));
+        assert_eq!(desktops_from_env(Some(":KDE: "), None), desktops(&["KDE"]));
+    }
+
+    #[test]
+    fn empty_or_unset_current_desktop_falls_back_to_session() {
+        assert_eq!(desktops_from_env(None, Some("plasmawayland")), desktops(&["KDE"]));
+        assert_eq!(desktops_from_env(Some(""), Some("cosmic")), desktops(&["COSMIC"]));
+        assert_eq!(desktops_from_env(Some(" "), Some("gnome-xorg")), desktops(&["GNOME"]));
+        assert_eq!(desktops_from_env(None, Some("sway")), desktops(&["sway"]));
+        assert_eq!(desktops_from_env(None, None), Vec::<String>::new());
+        assert_eq!(desktops_from_env(Some(""), Some("")), Vec::<String>::new());
+    }
+
+    #[test]
+    fn only_show_in_matches_any_session_desktop() {
+        // `OnlyShowIn=GNOME;` splits with an empty last element
+        let only = ["GNOME", ""];
+        let ubuntu = desktops_from_env(Some("ubuntu:GNOME"), None);
+
+        assert!(shown_in(Some(&only), None, &ubuntu));
+        assert!(!shown_in(Some(&only), None, &desktops(&["COSMIC"])));
+        assert!(!shown_in(Some(&only), None, &[]));
+        assert!(!shown_in(Some(&[""]), None, &[]));
+    }
+
+    #[test]
+    fn not_show_in_matches_any_session_desktop() {
+        let not = ["GNOME", "KDE", ""];
+
+        assert!(!shown_in(None, Some(&not), &desktops(&["ubuntu", "GNOME"])));
+        assert!(!shown_in(None, Some(&not), &desktops(&["KDE"])));
+        assert!(shown_in(None, Some(&not), &desktops(&["COSMIC"])));
+        assert!(shown_in(None, Some(&not), &[]));
+        assert!(shown_in(None, None, &[]));
+    }
+
+    #[test]
+    fn first_named_session_desktop_decides() {
+        let session = desktops(&["pop", "COSMIC", "GNOME"]);
+
+        assert!(shown_in(Some(&["COSMIC"]), Some(&["GNOME"]), &session));
+        assert!(!shown_in(Some(&["GNOME"]), Some(&["COSMIC"]), &session));
+    }
+
+    #[test]
+    fn desktop_lists() {
+        assert_eq!(parse_desktop_list("GNOME;KDE;"), Ok(desktops(&["GNOME", "KDE"])));
+        assert_eq!(
+            parse_desktop_list("COSMIC, X-Custom GNOME;;COSMIC"),
+            Ok(desktops(&["COSMIC", "X-Custom", "GNOME"]))
+        );
+        assert_eq!(parse_desktop_list(""), Ok(Vec::new()));
+        assert_eq!(parse_desktop_list(";"), Ok(Vec::new()));
+        assert!(parse_desktop_list("gnome;").is_err());
+        assert!(parse_desktop_list("Plasma").is_err());
+
+        assert_eq!(format_desktop_list(&desktops(&["GNOME", "KDE"])), "GNOME;KDE;");
+    }
+
+    #[test]
+    fn autostart_conditions_round_trip() {
+        for value in [
+            "GSettings org.gnome.desktop.a11y.applications screen-reader-enabled",
+            "if-exists autostart-marker",
+            "unless-exists gnome-initial-setup-done",
+            "GNOME3 if-session gnome",
+        ] {
+            assert_eq!(AutostartCondition::parse(value).unwrap().to_string(), value);
+        }
+
+        assert_eq!(AutostartCondition::parse("  "), None);
+        assert_eq!(
+            AutostartCondition::parse("if-exists"),
+            Some(AutostartCondition::Other("if-exists".into()))
+        );
+    }
+}
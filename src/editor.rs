@@ -0,0 +1,394 @@
+// SPDX-License-Identifier: GPL-3
+
+//! State behind the "Edit" context drawer for a single autostart entry.
+
+use crate::apps::DirectoryType;
+use crate::desktops::{self, AutostartCondition};
+use crate::entry;
+use crate::environment;
+use crate::fl;
+use crate::terminal::{self, Terminal, TERMINAL_KEY};
+use freedesktop_desktop_entry::DesktopEntry;
+use startup_configuration::history;
+use startup_configuration::launcher::{self, Condition, IoClass, LaunchOptions, ResourceLimits};
+use std::path::PathBuf;
+
+pub struct EntryEditor {
+    pub directory_type: DirectoryType,
+    pub path: PathBuf,
+    pub name: String,
+
+    // launch conditions
+    pub on_ac: bool,
+    pub network_up: String,
+    pub hostname: String,
+    pub weekdays: String,
+    pub time_window: String,
+    pub if_exists: String,
+    pub unless_exists: String,
+
+    // resource limits
+    pub nice: String,
+    pub io_class_kind: usize,
+    pub io_level: String,
+    pub memory_max: String,
+    pub cpu_quota: String,
+
+    pub record_history: bool,
+
+    /// Variables set for the command through an `env` prefix
+    pub environment: Vec<(String, String)>,
+
+    /// Whether the command runs inside a terminal, and which of the installed ones
+    pub terminal: bool,
+    pub terminal_index: usize,
+
+    // session conditions
+    pub only_show_in: String,
+    pub not_show_in: String,
+    pub autostart_condition_kind: usize,
+    pub autostart_condition_value: String,
+
+    pub error: Option<String>,
+
+    /// The entry's own command, without the launcher, terminal or `env` in front of it
+    command: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum EditorMessage {
+    OnAc(bool),
+    NetworkUp(String),
+    Hostname(String),
+    Weekdays(String),
+    TimeWindow(String),
+    IfExists(String),
+    UnlessExists(String),
+    Nice(String),
+    IoClassKind(usize),
+    IoLevel(String),
+    MemoryMax(String),
+    CpuQuota(String),
+    RecordHistory(bool),
+    AddVariable,
+    VariableName(usize, String),
+    VariableValue(usize, String),
+    RemoveVariable(usize),
+    Terminal(bool),
+    TerminalIndex(usize),
+    OnlyShowIn(String),
+    NotShowIn(String),
+    AutostartConditionKind(usize),
+    AutostartConditionValue(String),
+    Save,
+    Cancel,
+}
+
+/// Index of each `AutostartCondition` kind in the editor's dropdown.
+pub mod condition_kind {
+    pub const NONE: usize = 0;
+    pub const GSETTINGS: usize = 1;
+    pub const IF_EXISTS: usize = 2;
+    pub const UNLESS_EXISTS: usize = 3;
+    pub const OTHER: usize = 4;
+}
+
+/// Index of each I/O scheduling class in the editor's dropdown.
+pub mod io_class_kind {
+    pub const DEFAULT: usize = 0;
+    pub const IDLE: usize = 1;
+    pub const BEST_EFFORT: usize = 2;
+}
+
+impl EntryEditor {
+    pub fn new(
+        directory_type: DirectoryType,
+        desktop_entry: &DesktopEntry,
+        locales: &[String],
+        terminals: &[Terminal],
+    ) -> Self {
+        let exec = entry::split_exec(desktop_entry.exec().unwrap_or_default());
+        let (options, command) = LaunchOptions::unwrap(&exec);
+
+        let unwrapped = terminal::unwrap(desktop_entry, &command, terminals);
+        let in_terminal = unwrapped.is_some();
+        let (terminal_index, command) = match unwrapped {
+            Some((terminal, command)) => (
+                terminal.and_then(|terminal| terminals.iter().position(|known| known == terminal)),
+                command,
+            ),
+            None => (None, command),
+        };
+        let (environment, command) = entry::split_env(&command);
+
+        let mut editor = EntryEditor {
+            directory_type,
+            path: desktop_entry.path.clone(),
+            name: desktop_entry
+                .name(locales)
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| desktop_entry.appid.clone()),
+            on_ac: false,
+            network_up: String::new(),
+            hostname: String::new(),
+            weekdays: String::new(),
+            time_window: String::new(),
+            if_exists: String::new(),
+            unless_exists: String::new(),
+            nice: options.limits.nice.map(|nice| nice.to_string()).unwrap_or_default(),
+            io_class_kind: io_class_kind::DEFAULT,
+            io_level: String::new(),
+            memory_max: options.limits.memory_max.clone().unwrap_or_default(),
+            cpu_quota: options
+                .limits
+                .cpu_quota
+                .map(|quota| quota.to_string())
+                .unwrap_or_default(),
+            record_history: options.record.is_some(),
+            environment,
+            // entries relying on the session to open a terminal get an explicit one once saved
+            terminal: in_terminal || desktop_entry.desktop_entry("Terminal") == Some("true"),
+            terminal_index: terminal_index.unwrap_or_default(),
+            only_show_in: desktop_entry
+                .only_show_in()
+                .map(|desktops| desktops.join(", "))
+                .unwrap_or_default(),
+            not_show_in: desktop_entry
+                .not_show_in()
+                .map(|desktops| desktops.join(", "))
+                .unwrap_or_default(),
+            autostart_condition_kind: condition_kind::NONE,
+            autostart_condition_value: String::new(),
+            error: None,
+            command,
+        };
+
+        match options.limits.io_class {
+            Some(IoClass::Idle) => editor.io_class_kind = io_class_kind::IDLE,
+            Some(IoClass::BestEffort(level)) => {
+                editor.io_class_kind = io_class_kind::BEST_EFFORT;
+                editor.io_level = level.to_string();
+            }
+            None => {}
+        }
+
+        for condition in options.conditions {
+            match condition {
+                Condition::OnAcPower => editor.on_ac = true,
+                Condition::NetworkUp(interface) => editor.network_up = interface,
+                Condition::Hostname(pattern) => editor.hostname = pattern,
+                Condition::Weekdays(days) => editor.weekdays = launcher::format_weekdays(&days),
+                Condition::TimeWindow(start, end) => {
+                    editor.time_window = launcher::format_time_window(start, end)
+                }
+                Condition::FileExists(path) => editor.if_exists = path.display().to_string(),
+                Condition::FileMissing(path) => editor.unless_exists = path.display().to_string(),
+            }
+        }
+
+        let autostart_condition = desktop_entry
+            .desktop_entry("AutostartCondition")
+            .or_else(|| desktop_entry.desktop_entry("X-GNOME-AutostartCondition"))
+            .and_then(AutostartCondition::parse);
+
+        if let Some(condition) = autostart_condition {
+            (editor.autostart_condition_kind, editor.autostart_condition_value) = match condition {
+                AutostartCondition::GSettings { schema, key } => {
+                    (condition_kind::GSETTINGS, format!("{schema} {key}"))
+                }
+                AutostartCondition::IfExists(path) => (condition_kind::IF_EXISTS, path),
+                AutostartCondition::UnlessExists(path) => (condition_kind::UNLESS_EXISTS, path),
+                AutostartCondition::Other(value) => (condition_kind::OTHER, value),
+            };
+        }
+
+        editor
+    }
+
+    pub fn update(&mut self, message: EditorMessage) {
+        match message {
+            EditorMessage::OnAc(on_ac) => self.on_ac = on_ac,
+            EditorMessage::NetworkUp(interface) => self.network_up = interface,
+            EditorMessage::Hostname(pattern) => self.hostname = pattern,
+            EditorMessage::Weekdays(weekdays) => self.weekdays = weekdays,
+            EditorMessage::TimeWindow(window) => self.time_window = window,
+            EditorMessage::IfExists(path) => self.if_exists = path,
+            EditorMessage::UnlessExists(path) => self.unless_exists = path,
+            EditorMessage::Nice(nice) => self.nice = nice,
+            EditorMessage::IoClassKind(kind) => self.io_class_kind = kind,
+            EditorMessage::IoLevel(level) => self.io_level = level,
+            EditorMessage::MemoryMax(size) => self.memory_max = size,
+            EditorMessage::CpuQuota(quota) => self.cpu_quota = quota,
+            EditorMessage::RecordHistory(record) => self.record_history = record,
+            EditorMessage::AddVariable => self.environment.push((String::new(), String::new())),
+            EditorMessage::VariableName(idx, name) => {
+                if let Some(variable) = self.environment.get_mut(idx) {
+                    variable.0 = name;
+                }
+            }
+            EditorMessage::VariableValue(idx, value) => {
+                if let Some(variable) = self.environment.get_mut(idx) {
+                    variable.1 = value;
+                }
+            }
+            EditorMessage::RemoveVariable(idx) => {
+                if idx < self.environment.len() {
+                    self.environment.remove(idx);
+                }
+            }
+            EditorMessage::Terminal(terminal) => self.terminal = terminal,
+            EditorMessage::TerminalIndex(idx) => self.terminal_index = idx,
+            EditorMessage::OnlyShowIn(desktops) => self.only_show_in = desktops,
+            EditorMessage::NotShowIn(desktops) => self.not_show_in = desktops,
+            EditorMessage::AutostartConditionKind(kind) => self.autostart_condition_kind = kind,
+            EditorMessage::AutostartConditionValue(value) => self.autostart_condition_value = value,
+            // handled by the application, as these close the drawer
+            EditorMessage::Save | EditorMessage::Cancel => {}
+        }
+    }
+
+    fn launch_options(&self) -> Result<LaunchOptions, String> {
+        let mut conditions = Vec::new();
+
+        if self.on_ac {
+            conditions.push(Condition::OnAcPower);
+        }
+        if !self.network_up.trim().is_empty() {
+            conditions.push(Condition::NetworkUp(self.network_up.trim().to_string()));
+        }
+        if !self.hostname.trim().is_empty() {
+            conditions.push(Condition::Hostname(self.hostname.trim().to_string()));
+        }
+        if !self.weekdays.trim().is_empty() {
+            conditions.push(Condition::Weekdays(launcher::parse_weekdays(&self.weekdays)?));
+        }
+        if !self.time_window.trim().is_empty() {
+            let (start, end) = launcher::parse_time_window(&self.time_window)?;
+            conditions.push(Condition::TimeWindow(start, end));
+        }
+        if !self.if_exists.trim().is_empty() {
+            conditions.push(Condition::FileExists(PathBuf::from(self.if_exists.trim())));
+        }
+        if !self.unless_exists.trim().is_empty() {
+            conditions.push(Condition::FileMissing(PathBuf::from(self.unless_exists.trim())));
+        }
+
+        Ok(LaunchOptions {
+            conditions,
+            limits: self.resource_limits()?,
+            record: self.record_history.then(|| history::entry_id(&self.path)),
+        })
+    }
+
+    fn resource_limits(&self) -> Result<ResourceLimits, String> {
+        let optional = |value: &str| Some(value.trim()).filter(|value| !value.is_empty());
+
+        let io_class = match self.io_class_kind {
+            io_class_kind::IDLE => Some(IoClass::Idle),
+            io_class_kind::BEST_EFFORT => Some(IoClass::parse(&format!(
+                "best-effort:{}",
+                optional(&self.io_level).unwrap_or("4")
+            ))?),
+            _ => None,
+        };
+
+        Ok(ResourceLimits {
+            nice: optional(&self.nice).map(launcher::parse_nice).transpose()?,
+            io_class,
+            memory_max: optional(&self.memory_max)
+                .map(launcher::parse_memory_max)
+                .transpose()?,
+            cpu_quota: optional(&self.cpu_quota)
+                .map(launcher::parse_cpu_quota)
+                .transpose()?,
+        })
+    }
+
+    /// The variables to set, leaving out rows which were never filled in.
+    fn environment(&self) -> Result<Vec<(String, String)>, String> {
+        self.environment
+            .iter()
+            .filter(|(name, value)| !name.trim().is_empty() || !value.is_empty())
+            .map(|(name, value)| {
+                let name = name.trim();
+                if environment::is_valid_name(name) {
+                    Ok((name.to_string(), value.clone()))
+                } else {
+                    Err(fl!("environment-error", "invalid-key", name = name))
+                }
+            })
+            .collect()
+    }
+
+    fn autostart_condition(&self) -> Result<Option<AutostartCondition>, String> {
+        let value = self.autostart_condition_value.trim();
+
+        let condition = match self.autostart_condition_kind {
+            condition_kind::NONE => return Ok(None),
+            condition_kind::GSETTINGS => match value.split_once(char::is_whitespace) {
+                Some((schema, key)) if !key.trim().contains(char::is_whitespace) => {
+                    AutostartCondition::GSettings {
+                        schema: schema.to_string(),
+                        key: key.trim().to_string(),
+                    }
+                }
+                _ => return Err(fl!("autostart-condition", "gsettings-invalid")),
+            },
+            condition_kind::IF_EXISTS => AutostartCondition::IfExists(value.to_string()),
+            condition_kind::UNLESS_EXISTS => AutostartCondition::UnlessExists(value.to_string()),
+            _ => AutostartCondition::Other(value.to_string()),
+        };
+
+        if value.is_empty() {
+            return Err(fl!("autostart-condition", "no-value"));
+        }
+
+        Ok(Some(condition))
+    }
+
+    /// Writes the edited entry back to disk, opening the command in one of `terminals` if asked.
+    pub fn save(&self, terminals: &[Terminal]) -> Result<(), String> {
+        let options = self.launch_options()?;
+        let mut command = entry::join_env(&self.environment()?, &self.command);
+
+        let terminal = terminals.get(self.terminal_index).filter(|_| self.terminal);
+        if let Some(terminal) = terminal {
+            command = terminal.wrap(&command);
+        }
+
+        let exec = if options.is_empty() {
+            command
+        } else {
+            options.wrap(&command)
+        };
+
+        let only_show_in = desktops::parse_desktop_list(&self.only_show_in)?;
+        let not_show_in = desktops::parse_desktop_list(&self.not_show_in)?;
+
+        if let Some(desktop) = only_show_in.iter().find(|desktop| not_show_in.contains(desktop)) {
+            return Err(fl!("session-conditions", "shown-and-hidden", desktop = desktop.as_str()));
+        }
+
+        let autostart_condition = self.autostart_condition()?.map(|condition| condition.to_string());
+
+        let desktop_list = |desktops: Vec<String>| {
+            (!desktops.is_empty()).then(|| desktops::format_desktop_list(&desktops))
+        };
+
+        entry::set_keys(
+            &self.path,
+            &[
+                ("Exec", Some(entry::join_exec(&exec))),
+                ("OnlyShowIn", desktop_list(only_show_in)),
+                ("NotShowIn", desktop_list(not_show_in)),
+                // systemd-xdg-autostart-generator and gnome-session only read the GNOME key
+                ("AutostartCondition", autostart_condition.clone()),
+                ("X-GNOME-AutostartCondition", autostart_condition),
+                // without an installed terminal, opening one is left to the session
+                ("Terminal", (self.terminal && terminal.is_none()).then(|| "true".to_string())),
+                (TERMINAL_KEY, terminal.map(|terminal| terminal.appid.clone())),
+            ],
+        )
+        .map_err(|why| why.to_string())
+    }
+}
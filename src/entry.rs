@@ -0,0 +1,413 @@
+// SPDX-License-Identifier: GPL-3
+
+//! Helpers for reading and rewriting autostart `.desktop` files in place.
+
+use crate::environment::is_valid_name;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const DESKTOP_ENTRY_GROUP: &str = "[Desktop Entry]";
+
+/// Characters which force an `Exec` argument to be quoted, as listed in the desktop entry spec.
+const RESERVED_CHARS: &[char] = &[
+    ' ', '\t', '\n', '"', '\'', '\\', '>', '<', '~', '|', '&', ';', '$', '*', '?', '#', '(', ')',
+    '`',
+];
+
+/// Splits an `Exec` value into its arguments, following the desktop entry quoting rules.
+pub fn split_exec(exec: &str) -> Vec<String> {
+    let exec = unescape_value(exec);
+
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut in_quotes = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                in_arg = true;
+            }
+            '\\' if in_quotes => {
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            }
+            ' ' | '\t' | '\n' if !in_quotes => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            _ => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+
+    if in_arg {
+        args.push(current);
+    }
+
+    args
+}
+
+/// Drops field codes such as `%U`, which have no meaning outside a launcher, and turns `%%` back
+/// into a literal `%`.
+pub fn strip_field_codes(args: &[String]) -> Vec<String> {
+    args.iter()
+        .filter(|arg| !(arg.len() == 2 && arg.starts_with('%') && arg.as_str() != "%%"))
+        .map(|arg| arg.replace("%%", "%"))
+        .collect()
+}
+
+/// Splits the variables of an `env VAR=value ...` prefix off a command.
+///
+/// Commands using any of `env`'s options are left alone, as the editor can't represent them.
+pub fn split_env(command: &[String]) -> (Vec<(String, String)>, Vec<String>) {
+    let untouched = || (Vec::new(), command.to_vec());
+
+    let Some((program, args)) = command.split_first() else {
+        return untouched();
+    };
+    if program != "env" && program != "/usr/bin/env" {
+        return untouched();
+    }
+
+    let mut variables = Vec::new();
+    for arg in args {
+        match arg.split_once('=') {
+            Some((name, value)) if is_valid_name(name) => {
+                variables.push((name.to_string(), value.replace("%%", "%")));
+            }
+            _ => break,
+        }
+    }
+
+    let rest = &args[variables.len()..];
+    if variables.is_empty() || rest.is_empty() || rest[0].starts_with('-') {
+        return untouched();
+    }
+
+    (variables, rest.to_vec())
+}
+
+/// Puts `env VAR=value ...` in front of a command, if there are any variables.
+pub fn join_env(variables: &[(String, String)], command: &[String]) -> Vec<String> {
+    if variables.is_empty() {
+        return command.to_vec();
+    }
+
+    std::iter::once("env".to_string())
+        // a lone `%` would be taken for a field code
+        .chain(variables.iter().map(|(name, value)| format!("{name}={}", value.replace('%', "%%"))))
+        .chain(command.iter().cloned())
+        .collect()
+}
+
+/// Joins arguments into an `Exec` value, quoting those which need it.
+pub fn join_exec(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| {
+            if !arg.is_empty() && !arg.contains(RESERVED_CHARS) {
+                return arg.clone();
+            }
+
+            let mut quoted = String::with_capacity(arg.len() + 2);
+            quoted.push('"');
+            for c in arg.chars() {
+                if matches!(c, '"' | '`' | '$' | '\\') {
+                    quoted.push('\\');
+                }
+                quoted.push(c);
+            }
+            quoted.push('"');
+            quoted
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Reads an unlocalized key from the `[Desktop Entry]` group of a desktop file's contents, still
+/// escaped.
+pub fn get_key(contents: &str, key: &str) -> Option<String> {
+    let mut in_group = false;
+
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_group = line == DESKTOP_ENTRY_GROUP;
+            continue;
+        }
+
+        if let Some((line_key, value)) = line.split_once('=') {
+            if in_group && line_key.trim() == key {
+                return Some(value.trim().to_string());
+            }
+        }
+    }
+
+    None
+}
+
+/// Sets (or removes, for `None`) keys in the `[Desktop Entry]` group of the file at `path`.
+///
+/// Autostart entries added from installed applications are symlinks to the system copy, so a
+/// symlink is replaced by a regular file holding the edited contents.
+pub fn set_keys(path: &Path, changes: &[(&str, Option<String>)]) -> io::Result<()> {
+    let contents = fs::read_to_string(path)?;
+
+    let mut lines: Vec<String> = contents.lines().map(String::from).collect();
+
+    let group_start = match lines.iter().position(|line| line.trim() == DESKTOP_ENTRY_GROUP) {
+        Some(idx) => idx,
+        None => {
+            lines.insert(0, DESKTOP_ENTRY_GROUP.to_string());
+            0
+        }
+    };
+
+    for (key, value) in changes {
+        let group_end = lines
+            .iter()
+            .skip(group_start + 1)
+            .position(|line| line.trim_start().starts_with('['))
+            .map(|idx| idx + group_start + 1)
+            .unwrap_or(lines.len());
+
+        let existing = (group_start + 1..group_end).find(|idx| {
+            lines[*idx]
+                .split_once('=')
+                .is_some_and(|(line_key, _)| line_key.trim() == *key)
+        });
+
+        match (existing, value) {
+            (Some(idx), Some(value)) => lines[idx] = format!("{key}={}", escape_value(value)),
+            (Some(idx), None) => {
+                lines.remove(idx);
+            }
+            (None, Some(value)) => {
+                // keep new keys next to the rest of the group rather than after blank lines
+                let mut insert_at = group_end;
+                while insert_at > group_start + 1 && lines[insert_at - 1].trim().is_empty() {
+                    insert_at -= 1;
+                }
+                lines.insert(insert_at, format!("{key}={}", escape_value(value)));
+            }
+            (None, None) => {}
+        }
+    }
+
+    let mut contents = lines.join("\n");
+    contents.push('\n');
+
+    if fs::symlink_metadata(path)?.file_type().is_symlink() {
+        fs::remove_file(path)?;
+    }
+
+    fs::write(path, contents)
+}
+
+/// Applies the string escapes (`\\`, `\s`, `\n`, `\t`, `\r`) of a desktop entry value.
+pub fn unescape_value(value: &str) -> String {
+    let mut res = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+
+        match chars.peek() {
+            Some('\\') => res.push('\\'),
+            Some('s') => res.push(' '),
+            Some('n') => res.push('\n'),
+            Some('t') => res.push('\t'),
+            Some('r') => res.push('\r'),
+            _ => {
+                res.push('\\');
+                continue;
+            }
+        }
+        chars.next();
+    }
+
+    res
+}
+
+/// Escapes a value for a desktop entry file, so that reading it back gives `value` again. `Exec`
+/// values are joined with [`join_exec`] first.
+pub fn escape_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+    use std::path::PathBuf;
+
+    /// A fresh directory to write fixture entries in.
+    fn fixture_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("entry-{name}-{}", std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn variables(variables: &[(&str, &str)]) -> Vec<(String, String)> {
+        variables
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn split_exec_values() {
+        assert_eq!(split_exec("prog  --flag\t\"quoted arg\" %U"), ["prog", "--flag", "quoted arg", "%U"]);
+        assert_eq!(split_exec("prog \"\" end"), ["prog", "", "end"]);
+        assert_eq!(split_exec("prog pre\"fix suf\"fix"), ["prog", "prefix suffix"]);
+        // `\\` in the file is a backslash, which then escapes the next character inside quotes
+        assert_eq!(split_exec(r#"sh -c "echo \\"hi\\" \\$HOME \\\\""#), ["sh", "-c", r#"echo "hi" $HOME \"#]);
+        assert!(split_exec("").is_empty());
+    }
+
+    #[test]
+    fn exec_round_trip() {
+        let commands = [
+            strings(&["prog", "plain", "--opt=value"]),
+            strings(&["/opt/my app/bin/app", "--name=two words", ""]),
+            strings(&["sh", "-c", "echo \"$HOME\" `date` | tee ~/log; exit"]),
+            strings(&["back\\slash", "tab\tand\nnewline", "'single'"]),
+            strings(&["prog", "100%%", "%U"]),
+        ];
+
+        for command in commands {
+            // `split_exec` reads the value as written to the file, with its string escapes
+            let exec = escape_value(&join_exec(&command));
+            assert_eq!(split_exec(&exec), command, "{exec}");
+        }
+
+        assert_eq!(join_exec(&strings(&["prog", "a b", "$x"])), r#"prog "a b" "\$x""#);
+    }
+
+    #[test]
+    fn field_codes() {
+        let args = strings(&["prog", "%f", "%U", "100%%", "%%", "a%%b%%", "%foo"]);
+
+        assert_eq!(strip_field_codes(&args), ["prog", "100%", "%", "a%b%", "%foo"]);
+    }
+
+    #[test]
+    fn env_round_trip() {
+        let vars = variables(&[("LANG", "C"), ("RATIO", "50%"), ("EMPTY", "")]);
+        let command = strings(&["prog", "--x"]);
+
+        let joined = join_env(&vars, &command);
+        assert_eq!(joined, ["env", "LANG=C", "RATIO=50%%", "EMPTY=", "prog", "--x"]);
+        assert_eq!(split_env(&joined), (vars, command.clone()));
+
+        assert_eq!(join_env(&[], &command), command);
+
+        let with_path = strings(&["/usr/bin/env", "A=1", "prog"]);
+        assert_eq!(split_env(&with_path), (variables(&[("A", "1")]), strings(&["prog"])));
+    }
+
+    #[test]
+    fn env_left_alone() {
+        let untouched = [
+            strings(&["env", "-i", "A=1", "prog"]),
+            strings(&["env", "A=1", "-u", "B", "prog"]),
+            strings(&["env", "A=1"]),
+            strings(&["env", "1A=x", "prog"]),
+            strings(&["env"]),
+            strings(&["prog", "A=1"]),
+        ];
+
+        for command in untouched {
+            assert_eq!(split_env(&command), (Vec::new(), command.clone()));
+        }
+        assert_eq!(split_env(&[]), (Vec::new(), Vec::new()));
+    }
+
+    #[test]
+    fn value_escapes() {
+        for value in ["plain", "back\\slash", "two\nlines", "tab\tand\rreturn", "\\n literally", "\\"] {
+            assert_eq!(unescape_value(&escape_value(value)), value);
+        }
+
+        assert_eq!(unescape_value(r"a\sb\q\"), r"a b\q\");
+    }
+
+    #[test]
+    fn set_keys_round_trip() {
+        let dir = fixture_dir("set-keys");
+        let path = dir.join("app.desktop");
+        fs::write(
+            &path,
+            "[Desktop Entry]\nType=Application\nName=Old\nComment=Gone\n\n[Desktop Action new]\nExec=other\n",
+        )
+        .unwrap();
+
+        let vars = variables(&[("GREETING", "hello world"), ("RATIO", "50%")]);
+        let command = strings(&["sh", "-c", "printf '100%%\\n' \"$GREETING\" > ~/out", "%U"]);
+
+        set_keys(
+            &path,
+            &[
+                ("Exec", Some(join_exec(&join_env(&vars, &command)))),
+                ("Name", Some("Two\nlines \\ here".to_string())),
+                ("Comment", None),
+                ("Hidden", None),
+            ],
+        )
+        .unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+
+        // new keys stay in the group, before the blank line, and other groups are untouched
+        assert!(contents.contains("Name=Two\\nlines \\\\ here\nExec="), "{contents}");
+        assert!(contents.ends_with("\n\n[Desktop Action new]\nExec=other\n"), "{contents}");
+        assert_eq!(get_key(&contents, "Comment"), None);
+        assert_eq!(get_key(&contents, "Type").as_deref(), Some("Application"));
+
+        let exec = split_exec(&get_key(&contents, "Exec").unwrap());
+        assert_eq!(split_env(&exec), (vars, command));
+        assert_eq!(
+            strip_field_codes(&split_env(&exec).1),
+            ["sh", "-c", "printf '100%\\n' \"$GREETING\" > ~/out"]
+        );
+        assert_eq!(unescape_value(&get_key(&contents, "Name").unwrap()), "Two\nlines \\ here");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn set_keys_replaces_symlinks() {
+        let dir = fixture_dir("symlink");
+        let system = dir.join("system.desktop");
+        let autostart = dir.join("autostart.desktop");
+        fs::write(&system, "Name=App\nExec=app\n").unwrap();
+        symlink(&system, &autostart).unwrap();
+
+        set_keys(&autostart, &[("Hidden", Some("true".to_string()))]).unwrap();
+
+        assert!(!fs::symlink_metadata(&autostart).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&autostart).unwrap(), "[Desktop Entry]\nName=App\nExec=app\nHidden=true\n");
+        assert_eq!(fs::read_to_string(&system).unwrap(), "Name=App\nExec=app\n");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
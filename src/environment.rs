@@ -0,0 +1,940 @@
+// SPDX-License-Identifier: GPL-3
+
+//! Variables set for the session by `environment.d` files, which the systemd user manager loads
+//! at login and passes on to everything it starts.
+//!
+//! Files are read from the user's `~/.config/environment.d` and the system directories below it.
+//! A file replaces any lower priority file with the same name, and the remaining files are
+//! loaded in order of their names. Later assignments win.
+//!
+//! systemd links `/etc/environment` into the lowest priority directory as
+//! `99-environment.conf`, so it's loaded after most files rather than before them, and a file of
+//! that name masks it.
+
+use crate::apps::host_path;
+use crate::fl;
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// The file new variables are added to.
+pub const DEFAULT_FILE: &str = "60-startup-configuration.conf";
+
+/// The name `/etc/environment` is loaded under.
+const ETC_ENVIRONMENT_NAME: &str = "99-environment.conf";
+
+/// Where `environment.d` files are looked up.
+#[derive(Debug, Clone)]
+pub struct EnvDirs {
+    /// `~/.config/environment.d`, the only directory the user can change
+    pub user: PathBuf,
+
+    /// System directories, highest priority first
+    pub system: Vec<PathBuf>,
+
+    /// `/etc/environment`, which is loaded as if it were the lowest priority
+    /// `99-environment.conf`
+    pub etc_environment: PathBuf,
+}
+
+/// One `KEY=VALUE` line, possibly continued over several lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assignment {
+    pub key: String,
+
+    /// The value with quotes and escapes removed, but `$VARIABLES` not yet expanded
+    pub value: String,
+
+    /// Which lines of the file the assignment covers, counting from zero
+    pub lines: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxErrorKind {
+    MissingEquals,
+    InvalidName(String),
+    UnterminatedQuote,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    /// Line of the file, counting from zero
+    pub line: usize,
+    pub kind: SyntaxErrorKind,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnvFile {
+    /// Path of the file, as seen by the host
+    pub path: PathBuf,
+    pub is_user: bool,
+
+    /// A higher priority file with the same name, which is loaded instead of this one
+    pub masked_by: Option<PathBuf>,
+
+    pub assignments: Vec<Assignment>,
+    pub errors: Vec<SyntaxError>,
+}
+
+/// A variable as it ends up in the session.
+#[derive(Debug, Clone)]
+pub struct MergedVariable {
+    pub key: String,
+
+    /// The final value, with other variables expanded
+    pub value: String,
+
+    /// The assignment which set the final value, and the file it's in
+    pub assignment: Assignment,
+    pub source: PathBuf,
+    pub is_user: bool,
+
+    /// System assignments of the same variable which a user file overrides
+    pub shadowed: Vec<Shadowed>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Shadowed {
+    pub path: PathBuf,
+    pub value: String,
+}
+
+impl SyntaxError {
+    pub fn describe(&self) -> String {
+        let line = self.line + 1;
+
+        match &self.kind {
+            SyntaxErrorKind::MissingEquals => fl!("environment-error", "missing-equals", line = line),
+            SyntaxErrorKind::InvalidName(name) => {
+                fl!("environment-error", "invalid-name", line = line, name = name.clone())
+            }
+            SyntaxErrorKind::UnterminatedQuote => {
+                fl!("environment-error", "unterminated-quote", line = line)
+            }
+        }
+    }
+}
+
+impl EnvDirs {
+    pub fn from_system() -> Self {
+        // when running as a flatpak, XDG_CONFIG_HOME points into the sandbox, so use the host's
+        // directory under the home directory instead
+        #[cfg(feature = "flatpak")]
+        let config_dir = dirs::home_dir().expect("home dir not found").join(".config");
+
+        #[cfg(not(feature = "flatpak"))]
+        let config_dir = dirs::config_dir().expect("config dir not found");
+
+        EnvDirs {
+            user: config_dir.join("environment.d"),
+            system: vec![
+                PathBuf::from("/etc/environment.d"),
+                PathBuf::from("/run/environment.d"),
+                PathBuf::from("/usr/local/lib/environment.d"),
+                PathBuf::from("/usr/lib/environment.d"),
+            ],
+            etc_environment: PathBuf::from("/etc/environment"),
+        }
+    }
+}
+
+/// Reads every `environment.d` file, in the order they are loaded.
+///
+/// Files replaced by a higher priority file of the same name are included, just after the file
+/// replacing them, with [`EnvFile::masked_by`] set.
+pub fn load_files(dirs: &EnvDirs) -> Vec<EnvFile> {
+    let read = |path: PathBuf, is_user: bool| {
+        let contents = fs::read_to_string(host_path(&path)).ok()?;
+        let (assignments, errors) = parse(&contents);
+
+        Some(EnvFile {
+            path,
+            is_user,
+            masked_by: None,
+            assignments,
+            errors,
+        })
+    };
+
+    let mut files: Vec<EnvFile> = Vec::new();
+
+    // file names in each directory, highest priority directory first
+    let mut by_name: Vec<(String, Vec<(PathBuf, bool)>)> = Vec::new();
+
+    let directories = std::iter::once((&dirs.user, true))
+        .chain(dirs.system.iter().map(|dir| (dir, false)));
+
+    for (dir, is_user) in directories {
+        let Ok(read_dir) = fs::read_dir(host_path(dir)) else {
+            continue;
+        };
+
+        let mut names: Vec<String> = read_dir
+            .flatten()
+            .filter_map(|file| file.file_name().into_string().ok())
+            .filter(|name| name.ends_with(".conf"))
+            .collect();
+        names.sort();
+
+        for name in names {
+            let path = dir.join(&name);
+            match by_name.iter_mut().find(|(existing, _)| *existing == name) {
+                Some((_, paths)) => paths.push((path, is_user)),
+                None => by_name.push((name, vec![(path, is_user)])),
+            }
+        }
+    }
+
+    // systemd ships the link to /etc/environment, but distributions don't always keep it
+    let linked = dirs
+        .system
+        .last()
+        .is_some_and(|dir| host_path(&dir.join(ETC_ENVIRONMENT_NAME)).exists());
+    if !linked {
+        let etc_environment = (dirs.etc_environment.clone(), false);
+        match by_name.iter_mut().find(|(name, _)| name == ETC_ENVIRONMENT_NAME) {
+            Some((_, paths)) => paths.push(etc_environment),
+            None => by_name.push((ETC_ENVIRONMENT_NAME.to_string(), vec![etc_environment])),
+        }
+    }
+
+    by_name.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    for (_, paths) in by_name {
+        let mut paths = paths.into_iter();
+        let Some((winner, is_user)) = paths.next() else {
+            continue;
+        };
+
+        files.extend(read(winner.clone(), is_user));
+
+        for (path, is_user) in paths {
+            files.extend(read(path, is_user).map(|file| EnvFile {
+                masked_by: Some(winner.clone()),
+                ..file
+            }));
+        }
+    }
+
+    files
+}
+
+/// Parses the contents of an `environment.d` file.
+///
+/// Follows systemd's rules: empty lines and lines starting with `#` or `;` are ignored, values
+/// may be quoted with `"` or `'`, and a backslash escapes the next character or, at the end of a
+/// line, continues the value on the next one.
+pub fn parse(contents: &str) -> (Vec<Assignment>, Vec<SyntaxError>) {
+    let lines: Vec<&str> = contents.lines().collect();
+
+    let mut assignments = Vec::new();
+    let mut errors = Vec::new();
+    let mut index = 0;
+
+    while index < lines.len() {
+        let start = index;
+        let line = lines[index].trim_start();
+        index += 1;
+
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        let Some((key, rest)) = line.split_once('=') else {
+            errors.push(SyntaxError {
+                line: start,
+                kind: SyntaxErrorKind::MissingEquals,
+            });
+            continue;
+        };
+
+        let key = key.trim();
+
+        match parse_value(rest, &lines, &mut index) {
+            Some(_) if !is_valid_name(key) => errors.push(SyntaxError {
+                line: start,
+                kind: SyntaxErrorKind::InvalidName(key.to_string()),
+            }),
+            Some(value) => assignments.push(Assignment {
+                key: key.to_string(),
+                value,
+                lines: start..index,
+            }),
+            None => errors.push(SyntaxError {
+                line: start,
+                kind: SyntaxErrorKind::UnterminatedQuote,
+            }),
+        }
+    }
+
+    (assignments, errors)
+}
+
+/// Parses a value starting with `first`, taking further lines from `lines` at `next` while it
+/// continues. Returns `None` if a quote is never closed.
+fn parse_value(first: &str, lines: &[&str], next: &mut usize) -> Option<String> {
+    enum Quote {
+        None,
+        Single,
+        Double,
+    }
+
+    let mut value = String::new();
+    // length of the value up to the last character which isn't unquoted whitespace
+    let mut kept = 0;
+    let mut quote = Quote::None;
+    let mut text = first;
+
+    loop {
+        let mut chars = text.chars();
+        let mut continues = false;
+
+        while let Some(c) = chars.next() {
+            match (&quote, c) {
+                (Quote::None, '\'') => quote = Quote::Single,
+                (Quote::None, '"') => quote = Quote::Double,
+                (Quote::Single, '\'') | (Quote::Double, '"') => quote = Quote::None,
+                (Quote::Single, c) => value.push(c),
+                (Quote::None, '\\') => match chars.next() {
+                    Some(c) => value.push(c),
+                    None => continues = true,
+                },
+                (Quote::Double, '\\') => match chars.next() {
+                    Some(c @ ('"' | '\\' | '`' | '$')) => value.push(c),
+                    Some(c) => {
+                        value.push('\\');
+                        value.push(c);
+                    }
+                    None => continues = true,
+                },
+                (Quote::None, c) if c.is_whitespace() => {
+                    // leading whitespace is dropped, trailing whitespace trimmed below
+                    if !value.is_empty() {
+                        value.push(c);
+                    }
+                    continue;
+                }
+                (_, c) => value.push(c),
+            }
+
+            kept = value.len();
+        }
+
+        let quoted = !matches!(quote, Quote::None);
+        if !quoted && !continues {
+            break;
+        }
+
+        let Some(line) = lines.get(*next) else {
+            // a backslash on the last line has nothing to continue onto
+            return (!quoted).then(|| {
+                value.truncate(kept);
+                value
+            });
+        };
+        *next += 1;
+
+        // a line break inside quotes is part of the value, unless it's escaped
+        if quoted && !continues {
+            value.push('\n');
+            kept = value.len();
+        }
+        text = line;
+    }
+
+    value.truncate(kept);
+    Some(value)
+}
+
+/// Whether `name` can be used as a variable name.
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Variables the systemd user manager starts out with, before `environment.d` is loaded.
+pub fn base_environment() -> HashMap<String, String> {
+    let mut base: HashMap<String, String> = ["HOME", "USER", "LOGNAME", "SHELL", "XDG_RUNTIME_DIR"]
+        .into_iter()
+        .filter_map(|key| Some((key.to_string(), std::env::var(key).ok()?)))
+        .collect();
+
+    // systemd's built in default, not the session's, which already includes environment.d
+    base.insert("PATH".to_string(), "/usr/local/bin:/usr/bin".to_string());
+
+    base
+}
+
+/// Works out the session's variables from `files`, in the order they are first set.
+///
+/// `base` holds the variables already set before `environment.d` is loaded, which assignments
+/// such as `PATH=$PATH:...` build on.
+pub fn merge(files: &[EnvFile], base: &HashMap<String, String>) -> Vec<MergedVariable> {
+    let mut merged: Vec<MergedVariable> = Vec::new();
+
+    for file in files.iter().filter(|file| file.masked_by.is_none()) {
+        for assignment in &file.assignments {
+            let value = expand(&assignment.value, &|name| {
+                merged
+                    .iter()
+                    .find(|variable| variable.key == name)
+                    .map(|variable| variable.value.clone())
+                    .or_else(|| base.get(name).cloned())
+            });
+
+            let variable = MergedVariable {
+                key: assignment.key.clone(),
+                value,
+                assignment: assignment.clone(),
+                source: file.path.clone(),
+                is_user: file.is_user,
+                shadowed: Vec::new(),
+            };
+
+            match merged.iter_mut().find(|existing| existing.key == assignment.key) {
+                Some(existing) => {
+                    // a later system file setting it again undoes the user's override
+                    let mut shadowed = Vec::new();
+                    if file.is_user {
+                        shadowed = std::mem::take(&mut existing.shadowed);
+                        // `PATH=$PATH:...` builds on the system value rather than replacing it
+                        if !existing.is_user && !references(&assignment.value, &assignment.key) {
+                            shadowed.push(Shadowed {
+                                path: existing.source.clone(),
+                                value: existing.assignment.value.clone(),
+                            });
+                        }
+                    }
+
+                    *existing = MergedVariable {
+                        shadowed,
+                        ..variable
+                    };
+                }
+                None => merged.push(variable),
+            }
+        }
+    }
+
+    // system files a user file replaces entirely are shadowed too
+    for file in files.iter().filter(|file| !file.is_user) {
+        let Some(user_file) = &file.masked_by else {
+            continue;
+        };
+
+        for assignment in &file.assignments {
+            if let Some(variable) = merged.iter_mut().find(|variable| {
+                variable.key == assignment.key
+                    && variable.source == *user_file
+                    && !references(&variable.assignment.value, &variable.key)
+            }) {
+                variable.shadowed.push(Shadowed {
+                    path: file.path.clone(),
+                    value: assignment.value.clone(),
+                });
+            }
+        }
+    }
+
+    merged
+}
+
+/// Expands `$NAME`, `${NAME}`, `${NAME:-default}` and `${NAME:+alternative}` in `value`.
+/// Unset variables expand to nothing.
+pub fn expand(value: &str, lookup: &dyn Fn(&str) -> Option<String>) -> String {
+    let mut expanded = String::new();
+    let mut rest = value;
+
+    while let Some(dollar) = rest.find('$') {
+        expanded.push_str(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
+
+        if let Some(braced) = after.strip_prefix('{') {
+            let Some(end) = closing_brace(braced) else {
+                // unterminated, so taken literally
+                expanded.push_str(&rest[dollar..]);
+                return expanded;
+            };
+
+            let inner = &braced[..end];
+            let set = |name: &str| lookup(name).filter(|value| !value.is_empty());
+            let operator = [inner.find(":-"), inner.find(":+")].into_iter().flatten().min();
+
+            expanded.push_str(&match operator {
+                Some(at) if inner[at..].starts_with(":-") => {
+                    set(&inner[..at]).unwrap_or_else(|| expand(&inner[at + 2..], lookup))
+                }
+                Some(at) => set(&inner[..at])
+                    .map(|_| expand(&inner[at + 2..], lookup))
+                    .unwrap_or_default(),
+                None => lookup(inner).unwrap_or_default(),
+            });
+
+            rest = &braced[end + 1..];
+            continue;
+        }
+
+        let name_len = after
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(after.len());
+
+        if name_len == 0 {
+            expanded.push('$');
+        } else {
+            expanded.push_str(&lookup(&after[..name_len]).unwrap_or_default());
+        }
+
+        rest = &after[name_len..];
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Whether `value` expands the variable `key`.
+fn references(value: &str, key: &str) -> bool {
+    let found = std::cell::Cell::new(false);
+    expand(value, &|name| {
+        found.set(found.get() || name == key);
+        None
+    });
+
+    found.get()
+}
+
+/// Finds the `}` closing a `${`, allowing for nested expansions in defaults.
+fn closing_brace(text: &str) -> Option<usize> {
+    let mut depth = 0;
+
+    for (index, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(index),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Checks a variable before it's written to a file.
+pub fn validate(key: &str, value: &str) -> Result<(), String> {
+    if !is_valid_name(key) {
+        return Err(fl!("environment-error", "invalid-key", name = key.to_string()));
+    }
+
+    if value.contains('\0') {
+        return Err(fl!("environment-error", "nul"));
+    }
+
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        match closing_brace(&rest[start + 2..]) {
+            Some(end) => rest = &rest[start + 2 + end + 1..],
+            None => return Err(fl!("environment-error", "unterminated-expansion")),
+        }
+    }
+
+    Ok(())
+}
+
+/// Formats an assignment as a line of an `environment.d` file, quoting the value if it needs it.
+pub fn format_assignment(key: &str, value: &str) -> String {
+    let plain = !value.is_empty()
+        && !value.starts_with(['#', ';'])
+        && !value
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || matches!(c, '"' | '\'' | '\\' | '`'));
+
+    if plain {
+        return format!("{key}={value}");
+    }
+
+    let mut quoted = String::with_capacity(value.len() + 2);
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | '`') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+
+    format!("{key}=\"{quoted}\"")
+}
+
+/// Sets a variable in a user file, replacing the assignment at `lines` if given and adding a
+/// new one at the end otherwise.
+pub fn write_variable(
+    path: &Path,
+    lines: Option<Range<usize>>,
+    key: &str,
+    value: &str,
+) -> Result<(), String> {
+    validate(key, value)?;
+
+    let line = format_assignment(key, value);
+    edit_file(path, lines, Some(line))
+}
+
+/// Removes the assignment at `lines` from a user file.
+pub fn remove_variable(path: &Path, lines: Range<usize>) -> Result<(), String> {
+    edit_file(path, Some(lines), None)
+}
+
+fn edit_file(path: &Path, lines: Option<Range<usize>>, replacement: Option<String>) -> Result<(), String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(why) if why.kind() == std::io::ErrorKind::NotFound && lines.is_none() => String::new(),
+        Err(why) => return Err(why.to_string()),
+    };
+
+    let mut file_lines: Vec<&str> = contents.lines().collect();
+
+    match lines {
+        Some(lines) => {
+            // make sure the file hasn't changed since it was read
+            let (assignments, _) = parse(&contents);
+            if !assignments.iter().any(|assignment| assignment.lines == lines) {
+                return Err(fl!("environment-error", "changed", path = path.display().to_string()));
+            }
+
+            file_lines.splice(lines, replacement.as_deref());
+        }
+        None => file_lines.extend(replacement.as_deref()),
+    }
+
+    let mut contents = file_lines.join("\n");
+    contents.push('\n');
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|why| why.to_string())?;
+    }
+
+    fs::write(path, contents).map_err(|why| why.to_string())
+}
+
+/// State behind the drawer for adding or editing one of the user's variables.
+#[derive(Debug, Clone, Default)]
+pub struct VariableEditor {
+    /// Name of the file in the user's directory
+    pub file: String,
+    pub key: String,
+    pub value: String,
+
+    /// The assignment being edited, or `None` when adding a variable
+    pub editing: Option<Range<usize>>,
+
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum VariableMessage {
+    File(String),
+    Key(String),
+    Value(String),
+    Save,
+    Cancel,
+}
+
+impl VariableEditor {
+    pub fn new() -> Self {
+        VariableEditor {
+            file: DEFAULT_FILE.to_string(),
+            ..VariableEditor::default()
+        }
+    }
+
+    pub fn edit(path: &Path, assignment: &Assignment) -> Self {
+        VariableEditor {
+            file: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            key: assignment.key.clone(),
+            value: assignment.value.clone(),
+            editing: Some(assignment.lines.clone()),
+            error: None,
+        }
+    }
+
+    pub fn update(&mut self, message: VariableMessage) {
+        match message {
+            VariableMessage::File(file) => self.file = file,
+            VariableMessage::Key(key) => self.key = key,
+            VariableMessage::Value(value) => self.value = value,
+            // handled by the application, as these close the drawer
+            VariableMessage::Save | VariableMessage::Cancel => {}
+        }
+    }
+
+    /// Writes the variable to its file in the user's directory.
+    pub fn save(&self, dirs: &EnvDirs) -> Result<(), String> {
+        let file = self.file.trim();
+        if !file.ends_with(".conf") || file.contains('/') || file.starts_with('.') {
+            return Err(fl!("environment-error", "invalid-file"));
+        }
+
+        write_variable(
+            &dirs.user.join(file),
+            self.editing.clone(),
+            self.key.trim(),
+            &self.value,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory to write fixture files in.
+    fn fixture_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("environment-{name}-{}", std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn env_file(path: &str, is_user: bool, contents: &str) -> EnvFile {
+        let (assignments, errors) = parse(contents);
+        assert!(errors.is_empty(), "{errors:?}");
+
+        EnvFile {
+            path: PathBuf::from(path),
+            is_user,
+            masked_by: None,
+            assignments,
+            errors,
+        }
+    }
+
+    fn values(assignments: &[Assignment]) -> Vec<(&str, &str, Range<usize>)> {
+        assignments
+            .iter()
+            .map(|assignment| (assignment.key.as_str(), assignment.value.as_str(), assignment.lines.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn plain_lines() {
+        let (assignments, errors) = parse("# comment\n; also a comment\n\nA=1\n  B = two words  \nC=\n");
+
+        assert!(errors.is_empty());
+        assert_eq!(
+            values(&assignments),
+            [("A", "1", 3..4), ("B", "two words", 4..5), ("C", "", 5..6)]
+        );
+    }
+
+    #[test]
+    fn continued_lines() {
+        let (assignments, errors) = parse("PATH=/a:\\\n/b:\\\n/c\nNEXT=x\nLAST=end\\");
+
+        assert!(errors.is_empty());
+        assert_eq!(
+            values(&assignments),
+            [("PATH", "/a:/b:/c", 0..3), ("NEXT", "x", 3..4), ("LAST", "end", 4..5)]
+        );
+    }
+
+    #[test]
+    fn quoted_values() {
+        let contents = concat!(
+            "A=\"one \\\"two\\\" \\$x \\n\"\n",
+            "B='a \\ \"b\"'\n",
+            "C=\"first\n",
+            "second\"\n",
+            "D=\"joined \\\n",
+            "line\"\n",
+            "E=un\"quoted \"middle\n",
+            "F=escaped\\ space\n",
+        );
+        let (assignments, errors) = parse(contents);
+
+        assert!(errors.is_empty());
+        assert_eq!(
+            values(&assignments),
+            [
+                ("A", "one \"two\" $x \\n", 0..1),
+                ("B", "a \\ \"b\"", 1..2),
+                ("C", "first\nsecond", 2..4),
+                ("D", "joined line", 4..6),
+                ("E", "unquoted middle", 6..7),
+                ("F", "escaped space", 7..8),
+            ]
+        );
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let (assignments, errors) = parse("NO_EQUALS\n1BAD=x\nBAD-NAME=y\nGOOD=z\nOPEN=\"never closed\nX=1\n");
+
+        assert_eq!(values(&assignments), [("GOOD", "z", 3..4)]);
+        assert_eq!(
+            errors,
+            [
+                SyntaxError {
+                    line: 0,
+                    kind: SyntaxErrorKind::MissingEquals,
+                },
+                SyntaxError {
+                    line: 1,
+                    kind: SyntaxErrorKind::InvalidName("1BAD".to_string()),
+                },
+                SyntaxError {
+                    line: 2,
+                    kind: SyntaxErrorKind::InvalidName("BAD-NAME".to_string()),
+                },
+                SyntaxError {
+                    line: 4,
+                    kind: SyntaxErrorKind::UnterminatedQuote,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn expansions() {
+        let variables: HashMap<&str, &str> = [("A", "1"), ("B", "2"), ("EMPTY", "")].into_iter().collect();
+        let lookup = |name: &str| variables.get(name).map(|value| value.to_string());
+        let expand = |value| expand(value, &lookup);
+
+        assert_eq!(expand("$A/${B}"), "1/2");
+        assert_eq!(expand("$A_B ${A}_B"), " 1_B");
+        assert_eq!(expand("${UNSET:-default}"), "default");
+        assert_eq!(expand("${EMPTY:-default}"), "default");
+        assert_eq!(expand("${A:-default}"), "1");
+        assert_eq!(expand("${A:+alternative}"), "alternative");
+        assert_eq!(expand("${EMPTY:+alternative}"), "");
+        assert_eq!(expand("${UNSET:-${A}x}"), "1x");
+        assert_eq!(expand("a$ b$"), "a$ b$");
+        assert_eq!(expand("${A"), "${A");
+        assert_eq!(expand("$UNSET"), "");
+    }
+
+    #[test]
+    fn merged_variables() {
+        let system = env_file("/etc/environment.d/10-system.conf", false, "PATH=$PATH:/opt\nEDITOR=nano\n");
+        let user = env_file("/home/user/.config/environment.d/60-user.conf", true, "EDITOR=vim\nPATH=$PATH:/home\n");
+        let base: HashMap<String, String> = [("PATH".to_string(), "/usr/bin".to_string())].into_iter().collect();
+
+        let merged = merge(&[system, user], &base);
+        let variable = |key: &str| merged.iter().find(|variable| variable.key == key).unwrap();
+
+        assert_eq!(merged.len(), 2);
+
+        // building on the system value doesn't shadow it
+        let path = variable("PATH");
+        assert_eq!(path.value, "/usr/bin:/opt:/home");
+        assert!(path.is_user);
+        assert!(path.shadowed.is_empty());
+
+        let editor = variable("EDITOR");
+        assert_eq!(editor.value, "vim");
+        assert_eq!(editor.source, PathBuf::from("/home/user/.config/environment.d/60-user.conf"));
+        assert_eq!(editor.shadowed.len(), 1);
+        assert_eq!(editor.shadowed[0].path, PathBuf::from("/etc/environment.d/10-system.conf"));
+        assert_eq!(editor.shadowed[0].value, "nano");
+    }
+
+    #[test]
+    fn later_system_file_undoes_override() {
+        let first = env_file("/usr/lib/environment.d/10-first.conf", false, "EDITOR=nano\n");
+        let user = env_file("/home/user/.config/environment.d/50-user.conf", true, "EDITOR=vim\n");
+        let last = env_file("/usr/lib/environment.d/90-last.conf", false, "EDITOR=vi\n");
+
+        let merged = merge(&[first, user, last], &HashMap::new());
+
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].value, "vi");
+        assert!(!merged[0].is_user);
+        assert!(merged[0].shadowed.is_empty());
+    }
+
+    #[test]
+    fn masked_files_are_shadowed() {
+        let user = env_file("/home/user/.config/environment.d/50-same.conf", true, "EDITOR=vim\n");
+        let masked = EnvFile {
+            masked_by: Some(user.path.clone()),
+            ..env_file("/usr/lib/environment.d/50-same.conf", false, "EDITOR=nano\nPAGER=less\n")
+        };
+
+        let merged = merge(&[user, masked], &HashMap::new());
+
+        // the masked file isn't loaded at all
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].value, "vim");
+        assert_eq!(merged[0].shadowed.len(), 1);
+        assert_eq!(merged[0].shadowed[0].path, PathBuf::from("/usr/lib/environment.d/50-same.conf"));
+        assert_eq!(merged[0].shadowed[0].value, "nano");
+    }
+
+    #[test]
+    fn assignments_round_trip() {
+        assert_eq!(format_assignment("KEY", "plain/value"), "KEY=plain/value");
+
+        let values = [
+            "plain",
+            "$HOME/bin:${PATH}",
+            "",
+            "two words",
+            " padded ",
+            "#not a comment",
+            ";not a comment either",
+            "quote \" backslash \\ backtick `",
+            "it's",
+            "tab\tand\nnewline",
+        ];
+
+        for value in values {
+            let line = format_assignment("KEY", value);
+            let (assignments, errors) = parse(&line);
+
+            assert!(errors.is_empty(), "{line}");
+            assert_eq!(assignments.len(), 1, "{line}");
+            assert_eq!(assignments[0].value, value, "{line}");
+        }
+    }
+
+    #[test]
+    fn edits_check_the_file_is_unchanged() {
+        let dir = fixture_dir("edits");
+        let path = dir.join("60-startup-configuration.conf");
+        fs::write(&path, "# kept\nA=1\nB=\"two\\\nlines\"\n").unwrap();
+
+        let (assignments, _) = parse(&fs::read_to_string(&path).unwrap());
+        assert_eq!(assignments[1].lines, 2..4);
+
+        write_variable(&path, Some(assignments[1].lines.clone()), "B", "2").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# kept\nA=1\nB=2\n");
+
+        // the lines read before no longer hold an assignment
+        assert!(write_variable(&path, Some(2..4), "B", "3").is_err());
+        assert!(remove_variable(&path, 0..1).is_err());
+
+        // someone else added a line above, so the assignment moved
+        fs::write(&path, "# kept\nNEW=x\nA=1\nB=2\n").unwrap();
+        assert!(remove_variable(&path, 1..2).is_ok());
+        assert!(write_variable(&path, Some(3..4), "B", "3").is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# kept\nA=1\nB=2\n");
+
+        remove_variable(&path, 1..2).unwrap();
+        write_variable(&path, None, "C", "x y").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# kept\nB=2\nC=\"x y\"\n");
+
+        // a new file is created, but a missing one can't be edited
+        let new = dir.join("new").join("70-new.conf");
+        assert!(write_variable(&new, Some(0..1), "D", "4").is_err());
+        write_variable(&new, None, "D", "4").unwrap();
+        assert_eq!(fs::read_to_string(&new).unwrap(), "D=4\n");
+
+        assert!(write_variable(&new, None, "1D", "4").is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
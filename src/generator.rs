@@ -0,0 +1,390 @@
+// SPDX-License-Identifier: GPL-3
+
+//! Mirrors how `systemd-xdg-autostart-generator` turns autostart entries into
+//! `app-*@autostart.service` units, so sessions using it can be debugged.
+
+use crate::apps::find_executable;
+use crate::entry;
+use crate::fl;
+use crate::systemd::exec_to_exec_start;
+use freedesktop_desktop_entry::DesktopEntry;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+const CONDITION_HELPER: &str = "/usr/lib/systemd/systemd-xdg-autostart-condition";
+const GNOME_CONDITION_HELPER: &str = "/usr/bin/gnome-systemd-autostart-condition";
+const KDE_CONDITION_HELPER: &str = "/usr/bin/kde-systemd-start-condition";
+
+/// Why the generator wouldn't create a unit for an entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GeneratorSkip {
+    /// A higher priority entry with the same file name is used instead
+    Shadowed(PathBuf),
+    Hidden,
+    /// `X-systemd-skip=true`
+    SystemdSkip,
+    /// `Type=` isn't `Application`
+    NotApplication(String),
+    NoExec,
+    /// `X-GNOME-Autostart-Phase=` is set, which gnome-session handles itself
+    AutostartPhase(String),
+    MissingTryExec(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GeneratorOutcome {
+    Unit { name: String, contents: String },
+    Skipped(GeneratorSkip),
+}
+
+#[derive(Debug, Clone)]
+pub struct GeneratedUnit {
+    pub entry: DesktopEntry,
+    pub outcome: GeneratorOutcome,
+}
+
+impl GeneratorSkip {
+    pub fn describe(&self) -> String {
+        match self {
+            GeneratorSkip::Shadowed(path) => {
+                fl!("generator-skip", "shadowed", path = path.display().to_string())
+            }
+            GeneratorSkip::Hidden => fl!("generator-skip", "hidden"),
+            GeneratorSkip::SystemdSkip => fl!("generator-skip", "systemd-skip"),
+            GeneratorSkip::NotApplication(kind) => {
+                fl!("generator-skip", "not-application", kind = kind.clone())
+            }
+            GeneratorSkip::NoExec => fl!("generator-skip", "no-exec"),
+            GeneratorSkip::AutostartPhase(phase) => {
+                fl!("generator-skip", "autostart-phase", phase = phase.clone())
+            }
+            GeneratorSkip::MissingTryExec(binary) => {
+                fl!("generator-skip", "missing-try-exec", binary = binary.clone())
+            }
+        }
+    }
+}
+
+/// Runs the generator over the user entries and the system entries found in `system_dirs`.
+///
+/// Like the generator, entries are deduplicated by file name, with the user's directory taking
+/// precedence over the system directories in `$XDG_CONFIG_DIRS` order.
+pub fn preview(
+    user_apps: &[DesktopEntry],
+    system_apps: &[DesktopEntry],
+    system_dirs: &[PathBuf],
+) -> Vec<GeneratedUnit> {
+    let priority = |path: &Path| {
+        system_dirs
+            .iter()
+            .position(|dir| path.parent() == Some(dir.as_path()))
+            .unwrap_or(system_dirs.len())
+    };
+
+    let mut winners: HashMap<OsString, &DesktopEntry> = HashMap::new();
+    for entry in system_apps {
+        let Some(file_name) = entry.path.file_name() else {
+            continue;
+        };
+
+        match winners.get(file_name) {
+            Some(winner) if priority(&winner.path) <= priority(&entry.path) => {}
+            _ => {
+                winners.insert(file_name.to_owned(), entry);
+            }
+        }
+    }
+    for entry in user_apps {
+        if let Some(file_name) = entry.path.file_name() {
+            winners.insert(file_name.to_owned(), entry);
+        }
+    }
+
+    user_apps
+        .iter()
+        .chain(system_apps)
+        .map(|entry| {
+            let file_name = entry.path.file_name().unwrap_or_default();
+
+            let outcome = match winners.get(file_name) {
+                Some(winner) if winner.path != entry.path => {
+                    GeneratorOutcome::Skipped(GeneratorSkip::Shadowed(winner.path.clone()))
+                }
+                _ => generate_unit(entry),
+            };
+
+            GeneratedUnit {
+                entry: entry.clone(),
+                outcome,
+            }
+        })
+        .collect()
+}
+
+/// Translates a single entry, following the generator's rules.
+pub fn generate_unit(entry: &DesktopEntry) -> GeneratorOutcome {
+    let skip = GeneratorOutcome::Skipped;
+    let is_true = |key: &str| {
+        entry
+            .desktop_entry(key)
+            .is_some_and(|value| value.trim().eq_ignore_ascii_case("true"))
+    };
+
+    // the generator reads X-GNOME-Autostart-enabled=false as Hidden=true
+    if is_true("Hidden")
+        || entry
+            .desktop_entry("X-GNOME-Autostart-enabled")
+            .is_some_and(|value| value.trim().eq_ignore_ascii_case("false"))
+    {
+        return skip(GeneratorSkip::Hidden);
+    }
+
+    if is_true("X-systemd-skip") {
+        return skip(GeneratorSkip::SystemdSkip);
+    }
+
+    let kind = entry.desktop_entry("Type").unwrap_or_default();
+    if kind != "Application" {
+        return skip(GeneratorSkip::NotApplication(kind.to_string()));
+    }
+
+    let Some(exec) = entry.exec().filter(|exec| !exec.trim().is_empty()) else {
+        return skip(GeneratorSkip::NoExec);
+    };
+
+    if let Some(phase) = entry.desktop_entry("X-GNOME-Autostart-Phase") {
+        return skip(GeneratorSkip::AutostartPhase(phase.to_string()));
+    }
+
+    if let Some(try_exec) = entry.desktop_entry("TryExec") {
+        if find_executable(try_exec).is_none() {
+            return skip(GeneratorSkip::MissingTryExec(try_exec.to_string()));
+        }
+    }
+
+    let mut args = entry::split_exec(exec);
+    // the executable is resolved to an absolute path when generating
+    if let Some(program) = args.first_mut() {
+        if !program.starts_with('/') {
+            if let Some(path) = find_executable(program) {
+                *program = host_visible(&path);
+            }
+        }
+    }
+
+    let file_name = entry
+        .path
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let stem = file_name.strip_suffix(".desktop").unwrap_or(&file_name);
+
+    let name = format!("app-{}@autostart.service", escape_name(stem));
+
+    let description = entry
+        .desktop_entry("Name")
+        .unwrap_or(stem)
+        .to_string();
+
+    let mut contents = format!(
+        "# Automatically generated by systemd-xdg-autostart-generator
+
+[Unit]
+Documentation=man:systemd-xdg-autostart-generator(8)
+SourcePath={}
+PartOf=graphical-session.target
+Description={description}
+After=graphical-session.target
+
+[Service]
+Type=exec
+ExitType=cgroup
+ExecStart=:{}
+Restart=no
+TimeoutStopSec=5s
+Slice=app.slice
+",
+        entry.path.display(),
+        exec_to_exec_start(&args),
+    );
+
+    // a trailing `;` leaves an empty desktop, which the generator drops
+    let desktop_list = |desktops: Vec<&str>| {
+        desktops
+            .into_iter()
+            .filter(|desktop| !desktop.is_empty())
+            .collect::<Vec<_>>()
+            .join(":")
+    };
+    let only_show_in = entry.only_show_in().map(desktop_list);
+    let not_show_in = entry.not_show_in().map(desktop_list);
+    if only_show_in.is_some() || not_show_in.is_some() {
+        contents.push_str(&format!(
+            "ExecCondition={CONDITION_HELPER} \"{}\" \"{}\"\n",
+            only_show_in.unwrap_or_default(),
+            not_show_in.unwrap_or_default(),
+        ));
+    }
+
+    // the generator only knows the vendor keys, not the plain `AutostartCondition`
+    if let Some(condition) = entry.desktop_entry("X-GNOME-AutostartCondition") {
+        contents.push_str(&format!(
+            "ExecCondition=-{GNOME_CONDITION_HELPER} \"{}\"\n",
+            condition.replace('%', "%%")
+        ));
+    }
+
+    if let Some(condition) = entry.desktop_entry("X-KDE-autostart-condition") {
+        contents.push_str(&format!(
+            "ExecCondition=-{KDE_CONDITION_HELPER} --condition \"{}\"\n",
+            condition.replace('%', "%%")
+        ));
+    }
+
+    GeneratorOutcome::Unit { name, contents }
+}
+
+/// Escapes `\` and `-` (and anything unprintable) as `\xNN`, like systemd's `xescape()`.
+fn escape_name(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+
+    for byte in name.bytes() {
+        if byte == b'\\' || byte == b'-' || !(0x20..0x7f).contains(&byte) {
+            escaped.push_str(&format!("\\x{byte:02x}"));
+        } else {
+            escaped.push(byte as char);
+        }
+    }
+
+    escaped
+}
+
+/// Strips the sandbox's `/run/host` prefix from a path found by [`find_executable`].
+fn host_visible(path: &Path) -> String {
+    match path.strip_prefix("/run/host") {
+        Ok(path) => Path::new("/").join(path).display().to_string(),
+        Err(_) => path.display().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(keys: &str) -> DesktopEntry {
+        DesktopEntry::from_str(
+            PathBuf::from("/etc/xdg/autostart/foo-bar.desktop"),
+            &format!("[Desktop Entry]\n{keys}"),
+            None::<&[&str]>,
+        )
+        .unwrap()
+    }
+
+    fn contents(outcome: GeneratorOutcome) -> String {
+        match outcome {
+            GeneratorOutcome::Unit { contents, .. } => contents,
+            GeneratorOutcome::Skipped(skip) => panic!("skipped: {skip:?}"),
+        }
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(escape_name("org.gnome.SettingsDaemon"), "org.gnome.SettingsDaemon");
+        assert_eq!(escape_name("foo-bar"), "foo\\x2dbar");
+        assert_eq!(escape_name("a\\b c"), "a\\x5cb c");
+        assert_eq!(escape_name("café"), "caf\\xc3\\xa9");
+    }
+
+    #[test]
+    fn unit() {
+        let outcome = generate_unit(&entry(
+            "Type=Application\nName=Foo Bar\nExec=/opt/foo/bin/foo --tray 100% %U\n",
+        ));
+
+        assert_eq!(
+            outcome,
+            GeneratorOutcome::Unit {
+                name: "app-foo\\x2dbar@autostart.service".into(),
+                contents: "# Automatically generated by systemd-xdg-autostart-generator
+
+[Unit]
+Documentation=man:systemd-xdg-autostart-generator(8)
+SourcePath=/etc/xdg/autostart/foo-bar.desktop
+PartOf=graphical-session.target
+Description=Foo Bar
+After=graphical-session.target
+
+[Service]
+Type=exec
+ExitType=cgroup
+ExecStart=:/opt/foo/bin/foo --tray 100%%
+Restart=no
+TimeoutStopSec=5s
+Slice=app.slice
+"
+                .into(),
+            }
+        );
+    }
+
+    #[test]
+    fn skipped() {
+        let skipped = |keys: &str| match generate_unit(&entry(keys)) {
+            GeneratorOutcome::Skipped(skip) => Some(skip),
+            GeneratorOutcome::Unit { .. } => None,
+        };
+
+        assert_eq!(skipped("Type=Application\nExec=foo\nHidden=true"), Some(GeneratorSkip::Hidden));
+        assert_eq!(
+            skipped("Type=Application\nExec=foo\nX-GNOME-Autostart-enabled=false"),
+            Some(GeneratorSkip::Hidden)
+        );
+        assert_eq!(
+            skipped("Type=Application\nExec=foo\nX-systemd-skip=true"),
+            Some(GeneratorSkip::SystemdSkip)
+        );
+        assert_eq!(skipped("Type=Link\nExec=foo"), Some(GeneratorSkip::NotApplication("Link".into())));
+        assert_eq!(skipped("Type=Application\nExec= "), Some(GeneratorSkip::NoExec));
+        assert_eq!(
+            skipped("Type=Application\nExec=foo\nX-GNOME-Autostart-Phase=Initialization"),
+            Some(GeneratorSkip::AutostartPhase("Initialization".into()))
+        );
+        assert_eq!(
+            skipped("Type=Application\nExec=foo\nTryExec=/nonexistent/foo"),
+            Some(GeneratorSkip::MissingTryExec("/nonexistent/foo".into()))
+        );
+    }
+
+    #[test]
+    fn conditions() {
+        let conditions = |keys: &str| {
+            contents(generate_unit(&entry(&format!("Type=Application\nExec=/opt/foo\n{keys}"))))
+                .lines()
+                .filter(|line| line.starts_with("ExecCondition="))
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            conditions("OnlyShowIn=GNOME;KDE;"),
+            [format!("ExecCondition={CONDITION_HELPER} \"GNOME:KDE\" \"\"")]
+        );
+        assert_eq!(
+            conditions("NotShowIn=COSMIC;"),
+            [format!("ExecCondition={CONDITION_HELPER} \"\" \"COSMIC\"")]
+        );
+        assert_eq!(
+            conditions("X-GNOME-AutostartCondition=unless-exists foo%"),
+            [format!("ExecCondition=-{GNOME_CONDITION_HELPER} \"unless-exists foo%%\"")]
+        );
+        assert_eq!(
+            conditions("X-KDE-autostart-condition=kfoorc:General:Autostart:true"),
+            [format!(
+                "ExecCondition=-{KDE_CONDITION_HELPER} --condition \"kfoorc:General:Autostart:true\""
+            )]
+        );
+        // the generator ignores the plain key
+        assert!(conditions("AutostartCondition=if-exists foo").is_empty());
+    }
+}
@@ -0,0 +1,438 @@
+// SPDX-License-Identifier: GPL-3
+
+//! Launch history written by `startup-configuration-launcher --record ID`, one file per entry
+//! under `$XDG_STATE_HOME/startup-configuration/history`.
+//!
+//! Each line is tab separated: the launch time, the pid (or `-`), an event and its details.
+//! A launch first gets a `started` line and then, once the command ends, an `exited` or
+//! `signalled` line for the same time and pid. A `sampled` line in between records what the
+//! command used during its first minute.
+
+use chrono::{Local, NaiveDateTime};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// How long after starting a command its resource usage is sampled.
+pub const SAMPLE_AFTER: Duration = Duration::from_secs(60);
+
+/// Once a history file grows past this many lines it's trimmed down to [`KEEP_LINES`].
+const MAX_LINES: usize = 400;
+const KEEP_LINES: usize = 200;
+
+/// What happened to one launch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// No exit was recorded, so it's either still running or ended along with the session
+    Unfinished,
+    Exited { code: i32, duration: Duration },
+    Signalled { signal: i32, duration: Duration },
+    /// A launch condition didn't hold
+    Skipped(String),
+    FailedToStart(String),
+}
+
+/// Resources a command used during its first [`SAMPLE_AFTER`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    pub cpu: Duration,
+
+    /// Peak resident memory in KiB
+    pub peak_rss: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LaunchRecord {
+    pub started: NaiveDateTime,
+    pub pid: Option<u32>,
+    pub outcome: Outcome,
+    pub first_minute: Option<Usage>,
+}
+
+/// A single line of a history file.
+enum Line {
+    Record(LaunchRecord),
+    Sample {
+        started: NaiveDateTime,
+        pid: Option<u32>,
+        usage: Usage,
+    },
+}
+
+impl LaunchRecord {
+    /// A record for a launch happening now.
+    pub fn now(pid: Option<u32>, outcome: Outcome) -> Self {
+        LaunchRecord {
+            started: Local::now().naive_local(),
+            pid,
+            outcome,
+            first_minute: None,
+        }
+    }
+
+    fn prefix(&self) -> String {
+        let started = self.started.format(TIME_FORMAT);
+        let pid = self.pid.map_or_else(|| "-".to_string(), |pid| pid.to_string());
+
+        format!("{started}\t{pid}")
+    }
+
+    fn to_line(&self) -> String {
+        let event = match &self.outcome {
+            Outcome::Unfinished => "started".to_string(),
+            Outcome::Exited { code, duration } => {
+                format!("exited\t{code}\t{}", duration.as_millis())
+            }
+            Outcome::Signalled { signal, duration } => {
+                format!("signalled\t{signal}\t{}", duration.as_millis())
+            }
+            Outcome::Skipped(reason) => format!("skipped\t{}", single_line(reason)),
+            Outcome::FailedToStart(reason) => format!("failed\t{}", single_line(reason)),
+        };
+
+        format!("{}\t{event}\n", self.prefix())
+    }
+}
+
+impl Line {
+    fn parse(line: &str) -> Option<Self> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [started, pid, event, details @ ..] = fields.as_slice() else {
+            return None;
+        };
+
+        let code = || details.first()?.parse::<i32>().ok();
+        let duration = || Some(Duration::from_millis(details.get(1)?.parse::<u64>().ok()?));
+
+        let started = NaiveDateTime::parse_from_str(started, TIME_FORMAT).ok()?;
+        let pid = pid.parse::<u32>().ok();
+
+        let outcome = match *event {
+            "sampled" => {
+                return Some(Line::Sample {
+                    started,
+                    pid,
+                    usage: Usage {
+                        cpu: Duration::from_millis(details.first()?.parse::<u64>().ok()?),
+                        peak_rss: details.get(1)?.parse::<u64>().ok()?,
+                    },
+                })
+            }
+            "started" => Outcome::Unfinished,
+            "exited" => Outcome::Exited {
+                code: code()?,
+                duration: duration()?,
+            },
+            "signalled" => Outcome::Signalled {
+                signal: code()?,
+                duration: duration()?,
+            },
+            "skipped" => Outcome::Skipped(details.first()?.to_string()),
+            "failed" => Outcome::FailedToStart(details.first()?.to_string()),
+            _ => return None,
+        };
+
+        Some(Line::Record(LaunchRecord {
+            started,
+            pid,
+            outcome,
+            first_minute: None,
+        }))
+    }
+}
+
+/// Where history files are kept.
+pub fn history_dir() -> PathBuf {
+    dirs::state_dir()
+        .unwrap_or_else(|| dirs::home_dir().unwrap_or_default().join(".local/state"))
+        .join("startup-configuration/history")
+}
+
+/// The history id of an autostart entry, which is its file name without `.desktop`.
+pub fn entry_id(desktop_file: &Path) -> String {
+    let file_name = desktop_file
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    file_name
+        .strip_suffix(".desktop")
+        .unwrap_or(&file_name)
+        .to_string()
+}
+
+fn history_file(dir: &Path, id: &str) -> PathBuf {
+    // ids come from desktop file names, but keep them from escaping the directory regardless
+    dir.join(format!("{}.log", id.replace('/', "_")))
+}
+
+/// Appends a record to the history of `id`.
+pub fn append(dir: &Path, id: &str, record: &LaunchRecord) -> io::Result<()> {
+    append_line(dir, id, &record.to_line())
+}
+
+/// Records what the launch `record` used during its first minute.
+pub fn append_sample(dir: &Path, id: &str, record: &LaunchRecord, usage: Usage) -> io::Result<()> {
+    append_line(
+        dir,
+        id,
+        &format!(
+            "{}\tsampled\t{}\t{}\n",
+            record.prefix(),
+            usage.cpu.as_millis(),
+            usage.peak_rss
+        ),
+    )
+}
+
+fn append_line(dir: &Path, id: &str, line: &str) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let path = history_file(dir, id);
+
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    file.write_all(line.as_bytes())?;
+
+    trim(&path)
+}
+
+fn trim(path: &Path) -> io::Result<()> {
+    let contents = fs::read_to_string(path)?;
+    let lines: Vec<&str> = contents.lines().collect();
+
+    if lines.len() <= MAX_LINES {
+        return Ok(());
+    }
+
+    let mut kept = lines[lines.len() - KEEP_LINES..].join("\n");
+    kept.push('\n');
+    fs::write(path, kept)
+}
+
+/// Reads the last `limit` launches of `id`, most recent first.
+pub fn read(dir: &Path, id: &str, limit: usize) -> Vec<LaunchRecord> {
+    let Ok(contents) = fs::read_to_string(history_file(dir, id)) else {
+        return Vec::new();
+    };
+
+    let mut records: Vec<LaunchRecord> = Vec::new();
+
+    for line in contents.lines().filter_map(Line::parse) {
+        match line {
+            Line::Record(record) => {
+                // a later line for the same launch replaces its `started` line
+                match records.iter_mut().rev().find(|existing| {
+                    existing.started == record.started && existing.pid == record.pid
+                }) {
+                    Some(existing) if record.pid.is_some() => {
+                        existing.outcome = record.outcome;
+                    }
+                    _ => records.push(record),
+                }
+            }
+            Line::Sample {
+                started,
+                pid,
+                usage,
+            } => {
+                if let Some(existing) = records
+                    .iter_mut()
+                    .rev()
+                    .find(|existing| existing.started == started && existing.pid == pid)
+                {
+                    existing.first_minute = Some(usage);
+                }
+            }
+        }
+    }
+
+    records.into_iter().rev().take(limit).collect()
+}
+
+fn single_line(text: &str) -> String {
+    text.replace(['\t', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// A fresh directory to keep fixture history files in.
+    fn fixture_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("history-{name}-{}", std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn time(hour: u32, minute: u32, second: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 1, 15)
+            .unwrap()
+            .and_hms_opt(hour, minute, second)
+            .unwrap()
+    }
+
+    fn record(started: NaiveDateTime, pid: Option<u32>, outcome: Outcome) -> LaunchRecord {
+        LaunchRecord {
+            started,
+            pid,
+            outcome,
+            first_minute: None,
+        }
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let records = [
+            record(time(9, 0, 0), Some(42), Outcome::Unfinished),
+            record(
+                time(9, 0, 1),
+                Some(42),
+                Outcome::Exited {
+                    code: -1,
+                    duration: Duration::from_millis(1500),
+                },
+            ),
+            record(
+                time(9, 0, 2),
+                Some(7),
+                Outcome::Signalled {
+                    signal: 9,
+                    duration: Duration::from_millis(20),
+                },
+            ),
+            record(time(9, 0, 3), None, Outcome::Skipped("on battery".to_string())),
+            record(time(9, 0, 4), None, Outcome::FailedToStart("not found".to_string())),
+        ];
+
+        for record in records {
+            let line = record.to_line();
+            match Line::parse(line.trim_end_matches('\n')) {
+                Some(Line::Record(parsed)) => assert_eq!(parsed, record, "{line}"),
+                _ => panic!("{line} didn't parse as a record"),
+            }
+        }
+
+        // reasons are kept on one line
+        let line = record(time(9, 0, 5), None, Outcome::Skipped("a\tb\nc".to_string())).to_line();
+        assert_eq!(line, "2025-01-15T09:00:05\t-\tskipped\ta b c\n");
+    }
+
+    #[test]
+    fn sample_lines() {
+        match Line::parse("2025-01-15T09:00:00\t42\tsampled\t1250\t20480") {
+            Some(Line::Sample { started, pid, usage }) => {
+                assert_eq!(started, time(9, 0, 0));
+                assert_eq!(pid, Some(42));
+                assert_eq!(
+                    usage,
+                    Usage {
+                        cpu: Duration::from_millis(1250),
+                        peak_rss: 20480,
+                    }
+                );
+            }
+            _ => panic!("didn't parse as a sample"),
+        }
+    }
+
+    #[test]
+    fn bad_lines() {
+        let lines = [
+            "",
+            "garbage",
+            "2025-01-15T09:00:00\t42",
+            "yesterday\t42\tstarted",
+            "2025-01-15T09:00:00\t42\tvanished",
+            "2025-01-15T09:00:00\t42\texited\t0",
+            "2025-01-15T09:00:00\t42\texited\tzero\t10",
+            "2025-01-15T09:00:00\t42\tsignalled\t9\t-5",
+            "2025-01-15T09:00:00\t42\tskipped",
+            "2025-01-15T09:00:00\t42\tsampled\t1250",
+            "2025-01-15T09:00:00\t42\tsampled\tlots\t20480",
+        ];
+
+        for line in lines {
+            assert!(Line::parse(line).is_none(), "{line}");
+        }
+    }
+
+    #[test]
+    fn launches_are_collected() {
+        let dir = fixture_dir("read");
+        let exited = Outcome::Exited {
+            code: 0,
+            duration: Duration::from_secs(90),
+        };
+        let usage = Usage {
+            cpu: Duration::from_millis(300),
+            peak_rss: 1024,
+        };
+
+        let first = record(time(8, 0, 0), Some(10), Outcome::Unfinished);
+        append(&dir, "app", &first).unwrap();
+        // launches without a pid at the same time are separate launches
+        append(&dir, "app", &record(time(8, 0, 0), None, Outcome::Skipped("idle".to_string()))).unwrap();
+        append(&dir, "app", &record(time(8, 0, 0), None, Outcome::FailedToStart("gone".to_string()))).unwrap();
+        append_sample(&dir, "app", &first, usage).unwrap();
+        append(&dir, "app", &record(time(8, 0, 0), Some(10), exited.clone())).unwrap();
+
+        let mut file = OpenOptions::new().append(true).open(history_file(&dir, "app")).unwrap();
+        file.write_all(b"not a record\n").unwrap();
+        append(&dir, "app", &record(time(9, 0, 0), Some(11), Outcome::Unfinished)).unwrap();
+
+        let records = read(&dir, "app", 10);
+        assert_eq!(
+            records,
+            [
+                record(time(9, 0, 0), Some(11), Outcome::Unfinished),
+                record(time(8, 0, 0), None, Outcome::FailedToStart("gone".to_string())),
+                record(time(8, 0, 0), None, Outcome::Skipped("idle".to_string())),
+                LaunchRecord {
+                    first_minute: Some(usage),
+                    ..record(time(8, 0, 0), Some(10), exited)
+                },
+            ]
+        );
+
+        assert_eq!(read(&dir, "app", 2), records[..2]);
+        assert!(read(&dir, "other", 10).is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn files_are_trimmed() {
+        let dir = fixture_dir("trim");
+        let launch = |second| {
+            let started = time(10, 0, 0) + chrono::Duration::seconds(second);
+            record(started, Some(1), Outcome::Unfinished)
+        };
+
+        for second in 0..MAX_LINES as i64 {
+            append(&dir, "app", &launch(second)).unwrap();
+        }
+        let path = history_file(&dir, "app");
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), MAX_LINES);
+
+        append(&dir, "app", &launch(MAX_LINES as i64)).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), KEEP_LINES);
+        assert_eq!(contents.lines().last().unwrap(), launch(MAX_LINES as i64).to_line().trim_end());
+
+        let records = read(&dir, "app", usize::MAX);
+        assert_eq!(records.len(), KEEP_LINES);
+        assert_eq!(records[0], launch(MAX_LINES as i64));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn ids() {
+        assert_eq!(entry_id(Path::new("/home/user/.config/autostart/org.app.desktop")), "org.app");
+        assert_eq!(entry_id(Path::new("script")), "script");
+        assert_eq!(history_file(Path::new("/history"), "../escape"), PathBuf::from("/history/.._escape.log"));
+    }
+}
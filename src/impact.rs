@@ -0,0 +1,191 @@
+// SPDX-License-Identifier: GPL-3
+
+//! Works out how long after login each autostart entry's process started, and what it used during
+//! its first minute, to find what makes logging in slow.
+
+use crate::processes::{self, Process};
+use chrono::{Local, NaiveDateTime, TimeDelta};
+use freedesktop_desktop_entry::DesktopEntry;
+use startup_configuration::history::{self, Usage};
+use startup_configuration::procfs;
+use std::cmp::Ordering;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// One autostart entry with a running process.
+#[derive(Debug, Clone)]
+pub struct ImpactRow {
+    pub name: String,
+    pub path: PathBuf,
+    pub pid: u32,
+
+    /// How long after the session leader the process started
+    pub delay: Duration,
+
+    /// What the process used in its first minute, if it was recorded or that minute is still going
+    pub first_minute: Option<Usage>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImpactColumn {
+    Name,
+    #[default]
+    Delay,
+    Cpu,
+    Memory,
+}
+
+/// Finds the process which started the current login session, normally the display manager's
+/// session worker.
+///
+/// systemd-logind records it as `LEADER=` in `<run_root>/systemd/sessions/$XDG_SESSION_ID`.
+/// Without logind, the oldest process sharing our audit session id is used instead.
+pub fn session_leader(run_root: &Path, proc_root: &Path) -> Option<u32> {
+    if let Some(leader) = env::var("XDG_SESSION_ID")
+        .ok()
+        .and_then(|id| fs::read_to_string(run_root.join("systemd/sessions").join(id)).ok())
+        .and_then(|session| {
+            session
+                .lines()
+                .find_map(|line| line.strip_prefix("LEADER="))?
+                .trim()
+                .parse::<u32>()
+                .ok()
+        })
+    {
+        return Some(leader);
+    }
+
+    let session_id = |dir: &Path| {
+        fs::read_to_string(dir.join("sessionid"))
+            .ok()
+            .map(|id| id.trim().to_string())
+    };
+    let own_session = session_id(&proc_root.join("self"))?;
+
+    fs::read_dir(proc_root)
+        .ok()?
+        .flatten()
+        .filter_map(|dir| {
+            let pid = dir.file_name().to_str()?.parse::<u32>().ok()?;
+            if session_id(&dir.path())? != own_session {
+                return None;
+            }
+
+            Some((procfs::read_stat(&dir.path())?.started, pid))
+        })
+        .min()
+        .map(|(_, pid)| pid)
+}
+
+/// Looks up the session leader from inside the sandbox, where neither `/run/systemd` nor the
+/// host's `/proc` can be read.
+#[cfg(feature = "flatpak")]
+pub fn host_session_leader() -> Option<u32> {
+    let session = match env::var("XDG_SESSION_ID") {
+        Ok(session) => session,
+        Err(_) => host_output(&["loginctl", "show-user", "--property=Display", "--value"])?,
+    };
+
+    host_output(&["loginctl", "show-session", &session, "--property=Leader", "--value"])?
+        .parse::<u32>()
+        .ok()
+}
+
+#[cfg(feature = "flatpak")]
+fn host_output(command: &[&str]) -> Option<String> {
+    let (program, args) = command.split_first()?;
+    let output = crate::apps::host_command(program).args(args).output().ok()?;
+
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Matches each entry to its running process and measures it against the session leader.
+///
+/// `system_uptime` is how long ago the machine booted, used to tell when the session started.
+pub fn build_report(
+    entries: &[(String, &DesktopEntry)],
+    processes: &[Process],
+    leader: u32,
+    system_uptime: Duration,
+) -> Vec<ImpactRow> {
+    let Some(leader) = processes.iter().find(|process| process.pid == leader) else {
+        return Vec::new();
+    };
+
+    let login_time = Local::now().naive_local()
+        - TimeDelta::from_std(system_uptime.saturating_sub(leader.started)).unwrap_or_default();
+
+    let mut rows: Vec<ImpactRow> = Vec::new();
+
+    for (name, entry) in entries {
+        let Some(process) = processes::find_process(entry, processes) else {
+            continue;
+        };
+
+        // a user entry and the system entry it overrides can match the same process
+        if rows.iter().any(|row| row.pid == process.pid) {
+            continue;
+        }
+
+        rows.push(ImpactRow {
+            name: name.clone(),
+            path: entry.path.clone(),
+            pid: process.pid,
+            delay: process.started.saturating_sub(leader.started),
+            first_minute: first_minute(entry, process, login_time),
+        });
+    }
+
+    rows
+}
+
+fn first_minute(entry: &DesktopEntry, process: &Process, login_time: NaiveDateTime) -> Option<Usage> {
+    // the launcher samples recorded entries a minute after starting them
+    let recorded = history::read(&history::history_dir(), &history::entry_id(&entry.path), 1)
+        .into_iter()
+        .next()
+        .filter(|record| record.started >= login_time - TimeDelta::seconds(1))
+        .and_then(|record| record.first_minute);
+
+    if recorded.is_some() {
+        return recorded;
+    }
+
+    // otherwise the usage so far will do, as long as it's still within the first minute
+    (process.uptime <= history::SAMPLE_AFTER).then_some(Usage {
+        cpu: process.cpu,
+        peak_rss: process.rss,
+    })
+}
+
+/// Sorts the report by `column`, with rows missing a value last.
+pub fn sort_rows(rows: &mut [ImpactRow], column: ImpactColumn, descending: bool) {
+    rows.sort_by(|a, b| {
+        let ordering = match column {
+            ImpactColumn::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            ImpactColumn::Delay => a.delay.cmp(&b.delay),
+            ImpactColumn::Cpu => compare_usage(a, b, |usage| usage.cpu.as_millis() as u64),
+            ImpactColumn::Memory => compare_usage(a, b, |usage| usage.peak_rss),
+        };
+
+        match (a.first_minute.is_some(), b.first_minute.is_some(), column) {
+            (true, false, ImpactColumn::Cpu | ImpactColumn::Memory) => Ordering::Less,
+            (false, true, ImpactColumn::Cpu | ImpactColumn::Memory) => Ordering::Greater,
+            _ if descending => ordering.reverse(),
+            _ => ordering,
+        }
+    });
+}
+
+fn compare_usage(a: &ImpactRow, b: &ImpactRow, value: impl Fn(&Usage) -> u64) -> Ordering {
+    a.first_minute
+        .as_ref()
+        .map(&value)
+        .cmp(&b.first_minute.as_ref().map(&value))
+}
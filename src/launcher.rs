@@ -0,0 +1,751 @@
+// SPDX-License-Identifier: GPL-3
+
+//! Options understood by `startup-configuration-launcher`, which sits in front of an autostart
+//! entry's real command, decides whether it should run at all and with which resource limits.
+
+use chrono::{Datelike, Local, NaiveDateTime, NaiveTime, Weekday};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the launcher binary installed next to the application.
+pub const LAUNCHER_BIN: &str = "startup-configuration-launcher";
+
+#[cfg(feature = "flatpak")]
+const APP_ID: &str = "best.ellie.StartupConfiguration";
+
+/// Separates the launcher's own options from the command it should run.
+const COMMAND_SEPARATOR: &str = "--";
+
+/// A check which has to pass before the wrapped command is launched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    /// A mains power supply is online, or the machine has no battery at all
+    OnAcPower,
+
+    /// The given network interface reports an `up` operstate
+    NetworkUp(String),
+
+    /// The hostname matches a pattern, which may contain `*` and `?` wildcards
+    Hostname(String),
+
+    /// Today is one of the given weekdays
+    Weekdays(Vec<Weekday>),
+
+    /// The local time is inside `[start, end)`, wrapping past midnight if `end < start`
+    TimeWindow(NaiveTime, NaiveTime),
+
+    /// The given file exists
+    FileExists(PathBuf),
+
+    /// The given file does not exist
+    FileMissing(PathBuf),
+}
+
+/// The I/O scheduling class to run a command with, as understood by `ionice`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoClass {
+    /// Only gets disk time when nothing else needs it
+    Idle,
+
+    /// The default class, with a priority from 0 (highest) to 7 (lowest)
+    BestEffort(u8),
+}
+
+/// Priorities and limits applied to the command once its conditions hold.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// Niceness from -20 to 19. Lowering it below 0 needs privileges and is ignored otherwise
+    pub nice: Option<i8>,
+
+    pub io_class: Option<IoClass>,
+
+    /// A systemd `MemoryMax=` value such as `512M` or `20%`
+    pub memory_max: Option<String>,
+
+    /// A systemd `CPUQuota=` percentage, where 100 is one whole CPU
+    pub cpu_quota: Option<u32>,
+}
+
+/// Everything the launcher needs to know before running a command.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LaunchOptions {
+    pub conditions: Vec<Condition>,
+    pub limits: ResourceLimits,
+
+    /// Records each launch in the history under this id, usually the entry's file name
+    pub record: Option<String>,
+}
+
+/// The state of the machine that conditions are evaluated against.
+///
+/// Paths are kept configurable so that conditions can be checked against a fixture `/sys`.
+#[derive(Debug, Clone)]
+pub struct Probe {
+    pub sys_root: PathBuf,
+    pub hostname: String,
+    pub now: NaiveDateTime,
+}
+
+impl Probe {
+    pub fn from_system() -> Self {
+        let hostname = fs::read_to_string("/proc/sys/kernel/hostname")
+            .or_else(|_| fs::read_to_string("/etc/hostname"))
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+
+        Probe {
+            sys_root: PathBuf::from("/sys"),
+            hostname,
+            now: Local::now().naive_local(),
+        }
+    }
+}
+
+impl Condition {
+    pub fn evaluate(&self, probe: &Probe) -> bool {
+        match self {
+            Condition::OnAcPower => on_ac_power(&probe.sys_root),
+            Condition::NetworkUp(interface) => {
+                let operstate = probe
+                    .sys_root
+                    .join("class/net")
+                    .join(interface)
+                    .join("operstate");
+
+                read_trimmed(&operstate).is_some_and(|state| state == "up")
+            }
+            Condition::Hostname(pattern) => wildcard_match(pattern, &probe.hostname),
+            Condition::Weekdays(days) => days.contains(&probe.now.weekday()),
+            Condition::TimeWindow(start, end) => {
+                let time = probe.now.time();
+                if start <= end {
+                    *start <= time && time < *end
+                } else {
+                    time >= *start || time < *end
+                }
+            }
+            Condition::FileExists(path) => expand_home(path).exists(),
+            Condition::FileMissing(path) => !expand_home(path).exists(),
+        }
+    }
+
+    fn to_args(&self) -> Vec<String> {
+        match self {
+            Condition::OnAcPower => vec!["--on-ac".into()],
+            Condition::NetworkUp(interface) => vec!["--network-up".into(), interface.clone()],
+            Condition::Hostname(pattern) => vec!["--hostname".into(), pattern.clone()],
+            Condition::Weekdays(days) => vec!["--weekdays".into(), format_weekdays(days)],
+            Condition::TimeWindow(start, end) => {
+                vec!["--time".into(), format_time_window(*start, *end)]
+            }
+            Condition::FileExists(path) => {
+                vec!["--if-exists".into(), path.to_string_lossy().into_owned()]
+            }
+            Condition::FileMissing(path) => {
+                vec!["--unless-exists".into(), path.to_string_lossy().into_owned()]
+            }
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::OnAcPower => write!(f, "on AC power"),
+            Condition::NetworkUp(interface) => write!(f, "{interface} is up"),
+            Condition::Hostname(pattern) => write!(f, "hostname is {pattern}"),
+            Condition::Weekdays(days) => write!(f, "weekday is {}", format_weekdays(days)),
+            Condition::TimeWindow(start, end) => {
+                write!(f, "time is {}", format_time_window(*start, *end))
+            }
+            Condition::FileExists(path) => write!(f, "{} exists", path.display()),
+            Condition::FileMissing(path) => write!(f, "{} does not exist", path.display()),
+        }
+    }
+}
+
+impl IoClass {
+    /// Parses `idle`, `best-effort` or `best-effort:LEVEL`.
+    pub fn parse(input: &str) -> Result<IoClass, String> {
+        let input = input.trim();
+        let (class, level) = input.split_once(':').unwrap_or((input, "4"));
+
+        match class {
+            "idle" => Ok(IoClass::Idle),
+            "best-effort" => match level.trim().parse::<u8>() {
+                Ok(level) if level <= 7 => Ok(IoClass::BestEffort(level)),
+                _ => Err(format!("invalid best-effort level {level}, expected 0 to 7")),
+            },
+            _ => Err(format!("invalid I/O class {class}, expected idle or best-effort")),
+        }
+    }
+
+    fn to_ionice_args(self) -> Vec<String> {
+        match self {
+            IoClass::Idle => vec!["-c".into(), "3".into()],
+            IoClass::BestEffort(level) => {
+                vec!["-c".into(), "2".into(), "-n".into(), level.to_string()]
+            }
+        }
+    }
+}
+
+impl fmt::Display for IoClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IoClass::Idle => write!(f, "idle"),
+            IoClass::BestEffort(level) => write!(f, "best-effort:{level}"),
+        }
+    }
+}
+
+impl ResourceLimits {
+    pub fn is_empty(&self) -> bool {
+        *self == ResourceLimits::default()
+    }
+
+    fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+
+        if let Some(nice) = self.nice {
+            args.extend(["--nice".into(), nice.to_string()]);
+        }
+        if let Some(io_class) = self.io_class {
+            args.extend(["--ionice".into(), io_class.to_string()]);
+        }
+        if let Some(memory_max) = &self.memory_max {
+            args.extend(["--memory-max".into(), memory_max.clone()]);
+        }
+        if let Some(cpu_quota) = self.cpu_quota {
+            args.extend(["--cpu-quota".into(), format!("{cpu_quota}%")]);
+        }
+
+        args
+    }
+
+    /// Prefixes `command` with the tools which apply these limits.
+    ///
+    /// Memory and CPU limits need a cgroup, so the command is started in a transient scope with
+    /// `systemd-run`. Niceness and I/O class are inherited, so `nice` and `ionice` go inside it.
+    pub fn apply(&self, command: &[String]) -> Vec<String> {
+        let mut args: Vec<String> = Vec::new();
+
+        if self.memory_max.is_some() || self.cpu_quota.is_some() {
+            args.extend(["systemd-run", "--user", "--scope", "--quiet", "--collect"].map(String::from));
+
+            if let Some(memory_max) = &self.memory_max {
+                args.extend(["-p".into(), format!("MemoryMax={memory_max}")]);
+            }
+            if let Some(cpu_quota) = self.cpu_quota {
+                args.extend(["-p".into(), format!("CPUQuota={cpu_quota}%")]);
+            }
+
+            args.push(COMMAND_SEPARATOR.into());
+        }
+
+        if let Some(nice) = self.nice {
+            args.extend(["nice".into(), "-n".into(), nice.to_string()]);
+        }
+        if let Some(io_class) = self.io_class {
+            args.push("ionice".into());
+            args.extend(io_class.to_ionice_args());
+        }
+
+        args.extend_from_slice(command);
+        args
+    }
+}
+
+/// Parses a niceness from -20 to 19.
+pub fn parse_nice(input: &str) -> Result<i8, String> {
+    match input.trim().parse::<i8>() {
+        Ok(nice) if (-20..=19).contains(&nice) => Ok(nice),
+        _ => Err(format!("invalid nice level {input}, expected -20 to 19")),
+    }
+}
+
+/// Checks a systemd size such as `512M`, `1.5G`, `20%` or `infinity`.
+pub fn parse_memory_max(input: &str) -> Result<String, String> {
+    let input = input.trim();
+    let invalid = || format!("invalid memory limit {input}, expected a size such as 512M or 20%");
+
+    if input == "infinity" {
+        return Ok(input.to_string());
+    }
+
+    let number = match input.strip_suffix('%') {
+        Some(percentage) => percentage,
+        None => input.strip_suffix(['K', 'M', 'G', 'T']).unwrap_or(input),
+    };
+
+    match number.parse::<f64>() {
+        Ok(number) if number > 0.0 && number.is_finite() => Ok(input.to_string()),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parses a CPU quota percentage, with or without the `%` sign.
+pub fn parse_cpu_quota(input: &str) -> Result<u32, String> {
+    let input = input.trim();
+
+    match input.strip_suffix('%').unwrap_or(input).trim().parse::<u32>() {
+        Ok(quota) if quota > 0 => Ok(quota),
+        _ => Err(format!("invalid CPU quota {input}, expected a percentage such as 50%")),
+    }
+}
+
+impl LaunchOptions {
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty() && self.limits.is_empty() && self.record.is_none()
+    }
+
+    /// Returns the first condition which does not hold, if any.
+    pub fn failed_condition(&self, probe: &Probe) -> Option<&Condition> {
+        self.conditions.iter().find(|condition| !condition.evaluate(probe))
+    }
+
+    /// Parses the launcher's arguments, returning the options and the command to run.
+    pub fn parse(args: &[String]) -> Result<(LaunchOptions, Vec<String>), String> {
+        let mut options = LaunchOptions::default();
+        let mut iter = args.iter();
+
+        while let Some(arg) = iter.next() {
+            if arg == COMMAND_SEPARATOR {
+                return Ok((options, iter.cloned().collect()));
+            }
+
+            let mut value = || {
+                iter.next()
+                    .cloned()
+                    .ok_or_else(|| format!("missing value for {arg}"))
+            };
+
+            match arg.as_str() {
+                "--nice" => {
+                    options.limits.nice = Some(parse_nice(&value()?)?);
+                    continue;
+                }
+                "--ionice" => {
+                    options.limits.io_class = Some(IoClass::parse(&value()?)?);
+                    continue;
+                }
+                "--memory-max" => {
+                    options.limits.memory_max = Some(parse_memory_max(&value()?)?);
+                    continue;
+                }
+                "--cpu-quota" => {
+                    options.limits.cpu_quota = Some(parse_cpu_quota(&value()?)?);
+                    continue;
+                }
+                "--record" => {
+                    options.record = Some(value()?);
+                    continue;
+                }
+                _ => {}
+            }
+
+            let condition = match arg.as_str() {
+                "--on-ac" => Condition::OnAcPower,
+                "--network-up" => Condition::NetworkUp(value()?),
+                "--hostname" => Condition::Hostname(value()?),
+                "--weekdays" => Condition::Weekdays(parse_weekdays(&value()?)?),
+                "--time" => {
+                    let (start, end) = parse_time_window(&value()?)?;
+                    Condition::TimeWindow(start, end)
+                }
+                "--if-exists" => Condition::FileExists(PathBuf::from(value()?)),
+                "--unless-exists" => Condition::FileMissing(PathBuf::from(value()?)),
+                _ => return Err(format!("unknown option {arg}")),
+            };
+
+            options.conditions.push(condition);
+        }
+
+        Err(format!("expected {COMMAND_SEPARATOR} followed by a command"))
+    }
+
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args: Vec<String> = self
+            .conditions
+            .iter()
+            .flat_map(Condition::to_args)
+            .collect();
+        args.extend(self.limits.to_args());
+        if let Some(id) = &self.record {
+            args.extend(["--record".into(), id.clone()]);
+        }
+        args
+    }
+
+    /// Builds the full command line which runs `command` through the launcher.
+    pub fn wrap(&self, command: &[String]) -> Vec<String> {
+        let mut args = launcher_command();
+        args.extend(self.to_args());
+        args.push(COMMAND_SEPARATOR.into());
+        args.extend_from_slice(command);
+        args
+    }
+
+    /// Splits a command line produced by [`LaunchOptions::wrap`] back into its options and the
+    /// wrapped command. Commands that don't go through the launcher are returned as-is.
+    pub fn unwrap(args: &[String]) -> (LaunchOptions, Vec<String>) {
+        let prefix = launcher_command();
+
+        let rest = if args.starts_with(&prefix) {
+            &args[prefix.len()..]
+        } else if args
+            .first()
+            .is_some_and(|program| Path::new(program).ends_with(LAUNCHER_BIN))
+        {
+            &args[1..]
+        } else {
+            return (LaunchOptions::default(), args.to_vec());
+        };
+
+        match LaunchOptions::parse(rest) {
+            Ok(parsed) => parsed,
+            Err(_) => (LaunchOptions::default(), args.to_vec()),
+        }
+    }
+}
+
+/// The command which starts the launcher from an autostart entry.
+pub fn launcher_command() -> Vec<String> {
+    // the sandbox's /app/bin isn't visible to the host session, so go through flatpak
+    #[cfg(feature = "flatpak")]
+    return vec![
+        "flatpak".into(),
+        "run".into(),
+        format!("--command={LAUNCHER_BIN}"),
+        APP_ID.into(),
+    ];
+
+    #[cfg(not(feature = "flatpak"))]
+    vec![LAUNCHER_BIN.into()]
+}
+
+fn on_ac_power(sys_root: &Path) -> bool {
+    let Ok(supplies) = fs::read_dir(sys_root.join("class/power_supply")) else {
+        // no power supply information at all, most likely a desktop
+        return true;
+    };
+
+    let mut has_battery = false;
+
+    for supply in supplies.flatten() {
+        let path = supply.path();
+        match read_trimmed(&path.join("type")).as_deref() {
+            Some("Mains") | Some("USB")
+                if read_trimmed(&path.join("online")).is_some_and(|online| online == "1") =>
+            {
+                return true;
+            }
+            Some("Battery") => has_battery = true,
+            _ => {}
+        }
+    }
+
+    !has_battery
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|contents| contents.trim().to_string())
+}
+
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Matches `text` against a shell-style pattern supporting `*` and `?`.
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p].eq_ignore_ascii_case(&text[t])) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Parses a comma separated list of weekdays, allowing ranges such as `mon-fri`.
+pub fn parse_weekdays(input: &str) -> Result<Vec<Weekday>, String> {
+    let mut days = Vec::new();
+
+    for part in input.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let from = parse_weekday(from)?;
+                let to = parse_weekday(to)?;
+
+                let mut day = from;
+                loop {
+                    if !days.contains(&day) {
+                        days.push(day);
+                    }
+                    if day == to {
+                        break;
+                    }
+                    day = day.succ();
+                }
+            }
+            None => {
+                let day = parse_weekday(part)?;
+                if !days.contains(&day) {
+                    days.push(day);
+                }
+            }
+        }
+    }
+
+    if days.is_empty() {
+        return Err("no weekdays given".into());
+    }
+
+    Ok(days)
+}
+
+fn parse_weekday(input: &str) -> Result<Weekday, String> {
+    input
+        .trim()
+        .parse::<Weekday>()
+        .map_err(|_| format!("invalid weekday {input}"))
+}
+
+pub fn format_weekdays(days: &[Weekday]) -> String {
+    days.iter()
+        .map(|day| day.to_string().to_lowercase())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Parses a window such as `08:00-17:30`.
+pub fn parse_time_window(input: &str) -> Result<(NaiveTime, NaiveTime), String> {
+    let Some((start, end)) = input.split_once('-') else {
+        return Err(format!("invalid time window {input}"));
+    };
+
+    let parse = |time: &str| {
+        NaiveTime::parse_from_str(time.trim(), "%H:%M")
+            .map_err(|_| format!("invalid time {}", time.trim()))
+    };
+
+    Ok((parse(start)?, parse(end)?))
+}
+
+pub fn format_time_window(start: NaiveTime, end: NaiveTime) -> String {
+    format!("{}-{}", start.format("%H:%M"), end.format("%H:%M"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// A fresh directory to build a fixture `/sys` in.
+    fn fixture_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("launcher-{name}-{}", std::process::id()));
+        _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn write(path: PathBuf, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn probe(sys_root: PathBuf) -> Probe {
+        Probe {
+            sys_root,
+            hostname: "work-laptop".into(),
+            // a Wednesday
+            now: NaiveDate::from_ymd_opt(2025, 1, 15).unwrap().and_hms_opt(9, 30, 0).unwrap(),
+        }
+    }
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn conditions_round_trip_through_args() {
+        let options = LaunchOptions {
+            conditions: vec![
+                Condition::OnAcPower,
+                Condition::NetworkUp("wlan0".into()),
+                Condition::Hostname("work-*".into()),
+                Condition::Weekdays(vec![Weekday::Mon, Weekday::Tue, Weekday::Fri]),
+                Condition::TimeWindow(time(22, 0), time(6, 30)),
+                Condition::FileExists("~/.work".into()),
+                Condition::FileMissing("/run/no-autostart".into()),
+            ],
+            limits: ResourceLimits {
+                nice: Some(10),
+                io_class: Some(IoClass::BestEffort(6)),
+                memory_max: Some("512M".into()),
+                cpu_quota: Some(50),
+            },
+            record: Some("backup.desktop".into()),
+        };
+
+        let mut args = options.to_args();
+        args.push(COMMAND_SEPARATOR.into());
+        args.extend(strings(&["backup", "--quiet"]));
+
+        assert_eq!(LaunchOptions::parse(&args), Ok((options, strings(&["backup", "--quiet"]))));
+    }
+
+    #[test]
+    fn conditions_display() {
+        assert_eq!(Condition::OnAcPower.to_string(), "on AC power");
+        assert_eq!(Condition::NetworkUp("eth0".into()).to_string(), "eth0 is up");
+        assert_eq!(
+            Condition::Weekdays(vec![Weekday::Sat, Weekday::Sun]).to_string(),
+            "weekday is sat,sun"
+        );
+        assert_eq!(
+            Condition::TimeWindow(time(8, 0), time(17, 30)).to_string(),
+            "time is 08:00-17:30"
+        );
+        assert_eq!(
+            Condition::FileMissing("/tmp/x".into()).to_string(),
+            "/tmp/x does not exist"
+        );
+    }
+
+    #[test]
+    fn parse_rejects_bad_arguments() {
+        assert!(LaunchOptions::parse(&strings(&["--on-ac", "true"])).is_err());
+        assert!(LaunchOptions::parse(&strings(&["--bogus", "--", "true"])).is_err());
+        assert!(LaunchOptions::parse(&strings(&["--network-up"])).is_err());
+        assert!(LaunchOptions::parse(&strings(&["--time", "25:00-26:00", "--", "true"])).is_err());
+        assert!(LaunchOptions::parse(&strings(&["--nice", "40", "--", "true"])).is_err());
+    }
+
+    #[test]
+    fn wrap_and_unwrap() {
+        let options = LaunchOptions {
+            conditions: vec![Condition::Hostname("desk*".into())],
+            ..LaunchOptions::default()
+        };
+        let command = strings(&["syncthing", "-no-browser"]);
+
+        let wrapped = options.wrap(&command);
+        assert!(wrapped.starts_with(&launcher_command()));
+        assert_eq!(LaunchOptions::unwrap(&wrapped), (options, command.clone()));
+
+        // commands which don't go through the launcher come back unchanged
+        assert_eq!(
+            LaunchOptions::unwrap(&command),
+            (LaunchOptions::default(), command.clone())
+        );
+
+        // and so does anything the launcher couldn't parse
+        let broken = strings(&[LAUNCHER_BIN, "--bogus"]);
+        assert_eq!(LaunchOptions::unwrap(&broken), (LaunchOptions::default(), broken.clone()));
+    }
+
+    #[test]
+    fn ac_power_from_power_supplies() {
+        let root = fixture_root("ac");
+        let supplies = root.join("class/power_supply");
+        let probe = probe(root.clone());
+
+        // no power supply information, as on most desktops
+        assert!(Condition::OnAcPower.evaluate(&probe));
+
+        write(supplies.join("BAT0/type"), "Battery\n");
+        write(supplies.join("AC/type"), "Mains\n");
+        write(supplies.join("AC/online"), "0\n");
+        assert!(!Condition::OnAcPower.evaluate(&probe));
+
+        write(supplies.join("AC/online"), "1\n");
+        assert!(Condition::OnAcPower.evaluate(&probe));
+
+        // charging over USB-C counts too
+        write(supplies.join("AC/online"), "0\n");
+        write(supplies.join("ucsi-source-psy-1/type"), "USB\n");
+        write(supplies.join("ucsi-source-psy-1/online"), "1\n");
+        assert!(Condition::OnAcPower.evaluate(&probe));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn network_interface_state() {
+        let root = fixture_root("net");
+        write(root.join("class/net/wlan0/operstate"), "up\n");
+        write(root.join("class/net/eth0/operstate"), "down\n");
+        let probe = probe(root.clone());
+
+        assert!(Condition::NetworkUp("wlan0".into()).evaluate(&probe));
+        assert!(!Condition::NetworkUp("eth0".into()).evaluate(&probe));
+        assert!(!Condition::NetworkUp("wg0".into()).evaluate(&probe));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn hostname_and_time_conditions() {
+        let root = fixture_root("time");
+        let probe = probe(root.clone());
+
+        assert!(Condition::Hostname("WORK-*".into()).evaluate(&probe));
+        assert!(Condition::Hostname("work-lapto?".into()).evaluate(&probe));
+        assert!(!Condition::Hostname("desktop".into()).evaluate(&probe));
+
+        assert!(Condition::Weekdays(parse_weekdays("mon-fri").unwrap()).evaluate(&probe));
+        assert!(!Condition::Weekdays(parse_weekdays("sat,sun").unwrap()).evaluate(&probe));
+        assert!(Condition::Weekdays(parse_weekdays("sat-wed").unwrap()).evaluate(&probe));
+
+        assert!(Condition::TimeWindow(time(9, 0), time(17, 0)).evaluate(&probe));
+        assert!(!Condition::TimeWindow(time(9, 30), time(9, 30)).evaluate(&probe));
+        assert!(!Condition::TimeWindow(time(22, 0), time(6, 0)).evaluate(&probe));
+        assert!(Condition::TimeWindow(time(22, 0), time(9, 45)).evaluate(&probe));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn file_conditions() {
+        let root = fixture_root("files");
+        let marker = root.join("marker");
+        let probe = probe(root.clone());
+
+        assert!(!Condition::FileExists(marker.clone()).evaluate(&probe));
+        assert!(Condition::FileMissing(marker.clone()).evaluate(&probe));
+
+        write(marker.clone(), "");
+        assert!(Condition::FileExists(marker.clone()).evaluate(&probe));
+        assert!(!Condition::FileMissing(marker).evaluate(&probe));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,8 @@
+// SPDX-License-Identifier: GPL-3
+
+//! Code shared between the application and `startup-configuration-launcher`.
+
+pub mod history;
+pub mod launcher;
+pub mod procfs;
+
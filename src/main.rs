@@ -1,8 +1,25 @@
 // SPDX-License-Identifier: GPL-3
 
 mod app;
+mod appimage;
 mod apps;
+mod desktops;
+mod editor;
+mod entry;
+mod environment;
+mod generator;
 mod i18n;
+mod impact;
+mod opener;
+mod origin;
+mod packages;
+mod processes;
+mod runner;
+mod scripts;
+mod session;
+mod snap;
+mod systemd;
+mod terminal;
 
 fn main() -> cosmic::iced::Result {
     // Get the system's preferred languages.
@@ -10,6 +27,13 @@ fn main() -> cosmic::iced::Result {
     // Enable localizations to be applied.
     i18n::init(&requested_languages);
 
+    // `--simulate-login [DESKTOP[:DESKTOP...]]` prints what would launch at login and exits
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "--simulate-login") {
+        session::print_simulation(args.get(1).map(String::as_str));
+        return Ok(());
+    }
+
     // Settings for configuring the application window and iced runtime.
     let settings = cosmic::app::Settings::default().size_limits(
         cosmic::iced::Limits::NONE
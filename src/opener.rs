@@ -0,0 +1,433 @@
+// SPDX-License-Identifier: GPL-3
+
+//! Autostart entries which open a file, folder or URL at login, in whichever application is set
+//! to handle it.
+//!
+//! The handler is looked up the way `xdg-open` would: the `mimeapps.list` files first, then any
+//! installed application declaring the MIME type, trying parent types such as `text/plain` for
+//! `text/markdown` when nothing handles the type itself.
+
+use crate::apps::host_path;
+use crate::entry;
+use crate::fl;
+use crate::scripts;
+use freedesktop_desktop_entry::DesktopEntry;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Marks autostart entries which open something, naming what they open.
+pub const OPEN_KEY: &str = "X-StartupConfiguration-Open";
+
+/// Something to open at login.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    File(PathBuf),
+    Directory(PathBuf),
+    Url(String),
+}
+
+impl Target {
+    /// Reads a target as typed: a URL, or an absolute or `~/` path to something that exists.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err(fl!("open-at-login", "empty"));
+        }
+
+        if let Some(path) = input.strip_prefix("file://") {
+            return Self::from_path(PathBuf::from(percent_decode(path)));
+        }
+
+        if let Some((scheme, rest)) = input.split_once(':') {
+            if is_scheme(scheme) && (rest.starts_with("//") || scheme == "mailto") {
+                return Ok(Target::Url(input.to_string()));
+            }
+        }
+
+        let path = match input.strip_prefix("~/") {
+            Some(relative) => dirs::home_dir().unwrap_or_default().join(relative),
+            None => PathBuf::from(input),
+        };
+
+        if !path.is_absolute() {
+            return Err(fl!("open-at-login", "not-absolute"));
+        }
+
+        Self::from_path(path)
+    }
+
+    /// A target for a file or folder picked in a file chooser.
+    pub fn from_path(path: PathBuf) -> Result<Self, String> {
+        // files shared with the sandbox through the document portal have no path on the host
+        let Some(path) = scripts::host_location(&path) else {
+            return Err(fl!("open-at-login", "sandbox-only"));
+        };
+
+        let visible = host_path(&path);
+        if visible.is_dir() {
+            Ok(Target::Directory(path))
+        } else if visible.exists() {
+            Ok(Target::File(path))
+        } else {
+            Err(fl!("open-at-login", "not-found", path = path.display().to_string()))
+        }
+    }
+
+    /// How the target is passed to its handler.
+    pub fn argument(&self) -> String {
+        match self {
+            Target::File(path) | Target::Directory(path) => path.display().to_string(),
+            Target::Url(url) => url.clone(),
+        }
+    }
+
+    /// A short name for the target: the file or folder name, or the URL's host.
+    pub fn label(&self) -> String {
+        match self {
+            Target::File(path) | Target::Directory(path) => path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.display().to_string()),
+            Target::Url(url) => {
+                let rest = url.split_once(':').map_or(url.as_str(), |(_, rest)| rest);
+                let rest = rest.trim_start_matches('/');
+                rest.split(['/', '?', '#']).next().unwrap_or(rest).to_string()
+            }
+        }
+    }
+
+    pub fn mime_type(&self) -> String {
+        match self {
+            Target::Directory(_) => "inode/directory".to_string(),
+            Target::Url(url) => {
+                let scheme = url.split_once(':').map_or(url.as_str(), |(scheme, _)| scheme);
+                format!("x-scheme-handler/{}", scheme.to_lowercase())
+            }
+            Target::File(path) => file_mime_type(path),
+        }
+    }
+}
+
+/// Finds the application which opens `target`, among the `installed` ones.
+pub fn find_handler<'a>(target: &Target, installed: &'a [DesktopEntry]) -> Option<&'a DesktopEntry> {
+    let lists = mimeapps_lists();
+    let installed_entry = |id: &String| {
+        let appid = id.strip_suffix(".desktop").unwrap_or(id);
+        installed.iter().find(|desktop_entry| desktop_entry.appid == appid)
+    };
+
+    for mime_type in with_parent_types(&target.mime_type()) {
+        let defaults = associations(&lists, "Default Applications", &mime_type);
+        if let Some(handler) = defaults.iter().find_map(installed_entry) {
+            return Some(handler);
+        }
+
+        let removed = associations(&lists, "Removed Associations", &mime_type);
+        let added = associations(&lists, "Added Associations", &mime_type);
+        if let Some(handler) = added
+            .iter()
+            .filter(|id| !removed.contains(id))
+            .find_map(installed_entry)
+        {
+            return Some(handler);
+        }
+
+        if let Some(handler) = installed.iter().find(|desktop_entry| {
+            desktop_entry
+                .desktop_entry("MimeType")
+                .is_some_and(|types| types.split(';').any(|declared| declared == mime_type))
+                && !removed.iter().any(|id| id.strip_suffix(".desktop") == Some(&desktop_entry.appid))
+        }) {
+            return Some(handler);
+        }
+    }
+
+    None
+}
+
+/// The command which opens `target` with `handler`, filling in the file or URL field code, or
+/// adding the target at the end when the handler's command has none.
+pub fn open_command(handler: &DesktopEntry, target: &Target) -> Vec<String> {
+    // a lone `%` in the target would be taken for a field code
+    let argument = target.argument().replace('%', "%%");
+    let mut filled = false;
+    let mut command = Vec::new();
+
+    for arg in entry::split_exec(handler.exec().unwrap_or_default()) {
+        match arg.as_str() {
+            "%f" | "%F" | "%u" | "%U" if !filled => {
+                command.push(argument.clone());
+                filled = true;
+            }
+            code if code.len() == 2 && code.starts_with('%') && code != "%%" => {}
+            _ => command.push(arg),
+        }
+    }
+
+    if !filled {
+        command.push(argument);
+    }
+
+    command
+}
+
+/// Writes an autostart entry to `autostart_dir` which opens `target` with `handler`, returning
+/// the entry's path.
+pub fn write_entry(target: &Target, handler: &DesktopEntry, autostart_dir: &Path) -> Result<PathBuf, String> {
+    let label = target.label();
+    let file_name = format!(
+        "open-{}",
+        scripts::file_name_for(&label).ok_or_else(|| fl!("open-at-login", "empty"))?
+    );
+    let entry_path = autostart_dir.join(format!("{file_name}.desktop"));
+
+    let mut entry_text = format!(
+        "[Desktop Entry]\nType=Application\nName={}\nExec={}\n",
+        entry::escape_value(&fl!("open-at-login", "entry-name", target = label.replace('\n', " "))),
+        entry::escape_value(&entry::join_exec(&open_command(handler, target))),
+    );
+    if let Some(icon) = handler.icon() {
+        entry_text.push_str(&format!("Icon={}\n", entry::escape_value(icon)));
+    }
+    entry_text.push_str(&format!("{OPEN_KEY}={}\n", entry::escape_value(&target.argument())));
+
+    fs::create_dir_all(autostart_dir).map_err(|why| why.to_string())?;
+
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&entry_path)
+        .and_then(|mut file| file.write_all(entry_text.as_bytes()))
+        .map_err(|why| match why.kind() {
+            io::ErrorKind::AlreadyExists => fl!("open-at-login", "exists", name = file_name.clone()),
+            _ => why.to_string(),
+        })?;
+
+    Ok(entry_path)
+}
+
+/// Guesses a file's MIME type from its name using the shared MIME database, falling back on
+/// whether it looks like text.
+fn file_mime_type(path: &Path) -> String {
+    let Some(file_name) = path.file_name().map(|name| name.to_string_lossy().into_owned()) else {
+        return "application/octet-stream".to_string();
+    };
+    let lowercase = file_name.to_lowercase();
+
+    // the highest weight wins, then the longest pattern
+    let mut best: Option<(u32, usize, String)> = None;
+
+    for dir in data_dirs() {
+        let Ok(globs) = fs::read_to_string(host_path(&dir.join("mime/globs2"))) else {
+            continue;
+        };
+
+        for line in globs.lines().filter(|line| !line.starts_with('#')) {
+            let mut fields = line.split(':');
+            let (Some(weight), Some(mime_type), Some(pattern)) = (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            let case_sensitive = fields.any(|flag| flag.split(',').any(|flag| flag == "cs"));
+            let name = if case_sensitive { &file_name } else { &lowercase };
+
+            let matches = match pattern.strip_prefix('*') {
+                Some(suffix) if !suffix.contains(['*', '?', '[']) => name.ends_with(suffix),
+                _ => name == pattern,
+            };
+            let weight = weight.parse::<u32>().unwrap_or(50);
+
+            if matches
+                && best
+                    .as_ref()
+                    .is_none_or(|(best_weight, length, _)| (weight, pattern.len()) > (*best_weight, *length))
+            {
+                best = Some((weight, pattern.len(), mime_type.to_string()));
+            }
+        }
+    }
+
+    if let Some((_, _, mime_type)) = best {
+        return mime_type;
+    }
+
+    let mut head = Vec::new();
+    if let Ok(file) = fs::File::open(host_path(path)) {
+        _ = io::Read::read_to_end(&mut io::Read::take(file, 512), &mut head);
+    }
+
+    match !head.contains(&0) && std::str::from_utf8(&head).is_ok() {
+        true => "text/plain".to_string(),
+        false => "application/octet-stream".to_string(),
+    }
+}
+
+/// `mime_type` followed by the types it's a subclass of, nearest first.
+fn with_parent_types(mime_type: &str) -> Vec<String> {
+    let subclasses: Vec<(String, String)> = data_dirs()
+        .iter()
+        .filter_map(|dir| fs::read_to_string(host_path(&dir.join("mime/subclasses"))).ok())
+        .flat_map(|subclasses| {
+            subclasses
+                .lines()
+                .filter_map(|line| {
+                    let (child, parent) = line.split_once(' ')?;
+                    Some((child.to_string(), parent.trim().to_string()))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let mut types = vec![mime_type.to_string()];
+    let mut idx = 0;
+
+    while let Some(current) = types.get(idx).cloned() {
+        for (_, parent) in subclasses.iter().filter(|(child, _)| *child == current) {
+            if !types.contains(parent) {
+                types.push(parent.clone());
+            }
+        }
+        idx += 1;
+    }
+
+    // every text type can be shown as plain text, even when the database doesn't say so
+    if mime_type.starts_with("text/") && !types.iter().any(|known| known == "text/plain") {
+        types.push("text/plain".to_string());
+    }
+
+    types
+}
+
+/// The desktop entry ids listed for `mime_type` in `group`, in order of preference.
+fn associations(lists: &[PathBuf], group: &str, mime_type: &str) -> Vec<String> {
+    let header = format!("[{group}]");
+    let mut ids: Vec<String> = Vec::new();
+
+    for list in lists {
+        let Ok(contents) = fs::read_to_string(host_path(list)) else {
+            continue;
+        };
+
+        let mut in_group = false;
+        for line in contents.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_group = line == header;
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            if !in_group || key.trim() != mime_type {
+                continue;
+            }
+
+            for id in value.split(';').map(str::trim).filter(|id| !id.is_empty()) {
+                if !ids.iter().any(|known| known == id) {
+                    ids.push(id.to_string());
+                }
+            }
+        }
+    }
+
+    ids
+}
+
+/// The `mimeapps.list` files, highest priority first, including the desktop specific ones.
+fn mimeapps_lists() -> Vec<PathBuf> {
+    let desktops: Vec<String> = crate::desktops::current_desktops()
+        .iter()
+        .map(|desktop| desktop.to_lowercase())
+        .collect();
+
+    let dirs = config_dirs()
+        .into_iter()
+        .chain(data_dirs().into_iter().map(|dir| dir.join("applications")));
+
+    dirs.flat_map(|dir| {
+        desktops
+            .iter()
+            .map(|desktop| dir.join(format!("{desktop}-mimeapps.list")))
+            .chain([dir.join("mimeapps.list")])
+            .collect::<Vec<_>>()
+    })
+    .collect()
+}
+
+fn config_dirs() -> Vec<PathBuf> {
+    // inside the sandbox, the XDG variables describe the sandbox rather than the host
+    #[cfg(feature = "flatpak")]
+    return vec![
+        dirs::home_dir().expect("home dir not found").join(".config"),
+        PathBuf::from("/etc/xdg"),
+    ];
+
+    #[cfg(not(feature = "flatpak"))]
+    std::iter::once(dirs::config_dir().expect("config dir not found"))
+        .chain(search_path("XDG_CONFIG_DIRS", "/etc/xdg"))
+        .collect()
+}
+
+fn data_dirs() -> Vec<PathBuf> {
+    #[cfg(feature = "flatpak")]
+    return vec![
+        dirs::home_dir().expect("home dir not found").join(".local/share"),
+        PathBuf::from("/usr/local/share"),
+        PathBuf::from("/usr/share"),
+    ];
+
+    #[cfg(not(feature = "flatpak"))]
+    std::iter::once(
+        dirs::data_dir().unwrap_or_else(|| dirs::home_dir().unwrap_or_default().join(".local/share")),
+    )
+    .chain(search_path("XDG_DATA_DIRS", "/usr/local/share:/usr/share"))
+    .collect()
+}
+
+#[cfg(not(feature = "flatpak"))]
+fn search_path(variable: &str, default: &str) -> Vec<PathBuf> {
+    std::env::var(variable)
+        .ok()
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| default.to_string())
+        .split(':')
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+fn is_scheme(scheme: &str) -> bool {
+    // a single letter is more likely a Windows drive than a scheme
+    scheme.len() > 1
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        let hex = bytes
+            .get(idx + 1..idx + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[idx], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                idx += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                idx += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
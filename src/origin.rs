@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: GPL-3
+
+//! Works out where an entry comes from: a distribution package, a flatpak, a snap, an AppImage,
+//! a script the app manages, or a `.desktop` file someone put in their home directory.
+
+use crate::appimage::APPIMAGE_KEY;
+use crate::entry;
+use crate::scripts::SCRIPT_KEY;
+use crate::snap;
+use freedesktop_desktop_entry::DesktopEntry;
+use startup_configuration::launcher::LaunchOptions;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    /// Installed by the distribution's package manager
+    Distro,
+
+    /// A flatpak, with its application id
+    Flatpak(String),
+
+    /// A snap, with its name
+    Snap(String),
+
+    /// An AppImage, with the path of the image
+    AppImage(PathBuf),
+
+    /// A script the app wrote or copied
+    Script,
+
+    /// A `.desktop` file in the user's home directory
+    UserLocal,
+}
+
+/// Classifies an entry, checking what it starts before where its `.desktop` file lives, as
+/// copies of flatpak and snap entries end up in the user's autostart directory too. Only entries
+/// marked with the script key count as scripts, as a command pointing into a script directory
+/// says nothing about who put the script there.
+pub fn classify(desktop_entry: &DesktopEntry) -> Origin {
+    if desktop_entry.desktop_entry(SCRIPT_KEY).is_some() {
+        return Origin::Script;
+    }
+
+    let command = command(desktop_entry);
+
+    if let Some(image) = desktop_entry.desktop_entry(APPIMAGE_KEY) {
+        return Origin::AppImage(PathBuf::from(image));
+    }
+    if let Some(image) = command
+        .first()
+        .filter(|program| program.to_lowercase().ends_with(".appimage"))
+    {
+        return Origin::AppImage(PathBuf::from(image));
+    }
+
+    if let Some(appid) = flatpak_id(desktop_entry, &command) {
+        return Origin::Flatpak(appid);
+    }
+
+    if let Some(snap_name) = snap::snap_name(desktop_entry) {
+        return Origin::Snap(snap_name);
+    }
+
+    // entries added from installed applications are often symlinks to the packaged file
+    let path = fs::canonicalize(&desktop_entry.path).unwrap_or_else(|_| desktop_entry.path.clone());
+    let home = dirs::home_dir().unwrap_or_default();
+
+    match path.starts_with(&home) {
+        true => Origin::UserLocal,
+        false => Origin::Distro,
+    }
+}
+
+/// The flatpak an entry starts, from its `X-Flatpak` key, its path in an exports directory or a
+/// `flatpak run` command.
+fn flatpak_id(desktop_entry: &DesktopEntry, command: &[String]) -> Option<String> {
+    if let Some(appid) = desktop_entry.flatpak() {
+        return Some(appid.to_string());
+    }
+
+    if is_flatpak_export(&desktop_entry.path) {
+        return Some(desktop_entry.appid.clone());
+    }
+
+    match command {
+        [program, run, args @ ..] if program.rsplit('/').next() == Some("flatpak") && run == "run" => {
+            args.iter().find(|arg| !arg.starts_with('-')).cloned()
+        }
+        _ => None,
+    }
+}
+
+fn is_flatpak_export(path: &Path) -> bool {
+    let path = path.to_string_lossy();
+
+    path.contains("/flatpak/exports/") || path.contains("/flatpak/app/")
+}
+
+/// The entry's command without the launcher or an `env` prefix in front of it.
+fn command(desktop_entry: &DesktopEntry) -> Vec<String> {
+    let exec = entry::split_exec(desktop_entry.exec().unwrap_or_default());
+    let (_, command) = LaunchOptions::unwrap(&exec);
+    let (_, command) = entry::split_env(&command);
+
+    command
+}
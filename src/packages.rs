@@ -0,0 +1,365 @@
+// SPDX-License-Identifier: GPL-3
+
+//! Finds the distribution package which installed a file, from the package managers' local
+//! databases, so system entries can be traced back to what to remove or configure.
+//!
+//! dpkg and pacman keep plain file lists, which are read directly. rpm's database isn't meant to
+//! be read by anything else, so it's asked with `rpm -qf`, which runs on the host.
+//!
+//! All the database locations are paths on the host, read through `apps::host_path`. In the
+//! flatpak, databases under `/usr` are seen under `/run/host`, while those under `/var/lib` need
+//! their own `--filesystem` permission in the manifest.
+
+use crate::apps::{host_command, host_path};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    Dpkg,
+    Rpm,
+    Pacman,
+}
+
+impl PackageManager {
+    pub fn name(self) -> &'static str {
+        match self {
+            PackageManager::Dpkg => "dpkg",
+            PackageManager::Rpm => "rpm",
+            PackageManager::Pacman => "pacman",
+        }
+    }
+}
+
+/// An installed package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Package {
+    pub name: String,
+    pub manager: PackageManager,
+}
+
+/// Where each package manager keeps its database of installed files, on the host.
+#[derive(Debug, Clone)]
+pub struct PackageDbs {
+    /// dpkg's `info` directory, with a `<package>.list` of files for each package
+    pub dpkg_info: PathBuf,
+
+    /// pacman's `local` directory, with a `<name>-<version>/files` for each package
+    pub pacman_local: PathBuf,
+
+    /// rpm's database, queried with `rpm --dbpath`
+    pub rpm_db: Option<PathBuf>,
+}
+
+impl PackageDbs {
+    pub fn from_system() -> Self {
+        // newer rpm keeps its database under /usr, leaving a symlink at the old location, which
+        // is why the flatpak still needs /var/lib/rpm for older systems
+        let rpm_db = ["/usr/lib/sysimage/rpm", "/var/lib/rpm"]
+            .into_iter()
+            .map(PathBuf::from)
+            .find(|dir| host_path(dir).is_dir());
+
+        PackageDbs {
+            dpkg_info: PathBuf::from("/var/lib/dpkg/info"),
+            pacman_local: PathBuf::from("/var/lib/pacman/local"),
+            rpm_db,
+        }
+    }
+
+    /// Finds the package owning each of `paths`, which are paths on the host. Paths no package
+    /// owns are left out.
+    pub fn owners(&self, paths: &[PathBuf]) -> HashMap<PathBuf, Package> {
+        let mut owners = self.dpkg_owners(paths);
+
+        let remaining = |owners: &HashMap<PathBuf, Package>| -> Vec<PathBuf> {
+            paths.iter().filter(|path| !owners.contains_key(*path)).cloned().collect()
+        };
+
+        owners.extend(self.pacman_owners(&remaining(&owners)));
+
+        for path in remaining(&owners) {
+            if let Some(package) = self.rpm_owner(&path) {
+                owners.insert(path, package);
+            }
+        }
+
+        owners
+    }
+
+    fn dpkg_owners(&self, paths: &[PathBuf]) -> HashMap<PathBuf, Package> {
+        let mut owners = HashMap::new();
+        let Ok(lists) = fs::read_dir(host_path(&self.dpkg_info)) else {
+            return owners;
+        };
+
+        let wanted: HashSet<&Path> = paths.iter().map(PathBuf::as_path).collect();
+
+        for list in lists.flatten().map(|dir_entry| dir_entry.path()) {
+            if list.extension().is_none_or(|extension| extension != "list") {
+                continue;
+            }
+            let Ok(contents) = fs::read_to_string(&list) else {
+                continue;
+            };
+
+            // multi-arch packages are listed as `<package>:<arch>.list`
+            let stem = list.file_stem().unwrap_or_default().to_string_lossy();
+            let name = stem.split_once(':').map_or(&*stem, |(name, _)| name);
+
+            for line in contents.lines().map(Path::new).filter(|line| wanted.contains(line)) {
+                owners.insert(
+                    line.to_path_buf(),
+                    Package {
+                        name: name.to_string(),
+                        manager: PackageManager::Dpkg,
+                    },
+                );
+            }
+        }
+
+        owners
+    }
+
+    fn pacman_owners(&self, paths: &[PathBuf]) -> HashMap<PathBuf, Package> {
+        let mut owners = HashMap::new();
+        if paths.is_empty() {
+            return owners;
+        }
+        let Ok(packages) = fs::read_dir(host_path(&self.pacman_local)) else {
+            return owners;
+        };
+
+        // pacman lists files relative to the root
+        let wanted: HashMap<&Path, &PathBuf> = paths
+            .iter()
+            .filter_map(|path| Some((path.strip_prefix("/").ok()?, path)))
+            .collect();
+
+        for package in packages.flatten().map(|dir_entry| dir_entry.path()) {
+            let Ok(files) = fs::read_to_string(package.join("files")) else {
+                continue;
+            };
+
+            let owned: Vec<&PathBuf> = section(&files, "%FILES%")
+                .filter_map(|line| wanted.get(Path::new(line)).copied())
+                .collect();
+            if owned.is_empty() {
+                continue;
+            }
+
+            let name = fs::read_to_string(package.join("desc"))
+                .ok()
+                .and_then(|desc| section(&desc, "%NAME%").next().map(String::from))
+                .unwrap_or_else(|| pacman_name(&package.file_name().unwrap_or_default().to_string_lossy()));
+
+            for path in owned {
+                owners.insert(
+                    path.clone(),
+                    Package {
+                        name: name.clone(),
+                        manager: PackageManager::Pacman,
+                    },
+                );
+            }
+        }
+
+        owners
+    }
+
+    fn rpm_owner(&self, path: &Path) -> Option<Package> {
+        let rpm_db = self.rpm_db.as_ref()?;
+
+        let output = host_command("rpm")
+            .arg("--dbpath")
+            .arg(rpm_db)
+            .arg("--query")
+            .arg("--file")
+            .arg("--queryformat")
+            .arg("%{NAME}\\n")
+            .arg(path)
+            .output()
+            .ok()?;
+
+        if !output.status.success() {
+            return None;
+        }
+
+        // a file shared by several packages is listed once for each
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let name = stdout.lines().next()?.trim();
+
+        (!name.is_empty()).then(|| Package {
+            name: name.to_string(),
+            manager: PackageManager::Rpm,
+        })
+    }
+}
+
+/// The lines of a `%SECTION%` in a pacman database file, which runs until an empty line.
+fn section<'a>(contents: &'a str, header: &'a str) -> impl Iterator<Item = &'a str> {
+    contents
+        .lines()
+        .skip_while(move |line| *line != header)
+        .skip(1)
+        .take_while(|line| !line.is_empty())
+}
+
+/// The package name from a pacman database directory, which is `<name>-<version>-<release>`.
+fn pacman_name(dir_name: &str) -> String {
+    dir_name
+        .rsplitn(3, '-')
+        .nth(2)
+        .unwrap_or(dir_name)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fresh, empty database directories, without rpm.
+    fn fixture_dbs(name: &str) -> PackageDbs {
+        let root = std::env::temp_dir().join(format!("packages-{name}-{}", std::process::id()));
+        _ = fs::remove_dir_all(&root);
+
+        let dbs = PackageDbs {
+            dpkg_info: root.join("dpkg/info"),
+            pacman_local: root.join("pacman/local"),
+            rpm_db: None,
+        };
+        fs::create_dir_all(&dbs.dpkg_info).unwrap();
+        fs::create_dir_all(&dbs.pacman_local).unwrap();
+        dbs
+    }
+
+    fn remove(dbs: PackageDbs) {
+        fs::remove_dir_all(dbs.dpkg_info.parent().unwrap().parent().unwrap()).unwrap();
+    }
+
+    fn write(path: PathBuf, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn package(name: &str, manager: PackageManager) -> Package {
+        Package {
+            name: name.into(),
+            manager,
+        }
+    }
+
+    #[test]
+    fn dpkg_lists() {
+        let dbs = fixture_dbs("dpkg");
+        write(
+            dbs.dpkg_info.join("foo.list"),
+            "/.\n/etc\n/etc/xdg/autostart\n/etc/xdg/autostart/foo.desktop\n",
+        );
+        write(dbs.dpkg_info.join("bar:amd64.list"), "/usr/share/applications/bar.desktop\n");
+        // not a file list
+        write(dbs.dpkg_info.join("baz.md5sums"), "/etc/xdg/autostart/baz.desktop\n");
+
+        let owners = dbs.owners(&[
+            PathBuf::from("/etc/xdg/autostart/foo.desktop"),
+            PathBuf::from("/usr/share/applications/bar.desktop"),
+            PathBuf::from("/etc/xdg/autostart/baz.desktop"),
+        ]);
+
+        assert_eq!(owners.len(), 2);
+        assert_eq!(
+            owners[Path::new("/etc/xdg/autostart/foo.desktop")],
+            package("foo", PackageManager::Dpkg)
+        );
+        assert_eq!(
+            owners[Path::new("/usr/share/applications/bar.desktop")],
+            package("bar", PackageManager::Dpkg)
+        );
+
+        remove(dbs);
+    }
+
+    #[test]
+    fn pacman_databases() {
+        let dbs = fixture_dbs("pacman");
+        write(
+            dbs.pacman_local.join("network-manager-applet-1.36.0-1/files"),
+            "%FILES%\netc/\netc/xdg/autostart/nm-applet.desktop\n\n%BACKUP%\n",
+        );
+        write(
+            dbs.pacman_local.join("network-manager-applet-1.36.0-1/desc"),
+            "%NAME%\nnetwork-manager-applet\n\n%VERSION%\n1.36.0-1\n",
+        );
+        // without a `desc`, the name comes from the directory
+        write(
+            dbs.pacman_local.join("xdg-user-dirs-0.18-2/files"),
+            "%FILES%\netc/xdg/autostart/xdg-user-dirs.desktop\n",
+        );
+
+        let owners = dbs.owners(&[
+            PathBuf::from("/etc/xdg/autostart/nm-applet.desktop"),
+            PathBuf::from("/etc/xdg/autostart/xdg-user-dirs.desktop"),
+            PathBuf::from("/etc/xdg/autostart/unowned.desktop"),
+        ]);
+
+        assert_eq!(owners.len(), 2);
+        assert_eq!(
+            owners[Path::new("/etc/xdg/autostart/nm-applet.desktop")],
+            package("network-manager-applet", PackageManager::Pacman)
+        );
+        assert_eq!(
+            owners[Path::new("/etc/xdg/autostart/xdg-user-dirs.desktop")],
+            package("xdg-user-dirs", PackageManager::Pacman)
+        );
+
+        remove(dbs);
+    }
+
+    #[test]
+    fn dpkg_before_pacman() {
+        let dbs = fixture_dbs("both");
+        write(dbs.dpkg_info.join("foo.list"), "/etc/xdg/autostart/foo.desktop\n");
+        write(
+            dbs.pacman_local.join("foo-1.0-1/files"),
+            "%FILES%\netc/xdg/autostart/foo.desktop\n",
+        );
+
+        let owners = dbs.owners(&[PathBuf::from("/etc/xdg/autostart/foo.desktop")]);
+
+        assert_eq!(
+            owners[Path::new("/etc/xdg/autostart/foo.desktop")],
+            package("foo", PackageManager::Dpkg)
+        );
+
+        remove(dbs);
+    }
+
+    #[test]
+    fn without_rpm() {
+        let dbs = fixture_dbs("no-rpm");
+        let path = Path::new("/etc/xdg/autostart/foo.desktop");
+
+        assert_eq!(dbs.rpm_owner(path), None);
+        assert!(dbs.owners(&[path.to_path_buf()]).is_empty());
+
+        remove(dbs);
+    }
+
+    #[test]
+    fn pacman_names() {
+        assert_eq!(pacman_name("network-manager-applet-1.36.0-1"), "network-manager-applet");
+        assert_eq!(pacman_name("foo-1.0-1"), "foo");
+        assert_eq!(pacman_name("foo"), "foo");
+    }
+
+    #[test]
+    fn pacman_sections() {
+        let desc = "%NAME%\nfoo\n\n%DEPENDS%\nbar\nbaz\n\n%OPTDEPENDS%\n";
+
+        assert_eq!(section(desc, "%NAME%").collect::<Vec<_>>(), ["foo"]);
+        assert_eq!(section(desc, "%DEPENDS%").collect::<Vec<_>>(), ["bar", "baz"]);
+        assert_eq!(section(desc, "%OPTDEPENDS%").count(), 0);
+        assert_eq!(section(desc, "%PROVIDES%").count(), 0);
+    }
+}
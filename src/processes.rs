@@ -0,0 +1,309 @@
+// SPDX-License-Identifier: GPL-3
+
+//! Finds the running processes an autostart entry started, by scanning `/proc`, and starts or
+//! stops them on request.
+
+use crate::apps::host_command;
+use crate::entry;
+use freedesktop_desktop_entry::DesktopEntry;
+use startup_configuration::launcher::LaunchOptions;
+use startup_configuration::procfs;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Stdio;
+use std::thread;
+use std::time::Duration;
+
+/// The kernel truncates `comm` to this many bytes.
+const COMM_LEN: usize = 15;
+
+/// Programs which only run the actual application, so their name says nothing about it.
+const INTERPRETERS: &[&str] = &[
+    "sh", "bash", "dash", "zsh", "fish", "env", "python", "python3", "perl", "ruby", "node",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Process {
+    pub pid: u32,
+    pub comm: String,
+    pub args: Vec<String>,
+
+    /// When the process started, counted from boot
+    pub started: Duration,
+    pub uptime: Duration,
+
+    /// CPU time used so far
+    pub cpu: Duration,
+
+    /// Resident memory in KiB
+    pub rss: u64,
+}
+
+/// Lists the user's visible processes from `proc_root`, normally `/proc`.
+pub fn scan(proc_root: &Path) -> Vec<Process> {
+    let Some(system_uptime) = procfs::read_uptime(proc_root) else {
+        return Vec::new();
+    };
+
+    let Ok(dirs) = fs::read_dir(proc_root) else {
+        return Vec::new();
+    };
+
+    dirs.flatten()
+        .filter_map(|dir| {
+            let pid = dir.file_name().to_str()?.parse::<u32>().ok()?;
+            read_process(&dir.path(), pid, system_uptime)
+        })
+        .collect()
+}
+
+fn read_process(dir: &Path, pid: u32, system_uptime: Duration) -> Option<Process> {
+    let args: Vec<String> = fs::read(dir.join("cmdline"))
+        .ok()?
+        .split(|byte| *byte == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect();
+
+    // kernel threads have no command line
+    if args.is_empty() {
+        return None;
+    }
+
+    let stat = procfs::read_stat(dir)?;
+
+    Some(Process {
+        pid,
+        comm: stat.comm,
+        args,
+        started: stat.started,
+        uptime: system_uptime.saturating_sub(stat.started),
+        cpu: stat.cpu,
+        rss: procfs::read_status_kib(dir, "VmRSS").unwrap_or_default(),
+    })
+}
+
+/// Parses the output of `ps -eo pid=,etimes=,cputimes=,rss=,args=`, given how long ago the
+/// machine booted.
+///
+/// `ps` joins the arguments with spaces, so arguments containing spaces come back split.
+pub fn parse_ps(output: &str, system_uptime: Duration) -> Vec<Process> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid = fields.next()?.parse::<u32>().ok()?;
+            let uptime = Duration::from_secs(fields.next()?.parse::<u64>().ok()?);
+            let cpu = Duration::from_secs(fields.next()?.parse::<u64>().ok()?);
+            let rss = fields.next()?.parse::<u64>().ok()?;
+            let args: Vec<String> = fields.map(String::from).collect();
+
+            // kernel threads are shown as [name]
+            if args.first()?.starts_with('[') {
+                return None;
+            }
+
+            Some(Process {
+                pid,
+                comm: basename(&args[0]).chars().take(COMM_LEN).collect(),
+                args,
+                started: system_uptime.saturating_sub(uptime),
+                uptime,
+                cpu,
+                rss,
+            })
+        })
+        .collect()
+}
+
+/// Lists the processes running in the user's session.
+pub fn running_processes() -> Vec<Process> {
+    if !cfg!(feature = "flatpak") {
+        return scan(Path::new("/proc"));
+    }
+
+    // the sandbox has its own /proc, so ask the host instead
+    host_command("ps")
+        .args(["-eo", "pid=,etimes=,cputimes=,rss=,args="])
+        .output()
+        .map(|output| {
+            // the sandbox shares the host's kernel, and so its uptime
+            let system_uptime = procfs::read_uptime(Path::new("/proc")).unwrap_or_default();
+            parse_ps(&String::from_utf8_lossy(&output.stdout), system_uptime)
+        })
+        .unwrap_or_default()
+}
+
+/// Finds the longest running process which looks like it was started by `entry`.
+///
+/// Processes are matched on the name of the program in `Exec` (or the script an interpreter
+/// runs) and on `StartupWMClass`, against both the process's command name and its arguments.
+pub fn find_process<'a>(entry: &DesktopEntry, processes: &'a [Process]) -> Option<&'a Process> {
+    let names = entry_names(entry);
+    if names.is_empty() {
+        return None;
+    }
+
+    processes
+        .iter()
+        .filter(|process| process_matches(process, &names))
+        .max_by_key(|process| process.uptime)
+}
+
+fn entry_names(entry: &DesktopEntry) -> Vec<String> {
+    let mut names = Vec::new();
+
+    let (_, command) = LaunchOptions::unwrap(&entry::split_exec(entry.exec().unwrap_or_default()));
+    let mut args = command.iter().map(String::as_str);
+
+    // skip past `env VAR=value` and interpreters to the program doing the work
+    while let Some(arg) = args.next() {
+        if arg.contains('=') && !arg.starts_with('-') {
+            continue;
+        }
+
+        let name = basename(arg);
+        if name == "-c" {
+            // `sh -c "program args"`
+            if let Some(script) = args.next() {
+                if let Some(program) = script.split_whitespace().next() {
+                    names.push(basename(program).to_string());
+                }
+            }
+            break;
+        }
+
+        if arg.starts_with('-') || INTERPRETERS.contains(&name) {
+            continue;
+        }
+
+        names.push(name.to_string());
+        break;
+    }
+
+    if let Some(wm_class) = entry.desktop_entry("StartupWMClass") {
+        names.push(wm_class.to_string());
+    }
+
+    names.retain(|name| !name.is_empty());
+    names
+}
+
+fn process_matches(process: &Process, names: &[String]) -> bool {
+    names.iter().any(|name| {
+        let truncated: String = name.chars().take(COMM_LEN).collect();
+
+        process.comm.eq_ignore_ascii_case(&truncated)
+            || process
+                .args
+                .iter()
+                .take(2)
+                .any(|arg| basename(arg).eq_ignore_ascii_case(name))
+    })
+}
+
+fn basename(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Formats how long something ran for, such as `2h 05m` or `42s`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, secs) => format!("{secs}s"),
+        (0, mins, secs) => format!("{mins}m {secs:02}s"),
+        (hours, mins, _) => format!("{hours}h {mins:02}m"),
+    }
+}
+
+/// Starts an entry's command in the background, the way the session would at login.
+pub fn start(entry: &DesktopEntry) -> io::Result<()> {
+    let args = entry::strip_field_codes(&entry::split_exec(entry.exec().unwrap_or_default()));
+    let Some((program, args)) = args.split_first() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "the entry has no command"));
+    };
+
+    let mut child = host_command(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    // reap the child once it exits, so it doesn't linger as a zombie
+    thread::spawn(move || child.wait());
+
+    Ok(())
+}
+
+/// Asks a process to terminate.
+pub fn stop(pid: u32) -> io::Result<()> {
+    let status = host_command("kill").arg(pid.to_string()).status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("kill exited with {status}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOOTED: Duration = Duration::from_secs(10_000);
+
+    #[test]
+    fn ps_output() {
+        let output = "\
+      1    9990     3  12800 /sbin/init splash
+      2    9990     0      0 [kthreadd]
+   2345     600    42 104512 /usr/lib/evolution/evolution-alarm-notify
+   2400      75     1   8300 sh -c sleep 5; notify-send hi
+";
+        let processes = parse_ps(output, BOOTED);
+
+        assert_eq!(processes.len(), 3);
+        assert_eq!(
+            processes[1],
+            Process {
+                pid: 2345,
+                comm: "evolution-alarm".into(),
+                args: vec!["/usr/lib/evolution/evolution-alarm-notify".into()],
+                started: Duration::from_secs(9_400),
+                uptime: Duration::from_secs(600),
+                cpu: Duration::from_secs(42),
+                rss: 104512,
+            }
+        );
+        // arguments come back split on spaces
+        assert_eq!(processes[2].args, ["sh", "-c", "sleep", "5;", "notify-send", "hi"]);
+        assert_eq!(processes[2].comm, "sh");
+    }
+
+    #[test]
+    fn ps_output_skips_bad_lines() {
+        let output = "\
+  PID ELAPSED TIME RSS COMMAND
+   12     abc     0    0 /usr/bin/foo
+   13      10     0
+   14      10     0    0
+   15   20000     0    0 /usr/bin/bar
+";
+        let processes = parse_ps(output, BOOTED);
+
+        // a process can't have started before boot
+        assert_eq!(processes.len(), 1);
+        assert_eq!(processes[0].pid, 15);
+        assert_eq!(processes[0].started, Duration::ZERO);
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(format_duration(Duration::from_secs(65)), "1m 05s");
+        assert_eq!(format_duration(Duration::from_secs(2 * 3600 + 5 * 60 + 59)), "2h 05m");
+    }
+}
@@ -0,0 +1,70 @@
+// SPDX-License-Identifier: GPL-3
+
+//! Reading process information from `/proc`.
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// `/proc/<pid>/stat` reports times in clock ticks, which Linux always exposes at 100 per second.
+const CLOCK_TICKS: u64 = 100;
+
+/// The parts of `/proc/<pid>/stat` we care about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stat {
+    pub comm: String,
+
+    /// When the process started, counted from boot
+    pub started: Duration,
+
+    /// User and system time used by the process and the children it has waited for
+    pub cpu: Duration,
+}
+
+/// Parses `/proc/<pid>/stat` in the process directory `dir`.
+pub fn read_stat(dir: &Path) -> Option<Stat> {
+    let stat = fs::read_to_string(dir.join("stat")).ok()?;
+
+    // the command name is in parentheses and may itself contain spaces or parentheses
+    let comm = stat.get(stat.find('(')? + 1..stat.rfind(')')?)?.to_string();
+
+    // fields after the command name start at the third one
+    let fields: Vec<u64> = stat[stat.rfind(')')? + 1..]
+        .split_whitespace()
+        .map(|field| field.parse::<u64>().unwrap_or_default())
+        .collect();
+    let field = |number: usize| fields.get(number - 3).copied();
+
+    let cpu_ticks = field(14)? + field(15)? + field(16)? + field(17)?;
+
+    Some(Stat {
+        comm,
+        started: ticks(field(22)?),
+        cpu: ticks(cpu_ticks),
+    })
+}
+
+/// Reads a `kB` value such as `VmRSS` from `/proc/<pid>/status`.
+pub fn read_status_kib(dir: &Path, key: &str) -> Option<u64> {
+    let status = fs::read_to_string(dir.join("status")).ok()?;
+
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))?
+        .split_whitespace()
+        .next()?
+        .parse::<u64>()
+        .ok()
+}
+
+/// Reads how long ago the machine booted from `/proc/uptime`.
+pub fn read_uptime(proc_root: &Path) -> Option<Duration> {
+    let uptime = fs::read_to_string(proc_root.join("uptime")).ok()?;
+    let secs = uptime.split_whitespace().next()?.parse::<f64>().ok()?;
+
+    Some(Duration::from_secs_f64(secs.max(0.0)))
+}
+
+fn ticks(ticks: u64) -> Duration {
+    Duration::from_millis(ticks * 1000 / CLOCK_TICKS)
+}
@@ -0,0 +1,250 @@
+// SPDX-License-Identifier: GPL-3
+
+//! Test-launches an autostart entry, capturing what it prints, so it can be checked without
+//! logging out and back in.
+
+use crate::apps::{find_executable, host_command_in, host_path};
+use crate::entry;
+use crate::fl;
+use crate::scripts;
+use cosmic::iced::futures::channel::oneshot;
+use freedesktop_desktop_entry::DesktopEntry;
+use std::fs;
+use std::io::Read;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
+use std::process::{Child, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to wait for a test-launched command to exit before leaving it running.
+pub const RUN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How much of each output stream is kept.
+const MAX_OUTPUT: usize = 64 * 1024;
+
+/// Exit codes shells (and `flatpak-spawn`) use when a command can't be run.
+const EXIT_NOT_EXECUTABLE: i32 = 126;
+const EXIT_NOT_FOUND: i32 = 127;
+
+/// Why a command couldn't be started at all.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LaunchFailure {
+    NotFound(String),
+
+    /// The file exists but isn't executable
+    PermissionDenied(String),
+
+    /// A script's `#!` line names an interpreter which isn't installed
+    MissingInterpreter { script: String, interpreter: String },
+
+    NoCommand,
+
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunStatus {
+    Exited(i32),
+    Signalled(i32),
+
+    /// Still running when the timeout passed, and left running in the background
+    StillRunning,
+
+    FailedToStart(LaunchFailure),
+}
+
+/// The outcome of test-launching an entry.
+#[derive(Debug, Clone)]
+pub struct RunOutput {
+    pub command: Vec<String>,
+    pub status: RunStatus,
+    pub stdout: String,
+    pub stderr: String,
+    pub duration: Duration,
+}
+
+impl LaunchFailure {
+    pub fn describe(&self) -> String {
+        match self {
+            LaunchFailure::NotFound(program) => {
+                fl!("run-now", "not-found", program = program.clone())
+            }
+            LaunchFailure::PermissionDenied(program) => {
+                fl!("run-now", "permission-denied", program = program.clone())
+            }
+            LaunchFailure::MissingInterpreter { script, interpreter } => fl!(
+                "run-now",
+                "missing-interpreter",
+                script = script.clone(),
+                interpreter = interpreter.clone()
+            ),
+            LaunchFailure::NoCommand => fl!("run-now", "no-command"),
+            LaunchFailure::Other(why) => fl!("run-now", "failed", reason = why.clone()),
+        }
+    }
+}
+
+impl RunStatus {
+    pub fn describe(&self) -> String {
+        match self {
+            RunStatus::Exited(code) => fl!("run-now", "exited", code = *code),
+            RunStatus::Signalled(signal) => fl!("run-now", "signalled", signal = *signal),
+            RunStatus::StillRunning => fl!("run-now", "still-running"),
+            RunStatus::FailedToStart(failure) => failure.describe(),
+        }
+    }
+}
+
+/// Runs an entry's command with the session's environment, waiting up to `timeout` for it to
+/// exit. Applications which keep running are left running once the timeout passes.
+pub fn run(entry: &DesktopEntry, timeout: Duration) -> RunOutput {
+    let command = entry::strip_field_codes(&entry::split_exec(entry.exec().unwrap_or_default()));
+    let started = Instant::now();
+
+    let failed = |failure| RunOutput {
+        command: command.clone(),
+        status: RunStatus::FailedToStart(failure),
+        stdout: String::new(),
+        stderr: String::new(),
+        duration: started.elapsed(),
+    };
+
+    let Some((program, args)) = command.split_first() else {
+        return failed(LaunchFailure::NoCommand);
+    };
+
+    let dir = entry.desktop_entry("Path").filter(|dir| !dir.is_empty());
+    let mut process = host_command_in(program, dir.map(Path::new));
+    process
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    // with an `env VAR=value` prefix, it's the program after it which may be missing
+    let (_, target) = entry::split_env(&command);
+    let target = target.first().unwrap_or(program);
+
+    let mut child = match process.spawn() {
+        Ok(child) => child,
+        Err(why) => {
+            return failed(diagnose(target).unwrap_or_else(|| LaunchFailure::Other(why.to_string())))
+        }
+    };
+
+    let stdout = child.stdout.take().map(read_capped);
+    let stderr = child.stderr.take().map(read_capped);
+
+    let status = wait_timeout(child, timeout);
+
+    let collect = |reader: Option<thread::JoinHandle<String>>| match &status {
+        // whatever is still being printed by a running application is discarded
+        RunStatus::StillRunning => String::new(),
+        _ => reader
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default(),
+    };
+    let (stdout, stderr) = (collect(stdout), collect(stderr));
+
+    // the shell conventions for a command which couldn't be run, as reported from the host
+    let status = match status {
+        RunStatus::Exited(EXIT_NOT_EXECUTABLE | EXIT_NOT_FOUND) => match diagnose(target) {
+            Some(failure) => RunStatus::FailedToStart(failure),
+            None => status,
+        },
+        status => status,
+    };
+
+    RunOutput {
+        command,
+        status,
+        stdout,
+        stderr,
+        duration: started.elapsed(),
+    }
+}
+
+/// [`run`] on a thread of its own, so that waiting for the command doesn't hold up the caller's
+/// executor.
+pub async fn run_in_background(entry: DesktopEntry, timeout: Duration) -> RunOutput {
+    let command = entry::strip_field_codes(&entry::split_exec(entry.exec().unwrap_or_default()));
+    let (sender, receiver) = oneshot::channel();
+
+    thread::spawn(move || sender.send(run(&entry, timeout)));
+
+    receiver.await.unwrap_or_else(|why| RunOutput {
+        command,
+        status: RunStatus::FailedToStart(LaunchFailure::Other(why.to_string())),
+        stdout: String::new(),
+        stderr: String::new(),
+        duration: Duration::ZERO,
+    })
+}
+
+fn wait_timeout(mut child: Child, timeout: Duration) -> RunStatus {
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                return match (status.code(), status.signal()) {
+                    (Some(code), _) => RunStatus::Exited(code),
+                    (None, Some(signal)) => RunStatus::Signalled(signal),
+                    (None, None) => RunStatus::Exited(-1),
+                };
+            }
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(50)),
+            Ok(None) => break,
+            Err(why) => return RunStatus::FailedToStart(LaunchFailure::Other(why.to_string())),
+        }
+    }
+
+    // reap the application in the background once it exits
+    thread::spawn(move || child.wait());
+
+    RunStatus::StillRunning
+}
+
+/// Reads a stream to the end on another thread, keeping the first [`MAX_OUTPUT`] bytes, so that
+/// a chatty command never blocks on a full pipe.
+fn read_capped(mut stream: impl Read + Send + 'static) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut kept = Vec::new();
+        let mut buffer = [0; 8192];
+
+        while let Ok(read) = stream.read(&mut buffer) {
+            if read == 0 {
+                break;
+            }
+
+            let room = MAX_OUTPUT.saturating_sub(kept.len());
+            kept.extend_from_slice(&buffer[..read.min(room)]);
+        }
+
+        String::from_utf8_lossy(&kept).into_owned()
+    })
+}
+
+/// Works out why `program` can't be run, if it can't.
+pub fn diagnose(program: &str) -> Option<LaunchFailure> {
+    let Some(path) = find_executable(program).or_else(|| {
+        let path = host_path(Path::new(program));
+        (program.contains('/') && path.exists()).then_some(path)
+    }) else {
+        return Some(LaunchFailure::NotFound(program.to_string()));
+    };
+
+    let metadata = fs::metadata(&path).ok()?;
+    if metadata.is_dir() || metadata.permissions().mode() & 0o111 == 0 {
+        return Some(LaunchFailure::PermissionDenied(program.to_string()));
+    }
+
+    let interpreter = scripts::read_shebang(&path)?.missing_interpreter()?;
+
+    Some(LaunchFailure::MissingInterpreter {
+        script: program.to_string(),
+        interpreter,
+    })
+}
@@ -0,0 +1,567 @@
+// SPDX-License-Identifier: GPL-3
+
+//! Startup scripts owned by the app: those written in it, kept under
+//! `$XDG_DATA_HOME/startup-configuration/scripts`, and those the flatpak copied into the autostart
+//! directory. Each is started through an autostart entry which names it in [`SCRIPT_KEY`].
+
+use crate::apps::{find_executable, host_command, host_path, DirectoryType};
+use crate::entry;
+use crate::fl;
+use freedesktop_desktop_entry::DesktopEntry;
+use startup_configuration::launcher::LaunchOptions;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+/// Marks autostart entries which run a script the app owns, naming the script's path.
+pub const SCRIPT_KEY: &str = "X-StartupConfiguration-Script";
+
+/// Interpreters a script can explicitly be run with.
+pub const INTERPRETERS: &[&str] = &[
+    "sh", "bash", "zsh", "fish", "python3", "node", "perl", "ruby", "lua", "php",
+];
+
+/// The `#!` line of a script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shebang {
+    pub interpreter: String,
+    pub args: Vec<String>,
+}
+
+/// Why a chosen file can't simply be run as it is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptProblem {
+    /// The file has no execute permission
+    NotExecutable,
+
+    /// A text file without a `#!` line, which only a shell would guess how to run
+    NoShebang,
+
+    /// The `#!` line names an interpreter which isn't installed
+    MissingInterpreter(String),
+}
+
+/// A chosen script waiting for the user to decide how it's added.
+#[derive(Debug, Clone)]
+pub struct ScriptImport {
+    pub directory_type: DirectoryType,
+
+    /// The file as seen by the app
+    pub path: PathBuf,
+
+    /// Where the host can run the file in place, if it can
+    pub host_path: Option<PathBuf>,
+
+    pub problem: Option<ScriptProblem>,
+    pub is_binary: bool,
+
+    /// Index in the available interpreters to run the script with, if any
+    pub interpreter: Option<usize>,
+}
+
+impl Shebang {
+    /// The program which ends up running the script, looking past `/usr/bin/env [-S]`.
+    pub fn program(&self) -> Option<&str> {
+        match self.interpreter.rsplit('/').next() {
+            Some("env") => self
+                .args
+                .iter()
+                .map(String::as_str)
+                .find(|arg| !arg.starts_with('-') && !arg.contains('=')),
+            _ => Some(&self.interpreter),
+        }
+    }
+
+    /// The interpreter if it isn't installed, as it should be reported.
+    pub fn missing_interpreter(&self) -> Option<String> {
+        match self.interpreter.rsplit('/').next() {
+            // `#!/usr/bin/env python3` looks the interpreter up in $PATH
+            Some("env") => {
+                let program = self.program()?;
+                find_executable(program).is_none().then(|| program.to_string())
+            }
+            _ => (!host_path(Path::new(&self.interpreter)).exists()).then(|| self.interpreter.clone()),
+        }
+    }
+}
+
+/// Reads the `#!` line at the start of the file at `path`.
+pub fn read_shebang(path: &Path) -> Option<Shebang> {
+    let head = read_head(path, 256);
+
+    let line = head.strip_prefix(b"#!")?;
+    let line = line.split(|byte| *byte == b'\n').next()?;
+    let mut words = String::from_utf8_lossy(line)
+        .split_whitespace()
+        .map(String::from)
+        .collect::<Vec<String>>()
+        .into_iter();
+
+    Some(Shebang {
+        interpreter: words.next()?,
+        args: words.collect(),
+    })
+}
+
+/// Works out whether a chosen file can be run as it is.
+pub fn check_script(path: &Path) -> Option<ScriptProblem> {
+    let executable = fs::metadata(path).is_ok_and(|metadata| metadata.permissions().mode() & 0o111 != 0);
+
+    if let Some(shebang) = read_shebang(path) {
+        if let Some(interpreter) = shebang.missing_interpreter() {
+            return Some(ScriptProblem::MissingInterpreter(interpreter));
+        }
+    } else if !is_binary(path) {
+        return Some(ScriptProblem::NoShebang);
+    }
+
+    (!executable).then_some(ScriptProblem::NotExecutable)
+}
+
+/// Whether the file at `path` is a compiled program rather than a script.
+pub fn is_binary(path: &Path) -> bool {
+    let head = read_head(path, 512);
+
+    head.starts_with(b"\x7fELF") || head.contains(&0)
+}
+
+/// The interpreter a script most likely needs, from its `#!` line or its extension.
+pub fn guess_interpreter(path: &Path) -> &'static str {
+    if let Some(program) = read_shebang(path).as_ref().and_then(Shebang::program) {
+        let name = program.rsplit('/').next().unwrap_or(program);
+        if let Some(known) = INTERPRETERS
+            .iter()
+            .find(|known| name == **known || (**known == "python3" && name.starts_with("python")))
+        {
+            return known;
+        }
+    }
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("bash") => "bash",
+        Some("zsh") => "zsh",
+        Some("fish") => "fish",
+        Some("py") => "python3",
+        Some("js" | "mjs" | "cjs") => "node",
+        Some("pl") => "perl",
+        Some("rb") => "ruby",
+        Some("lua") => "lua",
+        Some("php") => "php",
+        _ => "sh",
+    }
+}
+
+/// The [`INTERPRETERS`] installed on the host.
+pub fn available_interpreters() -> Vec<String> {
+    INTERPRETERS
+        .iter()
+        .filter(|interpreter| find_executable(interpreter).is_some())
+        .map(|interpreter| interpreter.to_string())
+        .collect()
+}
+
+/// What a new script starts out as.
+pub const TEMPLATE: &str = "#!/bin/sh\n# Runs every time you log in.\n\n";
+
+/// Where scripts written in the app are saved.
+///
+/// Inside the sandbox this is under `~/.var/app`, which is the same path on the host, so the
+/// entry can still run the script directly.
+pub fn scripts_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| dirs::home_dir().unwrap_or_default().join(".local/share"))
+        .join("startup-configuration/scripts")
+}
+
+/// The user's autostart directory, which scripts copied from elsewhere are kept in.
+pub fn user_script_dir() -> PathBuf {
+    // inside the sandbox, the host's autostart directory is also mounted at its own path
+    #[cfg(feature = "flatpak")]
+    let config_dir = dirs::home_dir().expect("home dir not found").join(".config");
+
+    #[cfg(not(feature = "flatpak"))]
+    let config_dir = dirs::config_dir().expect("config dir not found");
+
+    config_dir.join("autostart")
+}
+
+/// Directories the app keeps scripts in, as seen by the host: [`scripts_dir`] and
+/// [`user_script_dir`].
+pub fn script_dirs() -> Vec<PathBuf> {
+    vec![scripts_dir(), user_script_dir()]
+}
+
+/// Directories which may hold scripts no entry runs any more. Only the flatpak ever copied
+/// scripts into the autostart directory without marking them, so elsewhere anything executable
+/// there is left to its owner.
+pub fn orphan_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![scripts_dir()];
+    if cfg!(feature = "flatpak") {
+        dirs.push(user_script_dir());
+    }
+    dirs
+}
+
+/// Turns a script's display name into a file name, such as `Sync notes` into `sync-notes`.
+pub fn file_name_for(name: &str) -> Option<String> {
+    let mut file_name = String::new();
+
+    for c in name.trim().chars() {
+        if c.is_alphanumeric() {
+            file_name.extend(c.to_lowercase());
+        } else if !file_name.is_empty() && !file_name.ends_with('-') {
+            file_name.push('-');
+        }
+    }
+
+    let file_name = file_name.trim_end_matches('-');
+    (!file_name.is_empty()).then(|| file_name.to_string())
+}
+
+/// Saves a new executable script to `scripts_dir`, and an autostart entry running it to
+/// `autostart_dir`. Returns the entry's path.
+///
+/// Neither file is overwritten if it already exists, and the script is removed again if the
+/// entry can't be written.
+pub fn create(
+    name: &str,
+    contents: &str,
+    scripts_dir: &Path,
+    autostart_dir: &Path,
+) -> Result<PathBuf, String> {
+    let file_name = file_name_for(name).ok_or_else(|| fl!("new-script", "no-name"))?;
+
+    if !contents.starts_with("#!") {
+        return Err(fl!("new-script", "no-shebang"));
+    }
+
+    let script_path = scripts_dir.join(&file_name);
+
+    if autostart_dir.join(format!("{file_name}.desktop")).exists() {
+        return Err(fl!("new-script", "exists", name = file_name.clone()));
+    }
+
+    fs::create_dir_all(scripts_dir).map_err(|why| why.to_string())?;
+    write_new(&script_path, contents, 0o755).map_err(|why| exists_error(why, &file_name))?;
+
+    let written = write_entry(
+        name.trim(),
+        &[script_path.display().to_string()],
+        Some(&script_path),
+        autostart_dir,
+    );
+
+    if written.is_err() {
+        _ = fs::remove_file(&script_path);
+    }
+
+    written
+}
+
+/// Writes an autostart entry which runs `command`, named after the file the command runs.
+/// `owned_script` marks a script which belongs to the entry, and is deleted along with it.
+pub fn write_entry(
+    name: &str,
+    command: &[String],
+    owned_script: Option<&Path>,
+    autostart_dir: &Path,
+) -> Result<PathBuf, String> {
+    let file_name = command
+        .last()
+        .and_then(|script| Path::new(script).file_name())
+        .map(|file_name| file_name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| name.to_string());
+    let entry_path = autostart_dir.join(format!("{file_name}.desktop"));
+
+    // scripts go through the launcher so that their launches are recorded
+    let exec = LaunchOptions {
+        record: Some(file_name.clone()),
+        ..LaunchOptions::default()
+    }
+    .wrap(command);
+
+    let mut entry_text = format!(
+        "[Desktop Entry]\nType=Application\nName={}\nExec={}\n",
+        entry::escape_value(name),
+        entry::escape_value(&entry::join_exec(&exec))
+    );
+    if let Some(script) = owned_script {
+        entry_text.push_str(&format!(
+            "{SCRIPT_KEY}={}\n",
+            entry::escape_value(&script.display().to_string())
+        ));
+    }
+
+    fs::create_dir_all(autostart_dir).map_err(|why| why.to_string())?;
+    write_new(&entry_path, &entry_text, 0o644).map_err(|why| exists_error(why, &file_name))?;
+
+    Ok(entry_path)
+}
+
+/// The script an entry runs which the app owns, if it has one.
+///
+/// Entries written by earlier versions aren't marked with [`SCRIPT_KEY`], so for those only the
+/// exact command the flatpak used to write counts.
+pub fn owned_script(entry: &DesktopEntry, script_dirs: &[PathBuf]) -> Option<PathBuf> {
+    let path = match entry.desktop_entry(SCRIPT_KEY) {
+        Some(path) => PathBuf::from(path),
+        None => legacy_script(entry)?,
+    };
+
+    // only ever touch files in the app's own directories
+    let owned = path
+        .parent()
+        .is_some_and(|dir| script_dirs.iter().any(|script_dir| script_dir == dir))
+        && !path.extension().is_some_and(|extension| extension == "desktop")
+        && path.is_file();
+
+    owned.then_some(path)
+}
+
+/// The script copied by versions before [`SCRIPT_KEY`], which wrote `<file>.desktop` running
+/// `sh -c '~/.config/autostart/<file>'`.
+fn legacy_script(entry: &DesktopEntry) -> Option<PathBuf> {
+    let (_, command) = LaunchOptions::unwrap(&entry::split_exec(entry.exec()?));
+
+    let [shell, flag, script] = command.as_slice() else {
+        return None;
+    };
+    let file_name = script
+        .strip_prefix("'~/.config/autostart/")?
+        .strip_suffix('\'')?;
+
+    (shell == "sh" && flag == "-c" && !file_name.contains('/') && entry.appid == file_name)
+        .then(|| dirs::home_dir().unwrap_or_default().join(".config/autostart").join(file_name))
+}
+
+/// Paths named anywhere in an entry's command, including inside `sh -c` scripts.
+fn command_paths(entry: &DesktopEntry) -> Vec<PathBuf> {
+    let (_, command) = LaunchOptions::unwrap(&entry::split_exec(entry.exec().unwrap_or_default()));
+    let home = dirs::home_dir().unwrap_or_default();
+
+    command
+        .iter()
+        .flat_map(|arg| arg.split_whitespace())
+        .filter_map(|word| match word.strip_prefix("~/") {
+            Some(relative) => Some(home.join(relative)),
+            None => word.starts_with('/').then(|| PathBuf::from(word)),
+        })
+        .collect()
+}
+
+/// Finds scripts in `orphan_dirs` which no entry runs any more, such as those left behind when
+/// earlier versions removed an entry.
+pub fn find_orphans(entries: &[DesktopEntry], orphan_dirs: &[PathBuf]) -> Vec<PathBuf> {
+    // scripts which entries only refer to belong to someone, even if not to the app
+    let script_dirs = script_dirs();
+    let owned: Vec<PathBuf> = entries
+        .iter()
+        .flat_map(|entry| owned_script(entry, &script_dirs).into_iter().chain(command_paths(entry)))
+        .collect();
+
+    let mut orphans = Vec::new();
+
+    for dir in orphan_dirs {
+        let Ok(read_dir) = fs::read_dir(dir) else {
+            continue;
+        };
+
+        for file in read_dir.flatten() {
+            let path = file.path();
+            let Ok(metadata) = file.metadata() else {
+                continue;
+            };
+
+            // the autostart directory holds entries too, and only executables are scripts
+            if !metadata.is_file()
+                || metadata.permissions().mode() & 0o111 == 0
+                || path.extension().is_some_and(|extension| extension == "desktop")
+                || file.file_name().to_string_lossy().starts_with('.')
+                || owned.contains(&path)
+            {
+                continue;
+            }
+
+            orphans.push(path);
+        }
+    }
+
+    orphans.sort();
+    orphans
+}
+
+/// How a script chosen in the file chooser is added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Copied into the autostart directory, and owned by the app from then on
+    Copy,
+
+    /// Run from where it is on the host
+    Reference,
+}
+
+/// Where a file picked in the file chooser lives on the host, if the host can see it at all.
+pub fn host_location(path: &Path) -> Option<PathBuf> {
+    #[cfg(feature = "flatpak")]
+    {
+        // /usr and /etc of the host
+        if let Ok(relative) = path.strip_prefix("/run/host") {
+            return Some(Path::new("/").join(relative));
+        }
+
+        // files shared through the document portal, and the sandbox's own runtime
+        if path.starts_with("/run/user") || path.starts_with("/app") || path.starts_with("/usr") {
+            return None;
+        }
+    }
+
+    Some(path.to_path_buf())
+}
+
+/// Whether the host can execute the file at `host_path`.
+pub fn host_executable(host_path: &Path) -> bool {
+    host_command("test")
+        .arg("-x")
+        .arg(host_path)
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Copies a chosen file into `autostart_dir` byte for byte, keeping its permissions, and adds
+/// an entry which runs the copy. `host_autostart_dir` is the same directory as seen by the host.
+///
+/// With an `interpreter`, the entry runs the copy through it instead of directly.
+pub fn import_copy(
+    source: &Path,
+    autostart_dir: &Path,
+    host_autostart_dir: &Path,
+    interpreter: Option<&str>,
+) -> Result<PathBuf, String> {
+    let file_name = source
+        .file_name()
+        .ok_or_else(|| fl!("new-script", "no-name"))?
+        .to_string_lossy()
+        .into_owned();
+    let target = autostart_dir.join(&file_name);
+
+    if target.exists() {
+        return Err(fl!("new-script", "exists", name = file_name));
+    }
+
+    fs::create_dir_all(autostart_dir)
+        .and_then(|_| fs::copy(source, &target))
+        .map_err(|why| why.to_string())?;
+
+    let mut permissions = fs::metadata(&target).map_err(|why| why.to_string())?.permissions();
+    if permissions.mode() & 0o111 == 0 {
+        permissions.set_mode(permissions.mode() | 0o111);
+        fs::set_permissions(&target, permissions).map_err(|why| why.to_string())?;
+    }
+
+    let host_target = host_autostart_dir.join(&file_name);
+    let command = match interpreter {
+        Some(interpreter) => vec![interpreter.to_string(), host_target.display().to_string()],
+        None => script_command(&target, &host_target),
+    };
+
+    let written = write_entry(&file_name, &command, Some(&host_target), autostart_dir);
+
+    if written.is_err() {
+        _ = fs::remove_file(&target);
+    }
+
+    written
+}
+
+/// Adds an entry which runs the file at `host_path` where it is, through `interpreter` if given.
+pub fn import_reference(
+    host_path: &Path,
+    autostart_dir: &Path,
+    interpreter: Option<&str>,
+) -> Result<PathBuf, String> {
+    let name = host_path
+        .file_name()
+        .ok_or_else(|| fl!("new-script", "no-name"))?
+        .to_string_lossy()
+        .into_owned();
+
+    let command: Vec<String> = interpreter
+        .into_iter()
+        .map(String::from)
+        .chain([host_path.display().to_string()])
+        .collect();
+
+    write_entry(&name, &command, None, autostart_dir)
+}
+
+/// Adds an entry for an orphaned script, which then belongs to it again.
+pub fn adopt_orphan(script: &Path, autostart_dir: &Path) -> Result<PathBuf, String> {
+    let name = script
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    write_entry(&name, &script_command(script, script), Some(script), autostart_dir)
+}
+
+/// The command running the script at `path`, which the host sees at `host_path`.
+fn script_command(path: &Path, host_path: &Path) -> Vec<String> {
+    let host_path = host_path.display().to_string();
+
+    match is_directly_executable(path) {
+        true => vec![host_path],
+        // scripts without a #! line were always run by sh
+        false => vec!["sh".to_string(), host_path],
+    }
+}
+
+/// Whether the kernel can run a file itself, as a binary or a script with a `#!` line.
+fn is_directly_executable(path: &Path) -> bool {
+    let head = read_head(path, 4);
+
+    head.starts_with(b"#!") || head.starts_with(b"\x7fELF")
+}
+
+/// Reads up to `len` bytes from the start of a file.
+fn read_head(path: &Path, len: usize) -> Vec<u8> {
+    let mut head = Vec::with_capacity(len);
+    if let Ok(file) = fs::File::open(path) {
+        _ = io::Read::read_to_end(&mut io::Read::take(file, len as u64), &mut head);
+    }
+
+    head
+}
+
+/// Reads a script for editing in place.
+pub fn read_script(path: &Path) -> Result<String, String> {
+    let contents = fs::read(path).map_err(|why| why.to_string())?;
+
+    String::from_utf8(contents).map_err(|_| fl!("new-script", "not-text"))
+}
+
+/// Saves an edited script, keeping its permissions.
+pub fn save_script(path: &Path, contents: &str) -> Result<(), String> {
+    if !contents.starts_with("#!") {
+        return Err(fl!("new-script", "no-shebang"));
+    }
+
+    fs::write(path, contents).map_err(|why| why.to_string())
+}
+
+fn exists_error(why: io::Error, file_name: &str) -> String {
+    match why.kind() {
+        io::ErrorKind::AlreadyExists => fl!("new-script", "exists", name = file_name.to_string()),
+        _ => why.to_string(),
+    }
+}
+
+fn write_new(path: &Path, contents: &str, mode: u32) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(mode)
+        .open(path)?;
+
+    file.write_all(contents.as_bytes())
+}
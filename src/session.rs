@@ -0,0 +1,227 @@
+// SPDX-License-Identifier: GPL-3
+
+//! Works out which autostart entries a session would actually launch at login.
+
+use crate::apps::{find_executable, get_startup_applications, DirectoryType};
+use crate::desktops::{self, AutostartCondition};
+use crate::fl;
+use freedesktop_desktop_entry::DesktopEntry;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Why an autostart entry won't be launched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The entry sets `Hidden=true`
+    Hidden,
+
+    /// A user entry with the same file name replaces this system entry
+    OverriddenByUser,
+
+    /// An entry with the same file name in a directory earlier in `$XDG_CONFIG_DIRS` wins
+    Shadowed(PathBuf),
+
+    /// `X-GNOME-Autostart-enabled=false`
+    Disabled,
+
+    /// `OnlyShowIn`/`NotShowIn` exclude the session's desktops
+    NotForDesktop,
+
+    /// The `TryExec` binary can't be found
+    MissingBinary(String),
+
+    /// The `AutostartCondition` doesn't hold
+    ConditionNotMet(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    Launch,
+    Skip(SkipReason),
+}
+
+/// An autostart entry together with what would happen to it at login.
+#[derive(Debug, Clone)]
+pub struct AutostartEntry {
+    pub entry: DesktopEntry,
+    pub directory_type: DirectoryType,
+    pub decision: Decision,
+}
+
+impl SkipReason {
+    pub fn describe(&self) -> String {
+        match self {
+            SkipReason::Hidden => fl!("skip-reason", "hidden"),
+            SkipReason::OverriddenByUser => fl!("skip-reason", "overridden-by-user"),
+            SkipReason::Shadowed(path) => {
+                fl!("skip-reason", "shadowed", path = path.display().to_string())
+            }
+            SkipReason::Disabled => fl!("skip-reason", "disabled"),
+            SkipReason::NotForDesktop => fl!("skip-reason", "not-for-desktop"),
+            SkipReason::MissingBinary(binary) => {
+                fl!("skip-reason", "missing-binary", binary = binary.clone())
+            }
+            SkipReason::ConditionNotMet(condition) => {
+                fl!("skip-reason", "condition-not-met", condition = condition.clone())
+            }
+        }
+    }
+}
+
+/// Applies the autostart rules to the user and system entries, as a session running `desktops`
+/// would at login.
+///
+/// User entries replace system entries with the same file name, and amongst the system
+/// directories the one listed first in `$XDG_CONFIG_DIRS` wins.
+pub fn simulate_login(
+    user_apps: &[DesktopEntry],
+    system_apps: &[DesktopEntry],
+    desktops: &[String],
+) -> Vec<AutostartEntry> {
+    let system_dirs: Vec<PathBuf> = DirectoryType::System.into();
+
+    let user_names: HashMap<OsString, &DesktopEntry> = user_apps
+        .iter()
+        .filter_map(|entry| Some((entry.path.file_name()?.to_owned(), entry)))
+        .collect();
+
+    // the highest priority system entry for each file name
+    let mut winners: HashMap<OsString, &DesktopEntry> = HashMap::new();
+    for entry in system_apps {
+        let Some(file_name) = entry.path.file_name() else {
+            continue;
+        };
+
+        let priority = dir_priority(&system_dirs, &entry.path);
+        match winners.get(file_name) {
+            Some(current) if dir_priority(&system_dirs, &current.path) <= priority => {}
+            _ => {
+                winners.insert(file_name.to_owned(), entry);
+            }
+        }
+    }
+
+    let mut res = Vec::with_capacity(user_apps.len() + system_apps.len());
+
+    for entry in user_apps {
+        res.push(AutostartEntry {
+            entry: entry.clone(),
+            directory_type: DirectoryType::User,
+            decision: evaluate(entry, desktops),
+        });
+    }
+
+    for entry in system_apps {
+        let file_name = entry.path.file_name().unwrap_or_default();
+
+        let decision = if user_names.contains_key(file_name) {
+            Decision::Skip(SkipReason::OverriddenByUser)
+        } else {
+            match winners.get(file_name) {
+                Some(winner) if winner.path != entry.path => {
+                    Decision::Skip(SkipReason::Shadowed(winner.path.clone()))
+                }
+                _ => evaluate(entry, desktops),
+            }
+        };
+
+        res.push(AutostartEntry {
+            entry: entry.clone(),
+            directory_type: DirectoryType::System,
+            decision,
+        });
+    }
+
+    res
+}
+
+/// Checks a single entry's own keys, ignoring any other entries.
+pub fn evaluate(entry: &DesktopEntry, desktops: &[String]) -> Decision {
+    if is_true(entry.desktop_entry("Hidden")) {
+        return Decision::Skip(SkipReason::Hidden);
+    }
+
+    if entry
+        .desktop_entry("X-GNOME-Autostart-enabled")
+        .is_some_and(|enabled| enabled.trim().eq_ignore_ascii_case("false"))
+    {
+        return Decision::Skip(SkipReason::Disabled);
+    }
+
+    if !desktops::entry_shown_in(entry, desktops) {
+        return Decision::Skip(SkipReason::NotForDesktop);
+    }
+
+    if let Some(try_exec) = entry.desktop_entry("TryExec").map(str::trim) {
+        if !try_exec.is_empty() && find_executable(try_exec).is_none() {
+            return Decision::Skip(SkipReason::MissingBinary(try_exec.to_string()));
+        }
+    }
+
+    let condition = entry
+        .desktop_entry("AutostartCondition")
+        .or_else(|| entry.desktop_entry("X-GNOME-AutostartCondition"))
+        .and_then(AutostartCondition::parse);
+
+    if let Some(condition) = condition {
+        let config_dir = dirs::config_dir().unwrap_or_default();
+
+        // GSettings and other session specific conditions can't be checked from here
+        let holds = match &condition {
+            AutostartCondition::IfExists(path) => config_dir.join(path).exists(),
+            AutostartCondition::UnlessExists(path) => !config_dir.join(path).exists(),
+            _ => true,
+        };
+
+        if !holds {
+            return Decision::Skip(SkipReason::ConditionNotMet(condition.to_string()));
+        }
+    }
+
+    Decision::Launch
+}
+
+fn is_true(value: Option<&str>) -> bool {
+    value.is_some_and(|value| value.trim().eq_ignore_ascii_case("true"))
+}
+
+fn dir_priority(dirs: &[PathBuf], path: &Path) -> usize {
+    dirs.iter()
+        .position(|dir| path.parent() == Some(dir.as_path()))
+        .unwrap_or(dirs.len())
+}
+
+/// Prints the outcome of [`simulate_login`] for the command line, using the current session's
+/// desktops unless a colon separated list is given.
+pub fn print_simulation(desktop_list: Option<&str>) {
+    let locales = freedesktop_desktop_entry::get_languages_from_env();
+
+    let desktops = match desktop_list {
+        Some(desktop_list) => desktops::desktops_from_env(Some(desktop_list), None),
+        None => desktops::current_desktops(),
+    };
+
+    let user_apps = get_startup_applications(DirectoryType::User, locales.clone());
+    let system_apps = get_startup_applications(DirectoryType::System, locales.clone());
+
+    println!("{}", fl!("simulate-login", "cli-header", desktops = desktops.join(":")));
+
+    for autostart in simulate_login(&user_apps, &system_apps, &desktops) {
+        let name = autostart
+            .entry
+            .name(&locales)
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| autostart.entry.appid.clone());
+        let path = autostart.entry.path.display();
+
+        match autostart.decision {
+            Decision::Launch => println!("{}\t{name} ({path})", fl!("simulate-login", "launch")),
+            Decision::Skip(reason) => println!(
+                "{}\t{name} ({path}): {}",
+                fl!("simulate-login", "skip"),
+                reason.describe()
+            ),
+        }
+    }
+}
@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: GPL-3
+
+//! Snap packages: recognising their desktop entries, starting them with `snap run`, and the
+//! autostart directories inside each snap's own user data.
+//!
+//! Strictly confined snaps can't write to `~/.config/autostart`, so `snap userd --autostart`
+//! starts entries from `$SNAP_USER_DATA/.config/autostart` instead, which is
+//! `~/snap/<name>/<revision>` with `current` pointing at the active revision. The flatpak can't
+//! write there, so it leaves those directories alone and only offers snaps' exported entries.
+
+use crate::apps::host_path;
+use crate::entry;
+use freedesktop_desktop_entry::DesktopEntry;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where snapd exports the desktop entries of installed snaps.
+pub const SNAP_APPLICATIONS_DIR: &str = "/var/lib/snapd/desktop/applications";
+
+/// Where snapd puts the commands of installed snaps.
+const SNAP_BIN: &str = "/snap/bin";
+
+/// The name of the snap an entry belongs to, if it comes from one.
+pub fn snap_name(desktop_entry: &DesktopEntry) -> Option<String> {
+    if let Some(name) = desktop_entry.desktop_entry("X-SnapInstanceName") {
+        return Some(name.to_string());
+    }
+
+    // exported entries are named `<snap>_<app>.desktop`
+    if desktop_entry.path.starts_with(host_path(Path::new(SNAP_APPLICATIONS_DIR))) {
+        if let Some((name, _)) = desktop_entry.appid.split_once('_') {
+            return Some(name.to_string());
+        }
+    }
+
+    let (app, _) = snap_app(desktop_entry)?;
+    Some(app.split_once('.').map_or(app.as_str(), |(name, _)| name).to_string())
+}
+
+/// The entry's command rewritten as `snap run <snap>[.<app>] ...`, so that it keeps working when
+/// snapd regenerates or removes the exported entry.
+pub fn snap_run_command(desktop_entry: &DesktopEntry) -> Option<Vec<String>> {
+    let (app, args) = snap_app(desktop_entry)?;
+
+    Some(
+        ["snap", "run", &app]
+            .into_iter()
+            .map(String::from)
+            // a lone `%` would be taken for a field code
+            .chain(entry::strip_field_codes(&args).into_iter().map(|arg| arg.replace('%', "%%")))
+            .collect(),
+    )
+}
+
+/// The snap whose own autostart directory holds the entry at `path`.
+pub fn autostart_snap(path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(snaps_dir()).ok()?;
+
+    relative
+        .components()
+        .next()
+        .map(|name| name.as_os_str().to_string_lossy().into_owned())
+}
+
+/// Each installed snap's `$SNAP_USER_DATA/.config/autostart` which exists, or none in the flatpak.
+pub fn user_autostart_dirs() -> Vec<PathBuf> {
+    // entries added or removed there need write access to every snap's user data
+    if cfg!(feature = "flatpak") {
+        return Vec::new();
+    }
+
+    let Ok(snaps) = fs::read_dir(snaps_dir()) else {
+        return Vec::new();
+    };
+
+    let mut dirs: Vec<PathBuf> = snaps
+        .flatten()
+        .map(|snap| snap.path().join("current/.config/autostart"))
+        .filter(|dir| dir.is_dir())
+        .collect();
+
+    dirs.sort();
+    dirs
+}
+
+/// The `<snap>.<app>` an entry starts and its arguments, from `/snap/bin/<snap>.<app>` or
+/// `snap run <snap>.<app>`, looking past the `env BAMF_DESKTOP_FILE_HINT=...` snapd puts in front.
+fn snap_app(desktop_entry: &DesktopEntry) -> Option<(String, Vec<String>)> {
+    let (_, command) = entry::split_env(&entry::split_exec(desktop_entry.exec()?));
+
+    match command.as_slice() {
+        [program, args @ ..] if Path::new(program).parent() == Some(Path::new(SNAP_BIN)) => Some((
+            Path::new(program).file_name()?.to_string_lossy().into_owned(),
+            args.to_vec(),
+        )),
+        [program, run, app, args @ ..] if program.rsplit('/').next() == Some("snap") && run == "run" => {
+            Some((app.clone(), args.to_vec()))
+        }
+        _ => None,
+    }
+}
+
+fn snaps_dir() -> PathBuf {
+    dirs::home_dir().unwrap_or_default().join("snap")
+}
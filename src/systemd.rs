@@ -0,0 +1,507 @@
+// SPDX-License-Identifier: GPL-3
+
+//! systemd user units which start with the graphical session, and enabling/disabling them by
+//! managing their `.wants/` symlinks.
+
+use crate::apps::{host_command, host_path};
+use crate::entry;
+use freedesktop_desktop_entry::DesktopEntry;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Marks services generated from an autostart entry, naming the entry's file.
+const SOURCE_KEY: &str = "X-StartupConfiguration-Source";
+
+/// Marks autostart entries which were disabled because they now run as a service.
+pub const UNIT_KEY: &str = "X-StartupConfiguration-Unit";
+
+/// Targets which are reached when logging in to a graphical session.
+pub const SESSION_TARGETS: &[&str] = &["graphical-session.target", "default.target"];
+
+/// A unit wanted (or installable) by one of the [`SESSION_TARGETS`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserUnit {
+    /// Unit name, such as `foo.service`
+    pub name: String,
+
+    /// Path of the unit file, as seen by the host
+    pub path: PathBuf,
+
+    pub description: Option<String>,
+    pub exec_start: Option<String>,
+
+    /// Targets listed under `WantedBy=` in the `[Install]` section
+    pub wanted_by: Vec<String>,
+
+    /// `.wants/` symlinks which currently enable this unit
+    pub enabled_by: Vec<Enablement>,
+
+    /// For services converted from an autostart entry, the entry's file name
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Enablement {
+    pub target: String,
+
+    /// The symlink itself, as seen by the host
+    pub link: PathBuf,
+
+    /// Whether the symlink lives in the user's own configuration, and so can be removed
+    pub is_user: bool,
+}
+
+/// Where user units are looked up, highest priority first.
+#[derive(Debug, Clone)]
+pub struct UnitDirs {
+    /// `~/.config/systemd/user`, where the user's own units and symlinks go
+    pub user_config: PathBuf,
+
+    /// All directories, including `user_config`
+    pub search: Vec<PathBuf>,
+}
+
+impl UnitDirs {
+    pub fn from_system() -> Self {
+        // when running as a flatpak, XDG_CONFIG_HOME points into the sandbox, so use the host's
+        // directories under the home directory instead
+        #[cfg(feature = "flatpak")]
+        let (config_dir, data_dir) = {
+            let home = dirs::home_dir().expect("home dir not found");
+            (home.join(".config"), home.join(".local/share"))
+        };
+
+        #[cfg(not(feature = "flatpak"))]
+        let (config_dir, data_dir) = (
+            dirs::config_dir().expect("config dir not found"),
+            dirs::data_dir().expect("data dir not found"),
+        );
+
+        let user_config = config_dir.join("systemd/user");
+
+        let search = vec![
+            user_config.clone(),
+            PathBuf::from("/etc/systemd/user"),
+            data_dir.join("systemd/user"),
+            PathBuf::from("/usr/local/lib/systemd/user"),
+            PathBuf::from("/usr/lib/systemd/user"),
+        ];
+
+        UnitDirs {
+            user_config,
+            search,
+        }
+    }
+
+    /// Finds the unit file for `name`, following systemd's lookup order.
+    fn find_unit(&self, name: &str) -> Option<PathBuf> {
+        // instances use their own unit file if there is one, and the template's otherwise
+        std::iter::once(name.to_string())
+            .chain(template_name(name))
+            .flat_map(|file_name| self.search.iter().map(move |dir| dir.join(&file_name)))
+            .find(|path| fs::symlink_metadata(host_path(path)).is_ok())
+    }
+}
+
+/// Lists units which are enabled for, or can be installed into, the session targets.
+pub fn get_user_units(dirs: &UnitDirs) -> Vec<UserUnit> {
+    let mut seen = HashSet::new();
+    let mut units = Vec::new();
+
+    // enabled units, including instances of templates
+    for dir in &dirs.search {
+        for target in SESSION_TARGETS {
+            let Ok(links) = fs::read_dir(host_path(&dir.join(format!("{target}.wants")))) else {
+                continue;
+            };
+
+            for link in links.flatten() {
+                let Some(name) = link.file_name().to_str().map(String::from) else {
+                    continue;
+                };
+
+                if seen.insert(name.clone()) {
+                    if let Some(unit) = load_unit(dirs, &name) {
+                        units.push(unit);
+                    }
+                }
+            }
+        }
+    }
+
+    // units which would be wanted by a session target once enabled
+    for dir in &dirs.search {
+        let Ok(files) = fs::read_dir(host_path(dir)) else {
+            continue;
+        };
+
+        for file in files.flatten() {
+            let Some(name) = file.file_name().to_str().map(String::from) else {
+                continue;
+            };
+
+            if !name.ends_with(".service") || name.contains("@.") || seen.contains(&name) {
+                continue;
+            }
+
+            if let Some(unit) = load_unit(dirs, &name) {
+                if unit
+                    .wanted_by
+                    .iter()
+                    .any(|target| SESSION_TARGETS.contains(&target.as_str()))
+                {
+                    seen.insert(name);
+                    units.push(unit);
+                }
+            }
+        }
+    }
+
+    units.sort_by_key(|unit| unit.display_name().to_lowercase());
+    units
+}
+
+/// Loads a single unit by name.
+pub fn load_unit(dirs: &UnitDirs, name: &str) -> Option<UserUnit> {
+    let path = dirs.find_unit(name)?;
+
+    let contents = fs::read_to_string(host_path(&path)).ok()?;
+    // masked units are symlinks to /dev/null
+    if contents.trim().is_empty() {
+        return None;
+    }
+
+    let mut unit = UserUnit {
+        name: name.to_string(),
+        path,
+        description: None,
+        exec_start: None,
+        wanted_by: Vec::new(),
+        enabled_by: Vec::new(),
+        source: None,
+    };
+
+    let mut section = String::new();
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line[1..line.len() - 1].to_string();
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+
+        match (section.as_str(), key.trim()) {
+            ("Unit", "Description") => unit.description = Some(value.to_string()),
+            ("Unit", SOURCE_KEY) => unit.source = Some(value.to_string()),
+            ("Service", "ExecStart") if unit.exec_start.is_none() && !value.is_empty() => {
+                // drop the special executable prefixes (`-`, `@`, `:`, `+`, `!`)
+                let command = value.trim_start_matches(['-', '@', ':', '+', '!']);
+                unit.exec_start = Some(command.to_string());
+            }
+            ("Install", "WantedBy") => {
+                unit.wanted_by
+                    .extend(value.split_whitespace().map(String::from));
+            }
+            _ => {}
+        }
+    }
+
+    for dir in &dirs.search {
+        for target in SESSION_TARGETS {
+            let link = dir.join(format!("{target}.wants")).join(name);
+            if fs::symlink_metadata(host_path(&link)).is_ok() {
+                unit.enabled_by.push(Enablement {
+                    target: target.to_string(),
+                    link,
+                    is_user: *dir == dirs.user_config,
+                });
+            }
+        }
+    }
+
+    Some(unit)
+}
+
+impl UserUnit {
+    pub fn display_name(&self) -> &str {
+        self.description.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Whether the unit file lives in the user's own configuration.
+    pub fn is_user_unit(&self, dirs: &UnitDirs) -> bool {
+        self.path.starts_with(&dirs.user_config)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.enabled_by.is_empty()
+    }
+
+    /// Whether the unit is enabled from a system directory, which the user can't change.
+    pub fn is_enabled_system_wide(&self) -> bool {
+        self.enabled_by.iter().any(|enablement| !enablement.is_user)
+    }
+
+    /// The session target to enable this unit for.
+    fn install_target(&self) -> &str {
+        SESSION_TARGETS
+            .iter()
+            .find(|target| self.wanted_by.iter().any(|wanted| wanted == *target))
+            .unwrap_or(&SESSION_TARGETS[0])
+    }
+}
+
+/// Enables a unit by linking it into the user's `<target>.wants/` directory.
+pub fn enable_unit(dirs: &UnitDirs, unit: &UserUnit) -> io::Result<()> {
+    let wants = dirs.user_config.join(format!("{}.wants", unit.install_target()));
+    fs::create_dir_all(host_path(&wants))?;
+
+    let link = wants.join(&unit.name);
+    if fs::symlink_metadata(host_path(&link)).is_ok() {
+        return Ok(());
+    }
+
+    // units in the user's own directory are linked relatively, like `systemctl --user enable`
+    let target = match unit.path.strip_prefix(&dirs.user_config) {
+        Ok(relative) => PathBuf::from("..").join(relative),
+        Err(_) => unit.path.clone(),
+    };
+
+    std::os::unix::fs::symlink(target, host_path(&link))
+}
+
+/// Disables a unit by removing the user's `.wants/` symlinks to it.
+pub fn disable_unit(unit: &UserUnit) -> io::Result<()> {
+    for enablement in unit.enabled_by.iter().filter(|enablement| enablement.is_user) {
+        fs::remove_file(host_path(&enablement.link))?;
+    }
+
+    Ok(())
+}
+
+/// Has the user's systemd reload its units, so that it knows about units enabled or disabled
+/// since. They're started or left out from the next login.
+pub fn daemon_reload() -> io::Result<()> {
+    let status = host_command("systemctl").args(["--user", "daemon-reload"]).status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("systemctl --user daemon-reload exited with {status}")))
+    }
+}
+
+/// For an instance such as `foo@bar.service`, returns the template `foo@.service`.
+fn template_name(name: &str) -> Option<String> {
+    let (prefix, rest) = name.split_once('@')?;
+    let (_, suffix) = rest.rsplit_once('.')?;
+
+    Some(format!("{prefix}@.{suffix}"))
+}
+
+/// Turns an autostart entry into a user service which is part of the graphical session, enables
+/// it, and hides the original entry. Returns the name of the new unit.
+///
+/// `autostart_dir` is the user's autostart directory, where system entries are overridden.
+pub fn convert_entry_to_service(
+    dirs: &UnitDirs,
+    desktop_entry: &DesktopEntry,
+    locales: &[String],
+    autostart_dir: &Path,
+) -> io::Result<String> {
+    let file_name = desktop_entry
+        .path
+        .file_name()
+        .and_then(|file_name| file_name.to_str())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid entry file name"))?;
+
+    let stem = file_name.strip_suffix(".desktop").unwrap_or(file_name);
+    let unit_name = format!("app-{}.service", sanitize_unit_name(stem));
+    let unit_path = dirs.user_config.join(&unit_name);
+
+    if fs::symlink_metadata(host_path(&unit_path)).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", unit_path.display()),
+        ));
+    }
+
+    // system entries can't be edited, so they're hidden with a user entry of the same name. One
+    // the user already has is kept, and it's what runs
+    let user_entry = autostart_dir.join(file_name);
+    let user_override = fs::read_to_string(&user_entry)
+        .ok()
+        .filter(|_| desktop_entry.path != user_entry);
+
+    let exec = match &user_override {
+        Some(contents) => entry::get_key(contents, "Exec"),
+        None => desktop_entry.exec().map(String::from),
+    }
+    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "entry has no Exec"))?;
+
+    let description = desktop_entry
+        .name(locales)
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| stem.to_string());
+
+    let contents = format!(
+        "[Unit]
+Description={description}
+PartOf=graphical-session.target
+After=graphical-session.target
+{SOURCE_KEY}={file_name}
+
+[Service]
+ExecStart={}
+Restart=on-failure
+
+[Install]
+WantedBy=graphical-session.target
+",
+        exec_to_exec_start(&entry::split_exec(&exec))
+    );
+
+    fs::create_dir_all(host_path(&dirs.user_config))?;
+    fs::write(host_path(&unit_path), contents)?;
+
+    let unit = load_unit(dirs, &unit_name)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "written unit can't be loaded"))?;
+    enable_unit(dirs, &unit)?;
+
+    let mut changes = Vec::new();
+    if fs::symlink_metadata(&user_entry).is_err() {
+        fs::write(&user_entry, "[Desktop Entry]\nType=Application\n")?;
+        changes.extend([
+            ("Name", Some(description)),
+            ("Exec", Some(entry::join_exec(&entry::split_exec(&exec)))),
+        ]);
+    }
+    changes.extend([("Hidden", Some("true".into())), (UNIT_KEY, Some(unit_name.clone()))]);
+
+    entry::set_keys(&user_entry, &changes)?;
+
+    Ok(unit_name)
+}
+
+/// Undoes [`convert_entry_to_service`], or turns any user service into an autostart entry.
+///
+/// The service is disabled, and removed if it was generated from an entry.
+pub fn convert_service_to_entry(dirs: &UnitDirs, unit: &UserUnit, autostart_dir: &Path) -> io::Result<()> {
+    let exec_start = unit
+        .exec_start
+        .as_deref()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "unit has no ExecStart"))?;
+
+    let file_name = unit.source.clone().unwrap_or_else(|| {
+        let stem = unit.name.strip_suffix(".service").unwrap_or(&unit.name);
+        format!("{}.desktop", stem.strip_prefix("app-").unwrap_or(stem))
+    });
+    let entry_path = autostart_dir.join(&file_name);
+
+    // a literal % has to be doubled in Exec
+    let args: Vec<String> = split_exec_start(exec_start)
+        .into_iter()
+        .map(|arg| arg.replace('%', "%%"))
+        .collect();
+    let exec = entry::join_exec(&args);
+
+    let mut changes = Vec::new();
+    if !entry_path.exists() {
+        fs::write(&entry_path, "[Desktop Entry]\nType=Application\n")?;
+        changes.push(("Name", Some(unit.display_name().to_string())));
+    }
+    changes.extend([("Exec", Some(exec)), ("Hidden", None), (UNIT_KEY, None)]);
+
+    entry::set_keys(&entry_path, &changes)?;
+
+    disable_unit(unit)?;
+
+    if unit.source.is_some() && unit.is_user_unit(dirs) {
+        fs::remove_file(host_path(&unit.path))?;
+    }
+
+    Ok(())
+}
+
+fn sanitize_unit_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, ':' | '_' | '.' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Converts parsed `Exec` arguments into an `ExecStart=` value.
+///
+/// Field codes such as `%U` have no meaning at login and are dropped, and `%`/`$` are escaped
+/// so systemd doesn't treat them as specifiers or variables.
+pub fn exec_to_exec_start(args: &[String]) -> String {
+    entry::strip_field_codes(args)
+        .iter()
+        .map(|arg| {
+            let arg = arg.replace('%', "%%").replace('$', "$$");
+
+            if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | ';')) {
+                return arg;
+            }
+
+            format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Splits an `ExecStart=` value into arguments, undoing [`exec_to_exec_start`].
+pub fn split_exec_start(exec_start: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote: Option<char> = None;
+    let mut chars = exec_start.chars();
+
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', _) => {
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                    in_arg = true;
+                }
+            }
+            ('"' | '\'', None) => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            (c, Some(open)) if c == open => quote = None,
+            (c, None) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            _ => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+
+    if in_arg {
+        args.push(current);
+    }
+
+    args.into_iter()
+        .map(|arg| arg.replace("%%", "%").replace("$$", "$"))
+        .collect()
+}
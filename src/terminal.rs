@@ -0,0 +1,240 @@
+// SPDX-License-Identifier: GPL-3
+
+//! Runs autostart commands inside a terminal emulator, for scripts which ask for input or show
+//! output someone needs to see.
+//!
+//! Sessions handle `Terminal=true` in autostart entries inconsistently, so the terminal is put in
+//! front of the command instead, and the entry remembers which one in [`TERMINAL_KEY`].
+
+use crate::entry;
+use freedesktop_desktop_entry::DesktopEntry;
+
+/// Names the installed terminal an entry's command is wrapped in, by its application id.
+pub const TERMINAL_KEY: &str = "X-StartupConfiguration-Terminal";
+
+/// An installed terminal emulator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Terminal {
+    pub appid: String,
+    pub name: String,
+
+    /// The terminal's own command, without field codes
+    pub command: Vec<String>,
+
+    /// What goes between the terminal's command and the command it should run
+    pub separator: &'static [&'static str],
+}
+
+impl Terminal {
+    /// The command which opens this terminal running `command`.
+    pub fn wrap(&self, command: &[String]) -> Vec<String> {
+        self.command
+            .iter()
+            .cloned()
+            .chain(self.separator.iter().map(|arg| arg.to_string()))
+            .chain(command.iter().cloned())
+            .collect()
+    }
+
+    /// The command this terminal runs, if `command` opens it.
+    pub fn unwrap(&self, command: &[String]) -> Option<Vec<String>> {
+        let prefix = self.wrap(&[]);
+
+        command
+            .starts_with(&prefix)
+            .then(|| command[prefix.len()..].to_vec())
+            .filter(|command| !command.is_empty())
+    }
+}
+
+/// Finds the terminal emulators among the installed applications, sorted by name.
+pub fn detect(installed: &[DesktopEntry], locales: &[String]) -> Vec<Terminal> {
+    let mut terminals: Vec<Terminal> = installed
+        .iter()
+        .filter(|desktop_entry| {
+            desktop_entry.desktop_entry("Categories").is_some_and(|categories| {
+                categories.split(';').any(|category| category == "TerminalEmulator")
+            })
+        })
+        .filter_map(|desktop_entry| {
+            let command = entry::strip_field_codes(&entry::split_exec(desktop_entry.exec()?));
+            let separator = separator_for(&program_name(&desktop_entry.appid, &command), &command);
+
+            (!command.is_empty()).then(|| Terminal {
+                appid: desktop_entry.appid.clone(),
+                name: desktop_entry
+                    .name(locales)
+                    .map(|name| name.to_string())
+                    .unwrap_or_else(|| desktop_entry.appid.clone()),
+                command,
+                separator,
+            })
+        })
+        .collect();
+
+    terminals.sort_by_key(|terminal| terminal.name.to_lowercase());
+    terminals
+}
+
+/// Finds the terminal an entry's command is wrapped in, returning it with the command it runs.
+/// The terminal is `None` when it's no longer installed, but the command is still unwrapped.
+pub fn unwrap<'a>(
+    desktop_entry: &DesktopEntry,
+    command: &[String],
+    terminals: &'a [Terminal],
+) -> Option<(Option<&'a Terminal>, Vec<String>)> {
+    unwrap_with(desktop_entry.desktop_entry(TERMINAL_KEY)?, command, terminals)
+}
+
+fn unwrap_with<'a>(
+    appid: &str,
+    command: &[String],
+    terminals: &'a [Terminal],
+) -> Option<(Option<&'a Terminal>, Vec<String>)> {
+    let terminal = terminals.iter().find(|terminal| terminal.appid == appid);
+    if let Some(unwrapped) = terminal.and_then(|terminal| terminal.unwrap(command)) {
+        return Some((terminal, unwrapped));
+    }
+
+    // without the terminal's own entry, the command starts after its separator
+    let separator = separator_for(&program_name(appid, command), command);
+    let start = match separator {
+        [] => 1,
+        _ => {
+            command
+                .windows(separator.len())
+                .position(|window| window.iter().zip(separator).all(|(arg, separator)| arg == separator))?
+                + separator.len()
+        }
+    };
+
+    command
+        .get(start..)
+        .filter(|unwrapped| !unwrapped.is_empty())
+        .map(|unwrapped| (terminal, unwrapped.to_vec()))
+}
+
+/// The name of the program a terminal's command starts, looking past `flatpak run`.
+fn program_name(appid: &str, command: &[String]) -> String {
+    let program = command.first().map(String::as_str).unwrap_or_default();
+    let program = program.rsplit('/').next().unwrap_or(program);
+
+    if program != "flatpak" {
+        return program.to_string();
+    }
+
+    command
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--command="))
+        .map(|command| command.rsplit('/').next().unwrap_or(command).to_string())
+        .unwrap_or_else(|| appid.rsplit('.').next().unwrap_or(appid).to_lowercase())
+}
+
+/// How each terminal takes the command to run, with xterm's `-e` as the fallback most follow.
+fn separator_for(program: &str, command: &[String]) -> &'static [&'static str] {
+    match program {
+        "gnome-terminal" | "kgx" | "ptyxis" | "blackbox" => &["--"],
+        // its entry usually already opens a window with `wezterm start --cwd .`
+        "wezterm" if command.iter().any(|arg| arg == "start") => &["--"],
+        "wezterm" => &["start", "--"],
+        "xfce4-terminal" | "mate-terminal" | "terminator" | "sakura" => &["-x"],
+        "kitty" | "foot" | "footclient" => &[],
+        _ => &["-e"],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn terminal(appid: &str, command: &[&str]) -> Terminal {
+        let command = strings(command);
+
+        Terminal {
+            appid: appid.into(),
+            name: appid.into(),
+            separator: separator_for(&program_name(appid, &command), &command),
+            command,
+        }
+    }
+
+    #[test]
+    fn separators() {
+        assert_eq!(separator_for("gnome-terminal", &strings(&["gnome-terminal"])), ["--"]);
+        assert_eq!(separator_for("wezterm", &strings(&["wezterm", "start", "--cwd", "."])), ["--"]);
+        assert_eq!(separator_for("wezterm", &strings(&["wezterm"])), ["start", "--"]);
+        assert_eq!(separator_for("xfce4-terminal", &strings(&["xfce4-terminal"])), ["-x"]);
+        assert!(separator_for("kitty", &strings(&["kitty"])).is_empty());
+        assert_eq!(separator_for("konsole", &strings(&["konsole"])), ["-e"]);
+    }
+
+    #[test]
+    fn program_names() {
+        assert_eq!(program_name("org.gnome.Ptyxis", &strings(&["/usr/bin/ptyxis", "--new-window"])), "ptyxis");
+        assert_eq!(
+            program_name(
+                "org.wezfurlong.wezterm",
+                &strings(&["flatpak", "run", "--command=/app/bin/wezterm", "org.wezfurlong.wezterm"])
+            ),
+            "wezterm"
+        );
+        assert_eq!(
+            program_name("com.raggesilver.BlackBox", &strings(&["flatpak", "run", "com.raggesilver.BlackBox"])),
+            "blackbox"
+        );
+    }
+
+    #[test]
+    fn wrap_and_unwrap() {
+        let ptyxis = terminal("org.gnome.Ptyxis", &["ptyxis", "--new-window"]);
+        let kitty = terminal("kitty", &["kitty"]);
+        let command = strings(&["ssh-add", "-q"]);
+
+        let wrapped = ptyxis.wrap(&command);
+        assert_eq!(wrapped, ["ptyxis", "--new-window", "--", "ssh-add", "-q"]);
+        assert_eq!(ptyxis.unwrap(&wrapped), Some(command.clone()));
+        assert_eq!(kitty.unwrap(&kitty.wrap(&command)), Some(command.clone()));
+
+        // another terminal's command, or the terminal alone, isn't a wrapped command
+        assert_eq!(ptyxis.unwrap(&kitty.wrap(&command)), None);
+        assert_eq!(ptyxis.unwrap(&ptyxis.wrap(&[])), None);
+    }
+
+    #[test]
+    fn unwrap_installed_terminal() {
+        let terminals = [terminal("kitty", &["kitty"]), terminal("org.gnome.Ptyxis", &["ptyxis"])];
+        let wrapped = strings(&["ptyxis", "--", "ssh-add"]);
+
+        let (terminal, command) = unwrap_with("org.gnome.Ptyxis", &wrapped, &terminals).unwrap();
+        assert_eq!(terminal, Some(&terminals[1]));
+        assert_eq!(command, ["ssh-add"]);
+    }
+
+    #[test]
+    fn unwrap_uninstalled_terminal() {
+        let terminals = [terminal("kitty", &["kitty"])];
+
+        assert_eq!(
+            unwrap_with("org.gnome.Ptyxis", &strings(&["ptyxis", "--new-window", "--", "ssh-add"]), &terminals),
+            Some((None, strings(&["ssh-add"])))
+        );
+        assert_eq!(
+            unwrap_with("xterm", &strings(&["/usr/bin/xterm", "-e", "sh", "-c", "make"]), &[]),
+            Some((None, strings(&["sh", "-c", "make"])))
+        );
+        assert_eq!(
+            unwrap_with("foot", &strings(&["foot", "htop"]), &[]),
+            Some((None, strings(&["htop"])))
+        );
+        // a terminal whose command changed since falls back the same way
+        assert_eq!(
+            unwrap_with("kitty", &strings(&["/usr/bin/kitty", "htop"]), &terminals),
+            Some((Some(&terminals[0]), strings(&["htop"])))
+        );
+        assert_eq!(unwrap_with("org.gnome.Ptyxis", &strings(&["ssh-add"]), &terminals), None);
+    }
+}